@@ -27,9 +27,9 @@ fn main() {
     );
     wb.add_extrusion("Ext1", extrusion);
 
-    let realization = p.get_realization(0, 1000);
+    let realization = p.get_realization(0, 1000).unwrap();
     let solids = realization.solids;
-    let solid = &solids["Ext1:0"];
+    let solid = &solids["Extrusion-0:0"];
 
     println!("{:?}", solid);
 
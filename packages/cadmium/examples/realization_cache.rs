@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+use cadmium::{
+    extrusion::{Direction, Extrusion, ExtrusionMode},
+    message::Message,
+    project::Project,
+    sketch::Sketch,
+};
+
+// Times realizing a workbench after its last extrusion is edited, with and without the
+// per-step cache. Run it with: cargo run --release --example realization_cache
+fn main() {
+    let contents = std::fs::read_to_string("src/test_inputs/very_complex_sketch.cadmium").unwrap();
+    let file: serde_json::Value = serde_json::from_str(&contents).unwrap();
+
+    // this file was saved before sketches had plane descriptions, so only take the sketch
+    let sketch: Sketch =
+        serde_json::from_value(file["workbenches"][0]["history"][4]["data"]["sketch"].clone())
+            .unwrap();
+
+    let mut p = Project::new("Benchmark Project");
+    let wb = p.workbenches.get_mut(0).unwrap();
+    wb.add_sketch_to_plane("Sketch 1", "Plane-0");
+    *wb.get_sketch_mut("Sketch 1").unwrap() = sketch;
+
+    // give every face of the sketch its own extrusion
+    let face_count = wb.realize(1000).sketches["Sketch-0"].1.faces.len() as u64;
+    for face_id in 0..face_count {
+        let extrusion = Extrusion::new(
+            "Sketch-0".to_owned(),
+            vec![face_id],
+            10.0 + face_id as f64,
+            0.0,
+            Direction::Normal,
+            ExtrusionMode::New,
+        );
+        wb.add_extrusion(&format!("Ext{}", face_id), extrusion);
+    }
+    let last_extrusion = format!("Ext{}", face_count - 1);
+
+    let start = Instant::now();
+    let realization = p.get_realization(0, 1000).unwrap();
+    println!(
+        "Realized {} faces into {} solids in {:?}",
+        face_count,
+        realization.solids.len(),
+        start.elapsed()
+    );
+
+    let runs = 10;
+    let mut cached = Duration::ZERO;
+    let mut uncached = Duration::ZERO;
+    for run in 0..runs {
+        let edit = Message::UpdateExtrusionLength {
+            workbench_id: 0,
+            extrusion_name: last_extrusion.clone(),
+            length: 20.0 + run as f64,
+        };
+
+        edit.handle(&mut p).unwrap();
+        let start = Instant::now();
+        p.get_realization(0, 1000).unwrap();
+        cached += start.elapsed();
+
+        edit.handle(&mut p).unwrap();
+        p.workbenches[0].clear_cache();
+        let start = Instant::now();
+        p.get_realization(0, 1000).unwrap();
+        uncached += start.elapsed();
+    }
+
+    println!(
+        "After editing the last extrusion: {:?} with the cache, {:?} without",
+        cached / runs,
+        uncached / runs
+    );
+}
@@ -3,13 +3,16 @@ use serde::{Deserialize, Serialize};
 use truck_modeling::Plane as TruckPlane;
 use truck_modeling::InnerSpace;
 
+use crate::datum::FaceReference;
 use crate::sketch::Point2;
 
 #[derive(Tsify, Debug, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum PlaneDescription {
     PlaneId(String),
+    // kept so that older projects still load; new sketches should use Face
     SolidFace { solid_id: String, normal: Vector3 },
+    Face(FaceReference),
 }
 
 #[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
@@ -95,13 +98,37 @@ impl Plane {
     }
 
     pub fn unproject(&self, point: &Point2) -> Point3 {
-        let x = self.origin.plus(self.primary.times(point.x));
-        let y = self.origin.plus(self.secondary.times(point.y));
-        x.plus(y).to_point3()
+        self.origin
+            .plus(self.primary.times(point.x))
+            .plus(self.secondary.times(point.y))
+            .to_point3()
+    }
+
+    // Builds a plane from just an origin and a normal. The in-plane axes are chosen
+    // so that the normals of Top, Front and Right give back exactly those planes
+    pub fn from_normal(origin: Point3, normal: Vector3) -> Self {
+        let normal = normal.normalize();
+        let up = if normal.z.abs() < 0.9 {
+            Vector3::new(0.0, 0.0, 1.0)
+        } else {
+            Vector3::new(0.0, 1.0, 0.0)
+        };
+        let primary = up.cross(&normal).normalize();
+        let secondary = normal.cross(&primary);
+        Plane {
+            origin,
+            primary,
+            secondary,
+            tertiary: normal,
+        }
+    }
+
+    pub fn distance_to(&self, point: &Point3) -> f64 {
+        point.minus(&self.origin).dot(&self.tertiary)
     }
 }
 
-#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Vector3 {
     pub x: f64,
@@ -137,9 +164,46 @@ impl Vector3 {
     pub fn dot(&self, other: &Vector3) -> f64 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
+
+    pub fn minus(&self, v: &Vector3) -> Self {
+        Self {
+            x: self.x - v.x,
+            y: self.y - v.y,
+            z: self.z - v.z,
+        }
+    }
+
+    pub fn cross(&self, other: &Vector3) -> Self {
+        Self {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return self.clone();
+        }
+        self.times(1.0 / length)
+    }
+
+    // Rodrigues' rotation of this vector about a unit axis
+    pub fn rotate_about(&self, axis: &Vector3, angle: f64) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+        self.times(cos)
+            .plus(axis.cross(self).times(sin))
+            .plus(axis.times(axis.dot(self) * (1.0 - cos)))
+    }
 }
 
-#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Point3 {
     pub x: f64,
@@ -0,0 +1,436 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use truck_modeling::{builder, Matrix4};
+use truck_stepio::out;
+
+use crate::archetypes::{Point3, Vector3};
+use crate::error::CADmiumError;
+use crate::export;
+use crate::interference::{self, InterferenceReport};
+use crate::mates::{Mate, MateKind};
+use crate::project::Project;
+use crate::solid::Solid;
+
+// A rigid placement: rotate about the origin, then translate
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Transform {
+    // the rows of the rotation matrix
+    pub rotation: [[f64; 3]; 3],
+    pub translation: [f64; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn from_translation(v: &Vector3) -> Self {
+        Transform {
+            translation: [v.x, v.y, v.z],
+            ..Transform::identity()
+        }
+    }
+
+    // A rotation of `angle` radians about an axis through the origin
+    pub fn from_axis_angle(axis: &Vector3, angle: f64) -> Self {
+        let columns = [
+            Vector3::new(1.0, 0.0, 0.0).rotate_about(axis, angle),
+            Vector3::new(0.0, 1.0, 0.0).rotate_about(axis, angle),
+            Vector3::new(0.0, 0.0, 1.0).rotate_about(axis, angle),
+        ];
+        let mut rotation = [[0.0; 3]; 3];
+        for (c, column) in columns.iter().enumerate() {
+            rotation[0][c] = column.x;
+            rotation[1][c] = column.y;
+            rotation[2][c] = column.z;
+        }
+        Transform {
+            rotation,
+            translation: [0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn apply_vector(&self, v: &Vector3) -> Vector3 {
+        let r = &self.rotation;
+        Vector3::new(
+            r[0][0] * v.x + r[0][1] * v.y + r[0][2] * v.z,
+            r[1][0] * v.x + r[1][1] * v.y + r[1][2] * v.z,
+            r[2][0] * v.x + r[2][1] * v.y + r[2][2] * v.z,
+        )
+    }
+
+    pub fn apply_point(&self, p: &Point3) -> Point3 {
+        let rotated = self.apply_vector(&Vector3::new(p.x, p.y, p.z));
+        Point3::new(
+            rotated.x + self.translation[0],
+            rotated.y + self.translation[1],
+            rotated.z + self.translation[2],
+        )
+    }
+
+    // The transform that applies `inner` first and then this one
+    pub fn compose(&self, inner: &Transform) -> Transform {
+        let rotation = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                (0..3)
+                    .map(|k| self.rotation[i][k] * inner.rotation[k][j])
+                    .sum()
+            })
+        });
+        let t = &inner.translation;
+        let moved = self.apply_point(&Point3::new(t[0], t[1], t[2]));
+        Transform {
+            rotation,
+            translation: [moved.x, moved.y, moved.z],
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        let inverse = Transform {
+            rotation: std::array::from_fn(|i| std::array::from_fn(|j| self.rotation[j][i])),
+            translation: [0.0, 0.0, 0.0],
+        };
+        let t = &self.translation;
+        let back = inverse.apply_vector(&Vector3::new(-t[0], -t[1], -t[2]));
+        Transform {
+            translation: [back.x, back.y, back.z],
+            ..inverse
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let r = &self.rotation;
+        let t = &self.translation;
+        // cgmath matrices are built column by column
+        Matrix4::new(
+            r[0][0], r[1][0], r[2][0], 0.0, //
+            r[0][1], r[1][1], r[2][1], 0.0, //
+            r[0][2], r[1][2], r[2][2], 0.0, //
+            t[0], t[1], t[2], 1.0,
+        )
+    }
+}
+
+// What an instance places: a part, which is a workbench, or another assembly
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum Component {
+    Part { workbench_id: u64 },
+    Assembly { assembly_id: u64 },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Instance {
+    pub id: String,
+    pub name: String,
+    pub component: Component,
+    // from the component's coordinates to the assembly's
+    pub transform: Transform,
+}
+
+#[derive(Tsify, Debug, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Assembly {
+    #[serde(default)]
+    pub(crate) id: u64,
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) instances: Vec<Instance>,
+    #[serde(default)]
+    pub(crate) instance_counter: u64,
+    #[serde(default)]
+    pub(crate) mates: Vec<Mate>,
+    #[serde(default)]
+    pub(crate) mate_counter: u64,
+}
+
+impl Assembly {
+    pub fn new(name: &str) -> Self {
+        Assembly {
+            id: 0,
+            name: name.to_owned(),
+            instances: vec![],
+            instance_counter: 0,
+            mates: vec![],
+            mate_counter: 0,
+        }
+    }
+
+    pub fn add_instance(
+        &mut self,
+        name: &str,
+        component: Component,
+        transform: Transform,
+    ) -> String {
+        let id = format!("Instance-{}", self.instance_counter);
+        self.instance_counter += 1;
+        self.instances.push(Instance {
+            id: id.clone(),
+            name: name.to_owned(),
+            component,
+            transform,
+        });
+        id
+    }
+
+    pub fn get_instance(&self, instance_id: &str) -> Result<&Instance, CADmiumError> {
+        self.instances
+            .iter()
+            .find(|instance| instance.id == instance_id)
+            .ok_or(CADmiumError::InstanceNotFound(instance_id.to_owned()))
+    }
+
+    pub fn get_instance_mut(&mut self, instance_id: &str) -> Result<&mut Instance, CADmiumError> {
+        self.instances
+            .iter_mut()
+            .find(|instance| instance.id == instance_id)
+            .ok_or(CADmiumError::InstanceNotFound(instance_id.to_owned()))
+    }
+
+    pub fn remove_instance(&mut self, instance_id: &str) -> Result<(), CADmiumError> {
+        let index = self
+            .instances
+            .iter()
+            .position(|instance| instance.id == instance_id)
+            .ok_or(CADmiumError::InstanceNotFound(instance_id.to_owned()))?;
+        self.instances.remove(index);
+        self.remove_dangling_mates();
+        Ok(())
+    }
+
+    pub fn add_mate(&mut self, name: &str, kind: MateKind) -> String {
+        let id = format!("Mate-{}", self.mate_counter);
+        self.mate_counter += 1;
+        self.mates.push(Mate {
+            id: id.clone(),
+            name: name.to_owned(),
+            kind,
+        });
+        id
+    }
+
+    pub fn remove_mate(&mut self, mate_id: &str) -> Result<(), CADmiumError> {
+        let index = self
+            .mates
+            .iter()
+            .position(|mate| mate.id == mate_id)
+            .ok_or(CADmiumError::MateNotFound(mate_id.to_owned()))?;
+        self.mates.remove(index);
+        Ok(())
+    }
+
+    // Drops the mates on instances that are gone
+    pub(crate) fn remove_dangling_mates(&mut self) {
+        let instances = &self.instances;
+        self.mates.retain(|mate| {
+            mate.kind
+                .instance_ids()
+                .iter()
+                .all(|id| instances.iter().any(|instance| instance.id == *id))
+        });
+    }
+}
+
+// One solid of one part, moved to where its instance puts it in the top-level assembly
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PlacedSolid {
+    // the instance IDs from the top-level assembly down to the part
+    pub instance_path: Vec<String>,
+    pub workbench_id: u64,
+    pub solid_id: String,
+    pub transform: Transform,
+    pub solid: Solid,
+}
+
+// Every solid in an assembly, including those of its sub-assemblies, in place
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RealAssembly {
+    pub assembly_id: u64,
+    pub solids: Vec<PlacedSolid>,
+}
+
+impl RealAssembly {
+    pub fn new(project: &Project, assembly_id: u64) -> Result<Self, CADmiumError> {
+        let mut real = RealAssembly {
+            assembly_id,
+            solids: vec![],
+        };
+        real.place(project, assembly_id, &Transform::identity(), &mut vec![])?;
+        Ok(real)
+    }
+
+    fn place(
+        &mut self,
+        project: &Project,
+        assembly_id: u64,
+        transform: &Transform,
+        path: &mut Vec<String>,
+    ) -> Result<(), CADmiumError> {
+        let assembly = project.get_assembly_by_id(assembly_id)?;
+        for instance in assembly.instances.iter() {
+            let placement = transform.compose(&instance.transform);
+            path.push(instance.id.clone());
+
+            match &instance.component {
+                Component::Part { workbench_id } => {
+                    let wb = project.get_workbench_by_id(*workbench_id)?;
+                    let realization = wb.realize(wb.history.len() as u64);
+                    let mut solid_ids: Vec<&String> = realization.solids.keys().collect();
+                    solid_ids.sort();
+
+                    for solid_id in solid_ids {
+                        let original = &realization.solids[solid_id];
+                        let moved =
+                            builder::transformed(&original.truck_solid, placement.to_matrix());
+                        let mut solid = Solid::from_truck_solid(original.name.clone(), moved);
+                        solid.face_names = original.face_names.clone();
+                        solid.material = original.material.clone();
+
+                        self.solids.push(PlacedSolid {
+                            instance_path: path.clone(),
+                            workbench_id: *workbench_id,
+                            solid_id: solid_id.clone(),
+                            transform: placement.clone(),
+                            solid,
+                        });
+                    }
+                }
+                Component::Assembly { assembly_id } => {
+                    self.place(project, *assembly_id, &placement, path)?;
+                }
+            }
+
+            path.pop();
+        }
+        Ok(())
+    }
+
+    // Every placed solid, named by its instance path and solid ID
+    fn bodies(&self) -> Vec<(String, &Solid)> {
+        self.solids
+            .iter()
+            .map(|placed| {
+                let name = format!("{}/{}", placed.instance_path.join("/"), placed.solid_id);
+                (name, &placed.solid)
+            })
+            .collect()
+    }
+
+    pub fn interference(&self, tolerance: f64) -> InterferenceReport {
+        interference::check(&self.bodies(), tolerance)
+    }
+
+    pub fn to_glb(&self) -> Vec<u8> {
+        export::to_glb(&self.bodies())
+    }
+
+    pub fn to_3mf(&self) -> Vec<u8> {
+        export::to_3mf(&self.bodies())
+    }
+
+    // All of the solids as one STEP file, one body each
+    pub fn to_step_string(&self) -> String {
+        let compressed: Vec<_> = self
+            .solids
+            .iter()
+            .map(|placed| placed.solid.truck_solid.compress())
+            .collect();
+        out::CompleteStepDisplay::new(
+            out::StepModels::from_iter(compressed.iter()),
+            out::StepHeaderDescriptor {
+                organization_system: "cadmium-assembly-to-step".to_owned(),
+                ..Default::default()
+            },
+        )
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbench::tests::block_with_hole;
+
+    use super::*;
+
+    fn min_x(solid: &Solid) -> f64 {
+        solid.vertices.iter().map(|v| v.x).fold(f64::MAX, f64::min)
+    }
+
+    #[test]
+    fn nested_instances_are_placed_and_exported_together() {
+        let mut p = Project::new("Test Project");
+        let part = p.add_workbench(block_with_hole());
+
+        let sub = p.add_assembly("Pair");
+        let offset = Transform::from_translation(&Vector3::new(100.0, 0.0, 0.0));
+        let assembly = p.get_assembly_by_id_mut(sub).unwrap();
+        assembly.add_instance(
+            "Left",
+            Component::Part { workbench_id: part },
+            Transform::identity(),
+        );
+        assembly.add_instance("Right", Component::Part { workbench_id: part }, offset);
+
+        let top = p.add_assembly("Top");
+        let lifted = Transform::from_translation(&Vector3::new(0.0, 0.0, 50.0));
+        p.add_instance(
+            top,
+            "Pair",
+            Component::Assembly { assembly_id: sub },
+            lifted,
+        )
+        .unwrap();
+
+        let real = RealAssembly::new(&p, top).unwrap();
+        assert_eq!(real.solids.len(), 2);
+        assert_eq!(
+            real.solids[1].instance_path,
+            vec!["Instance-0", "Instance-1"]
+        );
+        let shift = min_x(&real.solids[1].solid) - min_x(&real.solids[0].solid);
+        assert!((shift - 100.0).abs() < 1e-6);
+
+        let step = real.to_step_string();
+        assert_eq!(step.matches("MANIFOLD_SOLID_BREP").count(), 2);
+
+        // an assembly can't end up inside itself
+        assert!(p
+            .add_instance(
+                sub,
+                "Loop",
+                Component::Assembly { assembly_id: top },
+                Transform::identity()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn transforms_compose_and_invert() {
+        let turn =
+            Transform::from_axis_angle(&Vector3::new(0.0, 0.0, 1.0), std::f64::consts::FRAC_PI_2);
+        let shift = Transform::from_translation(&Vector3::new(1.0, 0.0, 0.0));
+
+        // turn first, then shift
+        let both = shift.compose(&turn);
+        let p = both.apply_point(&Point3::new(1.0, 0.0, 0.0));
+        assert!((p.x - 1.0).abs() < 1e-12 && (p.y - 1.0).abs() < 1e-12);
+
+        let back = both.inverse().apply_point(&p);
+        assert!((back.x - 1.0).abs() < 1e-12 && back.y.abs() < 1e-12);
+    }
+}
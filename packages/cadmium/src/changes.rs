@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::project::Project;
+use crate::realization::canonical_hash;
+use crate::step::StepData;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+#[derive(Tsify, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    // Compares two lists of (ID, fingerprint). Added and modified IDs come in the order of
+    // `after`, removed ones in the order of `before`
+    fn between(before: &[(String, u64)], after: &[(String, u64)]) -> Self {
+        let old: HashMap<&str, u64> = before.iter().map(|(id, f)| (id.as_str(), *f)).collect();
+        let new: HashMap<&str, u64> = after.iter().map(|(id, f)| (id.as_str(), *f)).collect();
+
+        let mut changes = Changes::default();
+        for (id, fingerprint) in after.iter() {
+            match old.get(id.as_str()) {
+                None => changes.added.push(id.clone()),
+                Some(previous) if previous != fingerprint => changes.modified.push(id.clone()),
+                Some(_) => {}
+            }
+        }
+        for (id, _) in before.iter() {
+            if !new.contains_key(id.as_str()) {
+                changes.removed.push(id.clone());
+            }
+        }
+        changes
+    }
+}
+
+// What changed inside one workbench
+#[derive(Tsify, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct WorkbenchChanges {
+    pub workbench_id: u64,
+    pub steps: Changes,
+    // sketch step ID -> its points, lines, circles, arcs and constraints, like "Point-3"
+    pub sketch_entities: HashMap<String, Changes>,
+    pub solids: Changes,
+}
+
+// Everything a message changed, so clients can update what they show instead of
+// fetching the whole project and realization again
+#[derive(Tsify, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ChangeSet {
+    // workbench IDs
+    pub workbenches: Changes,
+    // one entry for each workbench that was added or modified
+    pub details: Vec<WorkbenchChanges>,
+}
+
+impl ChangeSet {
+    pub fn between(before: &ProjectSnapshot, after: &ProjectSnapshot) -> Self {
+        let fingerprints = |snapshot: &ProjectSnapshot| -> Vec<(String, u64)> {
+            snapshot
+                .workbenches
+                .iter()
+                .map(|wb| (wb.id.to_string(), wb.fingerprint))
+                .collect()
+        };
+        let workbenches = Changes::between(&fingerprints(before), &fingerprints(after));
+
+        let details = after
+            .workbenches
+            .iter()
+            .filter(|wb| {
+                let id = wb.id.to_string();
+                workbenches.added.contains(&id) || workbenches.modified.contains(&id)
+            })
+            .map(|new| {
+                let empty = WorkbenchSnapshot::default();
+                let old = before
+                    .workbenches
+                    .iter()
+                    .find(|old| old.id == new.id)
+                    .unwrap_or(&empty);
+                new.changes_since(old)
+            })
+            .collect();
+
+        ChangeSet {
+            workbenches,
+            details,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workbenches.is_empty()
+    }
+}
+
+// A fingerprint of everything in a project that a client might be showing
+#[derive(Debug, Clone, Default)]
+pub struct ProjectSnapshot {
+    workbenches: Vec<WorkbenchSnapshot>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct WorkbenchSnapshot {
+    id: u64,
+    // covers the name and every step, so it changes whenever anything below does
+    fingerprint: u64,
+    steps: Vec<(String, u64)>,
+    sketch_entities: Vec<(String, Vec<(String, u64)>)>,
+    solids: Vec<(String, u64)>,
+}
+
+impl ProjectSnapshot {
+    // Solids are read from each workbench's realization cache, so they include any that
+    // changed because an earlier step did while only the steps that changed are redone
+    pub fn new(project: &Project) -> Self {
+        let workbenches = project
+            .workbenches
+            .iter()
+            .map(|wb| {
+                let steps: Vec<(String, u64)> = wb
+                    .history
+                    .iter()
+                    .map(|step| (step.unique_id.clone(), fingerprint(step)))
+                    .collect();
+
+                let sketch_entities = wb
+                    .history
+                    .iter()
+                    .filter_map(|step| match &step.data {
+                        StepData::Sketch { sketch, .. } => {
+                            let mut entities = vec![];
+                            entities.extend(entries("Point", &sketch.points));
+                            entities.extend(entries("Line", &sketch.line_segments));
+                            entities.extend(entries("Circle", &sketch.circles));
+                            entities.extend(entries("Arc", &sketch.arcs));
+                            entities.extend(entries("Constraint", &sketch.constraints));
+                            Some((step.unique_id.clone(), entities))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                let mut solids: Vec<(String, u64)> = wb
+                    .solid_checksums()
+                    .into_iter()
+                    .map(|(id, crc32)| {
+                        let material = wb.material_for(&id);
+                        (id, fingerprint(&(&crc32, &material)))
+                    })
+                    .collect();
+                solids.sort();
+
+                let mut hasher = DefaultHasher::new();
+                wb.name.hash(&mut hasher);
+                steps.hash(&mut hasher);
+                solids.hash(&mut hasher);
+
+                WorkbenchSnapshot {
+                    id: wb.id,
+                    fingerprint: hasher.finish(),
+                    steps,
+                    sketch_entities,
+                    solids,
+                }
+            })
+            .collect();
+
+        ProjectSnapshot { workbenches }
+    }
+}
+
+impl WorkbenchSnapshot {
+    fn changes_since(&self, old: &WorkbenchSnapshot) -> WorkbenchChanges {
+        let mut sketch_entities = HashMap::new();
+        for (sketch_id, entities) in self.sketch_entities.iter() {
+            let previous = old
+                .sketch_entities
+                .iter()
+                .find(|(id, _)| id == sketch_id)
+                .map(|(_, entities)| entities.as_slice())
+                .unwrap_or(&[]);
+            let changes = Changes::between(previous, entities);
+            if !changes.is_empty() {
+                sketch_entities.insert(sketch_id.clone(), changes);
+            }
+        }
+        for (sketch_id, entities) in old.sketch_entities.iter() {
+            if !self.sketch_entities.iter().any(|(id, _)| id == sketch_id) {
+                sketch_entities.insert(sketch_id.clone(), Changes::between(entities, &[]));
+            }
+        }
+
+        WorkbenchChanges {
+            workbench_id: self.id,
+            steps: Changes::between(&old.steps, &self.steps),
+            sketch_entities,
+            solids: Changes::between(&old.solids, &self.solids),
+        }
+    }
+}
+
+// Hashed without depending on map order, so a restored project compares equal to the original
+fn fingerprint<T: Serialize>(value: &T) -> u64 {
+    canonical_hash(value)
+}
+
+// Fingerprints one kind of sketch entity, in ID order
+fn entries<T: Serialize>(kind: &str, entities: &HashMap<u64, T>) -> Vec<(String, u64)> {
+    let mut ids: Vec<&u64> = entities.keys().collect();
+    ids.sort();
+    ids.into_iter()
+        .map(|id| (format!("{}-{}", kind, id), fingerprint(&entities[id])))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::Message;
+
+    use super::*;
+
+    #[test]
+    fn change_sets_list_what_a_message_touched() {
+        let mut p = Project::new("Test Project");
+        Message::NewSketchOnPlane {
+            workbench_id: 0,
+            sketch_name: "Sketch 1".to_owned(),
+            plane_id: "Plane-0".to_owned(),
+        }
+        .handle(&mut p)
+        .unwrap();
+
+        let (result, changes) = Message::NewPointOnSketch2 {
+            workbench_id: 0,
+            sketch_id: "Sketch-0".to_owned(),
+            x: 0.0,
+            y: 0.0,
+            hidden: false,
+        }
+        .handle_with_changes(&mut p);
+        result.unwrap();
+
+        assert_eq!(changes.workbenches.modified, vec!["0"]);
+        let wb = &changes.details[0];
+        assert_eq!(wb.steps.modified, vec!["Sketch-0"]);
+        assert_eq!(wb.sketch_entities["Sketch-0"].added, vec!["Point-1"]);
+        assert!(wb.solids.is_empty());
+
+        // a message that fails changes nothing
+        let (result, changes) = Message::DeleteStep {
+            workbench_id: 0,
+            step_name: "Nothing".to_owned(),
+            cascade: false,
+            dry_run: false,
+        }
+        .handle_with_changes(&mut p);
+        assert!(result.is_err());
+        assert!(changes.is_empty());
+
+        // nor does putting the project back as it was, whatever order its maps come back in
+        let before = ProjectSnapshot::new(&p);
+        let json = p.json();
+        p.restore(&json).unwrap();
+        assert!(ChangeSet::between(&before, &ProjectSnapshot::new(&p)).is_empty());
+    }
+}
@@ -0,0 +1,633 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::archetypes::{Plane, Point3};
+use crate::error::CADmiumError;
+use crate::geometry::{
+    closest_point_on_polyline, closest_points_between_lines, fit_circle, fit_cylinder, fit_line,
+    line_plane_intersection, plane_plane_intersection, polyline_midpoint, InfiniteLine,
+};
+use crate::naming::dependencies_in_name;
+use crate::project::RealSketch;
+use crate::realization::Realization;
+use crate::sketch::{arc_to_points, Point2, SketchFeatureType};
+use crate::solid::{face_plane, EdgePolyline, FaceMesh, Solid};
+use crate::step::Dependency;
+
+// The tessellation tolerance used when datums need to look at solid geometry
+pub const DATUM_TOLERANCE: f64 = 0.01;
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PointReference {
+    // a Point step, like the Origin
+    Point {
+        point_id: String,
+    },
+    SketchPoint {
+        sketch_id: String,
+        point_id: u64,
+    },
+    // solid geometry is referenced by its persistent name, see naming.rs
+    Vertex {
+        solid_id: String,
+        vertex_name: String,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum EdgeReference {
+    SketchLine { sketch_id: String, line_id: u64 },
+    SketchArc { sketch_id: String, arc_id: u64 },
+    SketchCircle { sketch_id: String, circle_id: u64 },
+    SolidEdge { solid_id: String, edge_name: String },
+    // an Axis step, treated as a straight edge through its origin
+    Axis { axis_id: String },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct FaceReference {
+    pub solid_id: String,
+    pub face_name: String,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PlaneReference {
+    Plane { plane_id: String },
+    Face { face: FaceReference },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PlaneDefinition {
+    Offset {
+        reference: PlaneReference,
+        distance: f64,
+    },
+    // rotate the reference plane about a straight edge by angle (radians)
+    Angle {
+        reference: PlaneReference,
+        axis: EdgeReference,
+        angle: f64,
+    },
+    ThreePoints {
+        p0: PointReference,
+        p1: PointReference,
+        p2: PointReference,
+    },
+    // tangent to the cylinder along the line closest to `near`
+    TangentToCylinder {
+        face: FaceReference,
+        near: PointReference,
+    },
+    // perpendicular to the curve, through the point on the curve closest to `at`
+    NormalToCurve {
+        curve: EdgeReference,
+        at: PointReference,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum AxisDefinition {
+    TwoPoints {
+        p0: PointReference,
+        p1: PointReference,
+    },
+    // a sketch line or any other straight edge
+    Edge {
+        edge: EdgeReference,
+    },
+    CylinderAxis {
+        face: FaceReference,
+    },
+    PlaneIntersection {
+        plane_a: PlaneReference,
+        plane_b: PlaneReference,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PointDefinition {
+    Midpoint {
+        edge: EdgeReference,
+    },
+    // the center of an arc, circle, or circular solid edge
+    Center {
+        edge: EdgeReference,
+    },
+    // where two straight edges cross
+    EdgeIntersection {
+        edge_a: EdgeReference,
+        edge_b: EdgeReference,
+    },
+    // where a straight edge pierces a plane
+    EdgePlaneIntersection {
+        edge: EdgeReference,
+        plane: PlaneReference,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RealAxis {
+    pub axis: InfiniteLine,
+    pub length: f64,
+    pub name: String,
+}
+
+fn get_solid<'a>(realized: &'a Realization, solid_id: &str) -> Result<&'a Solid, CADmiumError> {
+    realized
+        .solids
+        .get(solid_id)
+        .ok_or(CADmiumError::SolidNotFound(solid_id.to_owned()))
+}
+
+fn get_sketch<'a>(
+    realized: &'a Realization,
+    sketch_id: &str,
+) -> Result<&'a RealSketch, CADmiumError> {
+    realized
+        .sketches
+        .get(sketch_id)
+        .map(|(sketch, _split_sketch, _name)| sketch)
+        .ok_or(CADmiumError::StepIDNotFound(sketch_id.to_owned()))
+}
+
+fn sketch_point(sketch: &RealSketch, point_id: u64) -> Result<Point3, CADmiumError> {
+    sketch
+        .points
+        .get(&point_id)
+        .cloned()
+        .ok_or(CADmiumError::SketchFeatureNotFound(
+            SketchFeatureType::Point,
+            point_id,
+        ))
+}
+
+fn sketch_point_2d(sketch: &RealSketch, point_id: u64) -> Result<Point2, CADmiumError> {
+    sketch
+        .points_2d
+        .get(&point_id)
+        .cloned()
+        .ok_or(CADmiumError::SketchFeatureNotFound(
+            SketchFeatureType::Point,
+            point_id,
+        ))
+}
+
+impl PointReference {
+    pub fn resolve(&self, realized: &Realization) -> Result<Point3, CADmiumError> {
+        match self {
+            PointReference::Point { point_id } => realized
+                .points
+                .get(point_id)
+                .cloned()
+                .ok_or(CADmiumError::StepIDNotFound(point_id.to_owned())),
+            PointReference::SketchPoint {
+                sketch_id,
+                point_id,
+            } => sketch_point(get_sketch(realized, sketch_id)?, *point_id),
+            PointReference::Vertex {
+                solid_id,
+                vertex_name,
+            } => {
+                let solid = get_solid(realized, solid_id)?;
+                solid
+                    .vertex_names()
+                    .iter()
+                    .position(|name| name == vertex_name)
+                    .map(|index| solid.vertex_points()[index].clone())
+                    .ok_or(CADmiumError::VertexNotFound(
+                        solid_id.to_owned(),
+                        vertex_name.to_owned(),
+                    ))
+            }
+        }
+    }
+}
+
+impl EdgeReference {
+    // The edge as a sequence of 3D points, in order from start to end
+    pub fn polyline(&self, realized: &Realization) -> Result<Vec<Point3>, CADmiumError> {
+        match self {
+            EdgeReference::SketchLine { sketch_id, line_id } => {
+                let sketch = get_sketch(realized, sketch_id)?;
+                let line = sketch.line_segments.get(line_id).ok_or(
+                    CADmiumError::SketchFeatureNotFound(SketchFeatureType::Line, *line_id),
+                )?;
+                Ok(vec![
+                    sketch_point(sketch, line.start)?,
+                    sketch_point(sketch, line.end)?,
+                ])
+            }
+            EdgeReference::SketchArc { sketch_id, arc_id } => {
+                let sketch = get_sketch(realized, sketch_id)?;
+                let arc = sketch
+                    .arcs
+                    .get(arc_id)
+                    .ok_or(CADmiumError::SketchFeatureNotFound(
+                        SketchFeatureType::Arc,
+                        *arc_id,
+                    ))?;
+                let plane = &realized
+                    .planes
+                    .get(&sketch.plane_id)
+                    .ok_or(CADmiumError::StepIDNotFound(sketch.plane_id.clone()))?
+                    .plane;
+                let points = arc_to_points(
+                    &sketch_point_2d(sketch, arc.start)?,
+                    &sketch_point_2d(sketch, arc.end)?,
+                    &sketch_point_2d(sketch, arc.center)?,
+                    arc.clockwise,
+                );
+                Ok(points.iter().map(|p| plane.unproject(p)).collect())
+            }
+            EdgeReference::SketchCircle {
+                sketch_id,
+                circle_id,
+            } => {
+                let sketch = get_sketch(realized, sketch_id)?;
+                let circle =
+                    sketch
+                        .circles
+                        .get(circle_id)
+                        .ok_or(CADmiumError::SketchFeatureNotFound(
+                            SketchFeatureType::Circle,
+                            *circle_id,
+                        ))?;
+                let plane = &realized
+                    .planes
+                    .get(&sketch.plane_id)
+                    .ok_or(CADmiumError::StepIDNotFound(sketch.plane_id.clone()))?
+                    .plane;
+                let center = sketch_point_2d(sketch, circle.center)?;
+                let n = 72;
+                Ok((0..=n)
+                    .map(|i| {
+                        let theta = std::f64::consts::TAU * i as f64 / n as f64;
+                        plane.unproject(&Point2::new(
+                            center.x + circle.radius * theta.cos(),
+                            center.y + circle.radius * theta.sin(),
+                        ))
+                    })
+                    .collect())
+            }
+            EdgeReference::SolidEdge {
+                solid_id,
+                edge_name,
+            } => {
+                let solid = get_solid(realized, solid_id)?;
+                let edge_not_found =
+                    || CADmiumError::EdgeNotFound(solid_id.to_owned(), edge_name.to_owned());
+                let index = solid
+                    .edge_names()
+                    .iter()
+                    .position(|name| name == edge_name)
+                    .ok_or_else(edge_not_found)?;
+                let polylines: Vec<EdgePolyline> = solid.edge_polylines(DATUM_TOLERANCE);
+                polylines
+                    .into_iter()
+                    .nth(index)
+                    .map(|polyline| polyline.points)
+                    .ok_or_else(edge_not_found)
+            }
+            EdgeReference::Axis { axis_id } => {
+                let real_axis = realized
+                    .axes
+                    .get(axis_id)
+                    .ok_or(CADmiumError::StepIDNotFound(axis_id.to_owned()))?;
+                let axis = &real_axis.axis;
+                let half = axis.direction.times(real_axis.length / 2.0);
+                Ok(vec![
+                    axis.origin.plus(half.times(-1.0)).to_point3(),
+                    axis.origin.plus(half).to_point3(),
+                ])
+            }
+        }
+    }
+
+    pub fn line(&self, realized: &Realization) -> Result<InfiniteLine, CADmiumError> {
+        fit_line(&self.polyline(realized)?)
+    }
+}
+
+impl FaceReference {
+    // Where the named face sits in the solid's list of faces right now
+    pub fn index(&self, realized: &Realization) -> Result<u64, CADmiumError> {
+        get_solid(realized, &self.solid_id)?
+            .face_index(&self.face_name)
+            .ok_or(CADmiumError::FaceNotFound(
+                self.solid_id.to_owned(),
+                self.face_name.to_owned(),
+            ))
+    }
+
+    pub fn mesh(&self, realized: &Realization) -> Result<FaceMesh, CADmiumError> {
+        let index = self.index(realized)?;
+        get_solid(realized, &self.solid_id)?
+            .face_meshes(DATUM_TOLERANCE)
+            .into_iter()
+            .nth(index as usize)
+            .ok_or(CADmiumError::FaceNotFound(
+                self.solid_id.to_owned(),
+                self.face_name.to_owned(),
+            ))
+    }
+
+    pub fn plane(&self, realized: &Realization) -> Result<Plane, CADmiumError> {
+        let index = self.index(realized)?;
+        let face = get_solid(realized, &self.solid_id)?.get_face(index).ok_or(
+            CADmiumError::FaceNotFound(self.solid_id.to_owned(), self.face_name.to_owned()),
+        )?;
+        face_plane(&face).ok_or(CADmiumError::InvalidGeometry(format!(
+            "face {} of {} is not planar",
+            self.face_name, self.solid_id
+        )))
+    }
+}
+
+impl PlaneReference {
+    pub fn resolve(&self, realized: &Realization) -> Result<Plane, CADmiumError> {
+        match self {
+            PlaneReference::Plane { plane_id } => realized
+                .planes
+                .get(plane_id)
+                .map(|real_plane| real_plane.plane.clone())
+                .ok_or(CADmiumError::StepIDNotFound(plane_id.to_owned())),
+            PlaneReference::Face { face } => face.plane(realized),
+        }
+    }
+}
+
+impl PlaneDefinition {
+    pub fn evaluate(&self, realized: &Realization) -> Result<Plane, CADmiumError> {
+        match self {
+            PlaneDefinition::Offset {
+                reference,
+                distance,
+            } => {
+                let mut plane = reference.resolve(realized)?;
+                plane.origin = plane
+                    .origin
+                    .plus(plane.tertiary.times(*distance))
+                    .to_point3();
+                Ok(plane)
+            }
+            PlaneDefinition::Angle {
+                reference,
+                axis,
+                angle,
+            } => {
+                let plane = reference.resolve(realized)?;
+                let line = axis.line(realized)?;
+                Ok(Plane {
+                    origin: line.closest_point(&plane.origin),
+                    primary: plane.primary.rotate_about(&line.direction, *angle),
+                    secondary: plane.secondary.rotate_about(&line.direction, *angle),
+                    tertiary: plane.tertiary.rotate_about(&line.direction, *angle),
+                })
+            }
+            PlaneDefinition::ThreePoints { p0, p1, p2 } => {
+                let a = p0.resolve(realized)?;
+                let b = p1.resolve(realized)?;
+                let c = p2.resolve(realized)?;
+                let primary = b.minus(&a).normalize();
+                let normal = b.minus(&a).cross(&c.minus(&a));
+                if normal.length() < 1e-9 {
+                    return Err(CADmiumError::InvalidGeometry(
+                        "the three points are collinear".to_owned(),
+                    ));
+                }
+                let tertiary = normal.normalize();
+                Ok(Plane {
+                    origin: a,
+                    secondary: tertiary.cross(&primary),
+                    primary,
+                    tertiary,
+                })
+            }
+            PlaneDefinition::TangentToCylinder { face, near } => {
+                let cylinder = fit_cylinder(&face.mesh(realized)?)?;
+                let near = near.resolve(realized)?;
+
+                let axis_line = InfiniteLine {
+                    origin: cylinder.origin.clone(),
+                    direction: cylinder.axis.clone(),
+                };
+                let on_axis = axis_line.closest_point(&near);
+                let outward = near.minus(&on_axis);
+                if outward.length() < 1e-9 {
+                    return Err(CADmiumError::InvalidGeometry(
+                        "the point is on the cylinder's axis".to_owned(),
+                    ));
+                }
+                let outward = outward.normalize();
+
+                Ok(Plane {
+                    origin: on_axis.plus(outward.times(cylinder.radius)).to_point3(),
+                    primary: cylinder.axis.clone(),
+                    secondary: outward.cross(&cylinder.axis),
+                    tertiary: outward,
+                })
+            }
+            PlaneDefinition::NormalToCurve { curve, at } => {
+                let points = curve.polyline(realized)?;
+                let at = at.resolve(realized)?;
+                let (origin, tangent) = closest_point_on_polyline(&points, &at).ok_or(
+                    CADmiumError::InvalidGeometry("the curve has no length".to_owned()),
+                )?;
+
+                // arcs and circles are only approximated by their polyline, so
+                // prefer the exact tangent when the curve turns out to be circular
+                let tangent = match fit_circle(&points) {
+                    Ok(circle) => {
+                        let radial = origin.minus(&circle.center);
+                        let exact = circle.normal.cross(&radial).normalize();
+                        if exact.dot(&tangent) < 0.0 {
+                            exact.times(-1.0)
+                        } else {
+                            exact
+                        }
+                    }
+                    Err(_) => tangent,
+                };
+
+                Ok(Plane::from_normal(origin, tangent))
+            }
+        }
+    }
+}
+
+impl AxisDefinition {
+    pub fn evaluate(&self, realized: &Realization) -> Result<InfiniteLine, CADmiumError> {
+        match self {
+            AxisDefinition::TwoPoints { p0, p1 } => {
+                let a = p0.resolve(realized)?;
+                let b = p1.resolve(realized)?;
+                fit_line(&[a, b])
+            }
+            AxisDefinition::Edge { edge } => edge.line(realized),
+            AxisDefinition::CylinderAxis { face } => {
+                let cylinder = fit_cylinder(&face.mesh(realized)?)?;
+                Ok(InfiniteLine {
+                    origin: cylinder.origin,
+                    direction: cylinder.axis,
+                })
+            }
+            AxisDefinition::PlaneIntersection { plane_a, plane_b } => {
+                plane_plane_intersection(&plane_a.resolve(realized)?, &plane_b.resolve(realized)?)
+                    .ok_or(CADmiumError::InvalidGeometry(
+                        "the planes are parallel".to_owned(),
+                    ))
+            }
+        }
+    }
+}
+
+impl PointDefinition {
+    pub fn evaluate(&self, realized: &Realization) -> Result<Point3, CADmiumError> {
+        match self {
+            PointDefinition::Midpoint { edge } => polyline_midpoint(&edge.polyline(realized)?)
+                .ok_or(CADmiumError::InvalidGeometry(
+                    "the edge is empty".to_owned(),
+                )),
+            PointDefinition::Center { edge } => Ok(fit_circle(&edge.polyline(realized)?)?.center),
+            PointDefinition::EdgeIntersection { edge_a, edge_b } => {
+                let (on_a, on_b) =
+                    closest_points_between_lines(&edge_a.line(realized)?, &edge_b.line(realized)?)
+                        .ok_or(CADmiumError::InvalidGeometry(
+                            "the edges are parallel".to_owned(),
+                        ))?;
+                if on_a.distance_to(&on_b) > DATUM_TOLERANCE {
+                    return Err(CADmiumError::InvalidGeometry(
+                        "the edges do not intersect".to_owned(),
+                    ));
+                }
+                Ok(on_a)
+            }
+            PointDefinition::EdgePlaneIntersection { edge, plane } => {
+                line_plane_intersection(&edge.line(realized)?, &plane.resolve(realized)?).ok_or(
+                    CADmiumError::InvalidGeometry("the edge is parallel to the plane".to_owned()),
+                )
+            }
+        }
+    }
+}
+
+// Solid geometry depends on the solid, and also on everything named in its persistent name
+fn named_geometry_dependencies(solid_id: &str, name: &str) -> Vec<Dependency> {
+    let mut dependencies = vec![Dependency::Solid(solid_id.to_owned())];
+    for dependency in dependencies_in_name(name) {
+        if !dependencies.contains(&dependency) {
+            dependencies.push(dependency);
+        }
+    }
+    dependencies
+}
+
+impl PointReference {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            PointReference::Point { point_id } => vec![Dependency::Step(point_id.to_owned())],
+            PointReference::SketchPoint { sketch_id, .. } => {
+                vec![Dependency::Step(sketch_id.to_owned())]
+            }
+            PointReference::Vertex {
+                solid_id,
+                vertex_name,
+            } => named_geometry_dependencies(solid_id, vertex_name),
+        }
+    }
+}
+
+impl EdgeReference {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            EdgeReference::SketchLine { sketch_id, .. }
+            | EdgeReference::SketchArc { sketch_id, .. }
+            | EdgeReference::SketchCircle { sketch_id, .. } => {
+                vec![Dependency::Step(sketch_id.to_owned())]
+            }
+            EdgeReference::SolidEdge {
+                solid_id,
+                edge_name,
+            } => named_geometry_dependencies(solid_id, edge_name),
+            EdgeReference::Axis { axis_id } => vec![Dependency::Step(axis_id.to_owned())],
+        }
+    }
+}
+
+impl FaceReference {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        named_geometry_dependencies(&self.solid_id, &self.face_name)
+    }
+}
+
+impl PlaneReference {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            PlaneReference::Plane { plane_id } => vec![Dependency::Step(plane_id.to_owned())],
+            PlaneReference::Face { face } => face.dependencies(),
+        }
+    }
+}
+
+impl PlaneDefinition {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            PlaneDefinition::Offset { reference, .. } => reference.dependencies(),
+            PlaneDefinition::Angle {
+                reference, axis, ..
+            } => [reference.dependencies(), axis.dependencies()].concat(),
+            PlaneDefinition::ThreePoints { p0, p1, p2 } => {
+                [p0.dependencies(), p1.dependencies(), p2.dependencies()].concat()
+            }
+            PlaneDefinition::TangentToCylinder { face, near } => {
+                [face.dependencies(), near.dependencies()].concat()
+            }
+            PlaneDefinition::NormalToCurve { curve, at } => {
+                [curve.dependencies(), at.dependencies()].concat()
+            }
+        }
+    }
+}
+
+impl AxisDefinition {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            AxisDefinition::TwoPoints { p0, p1 } => [p0.dependencies(), p1.dependencies()].concat(),
+            AxisDefinition::Edge { edge } => edge.dependencies(),
+            AxisDefinition::CylinderAxis { face } => face.dependencies(),
+            AxisDefinition::PlaneIntersection { plane_a, plane_b } => {
+                [plane_a.dependencies(), plane_b.dependencies()].concat()
+            }
+        }
+    }
+}
+
+impl PointDefinition {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            PointDefinition::Midpoint { edge } | PointDefinition::Center { edge } => {
+                edge.dependencies()
+            }
+            PointDefinition::EdgeIntersection { edge_a, edge_b } => {
+                [edge_a.dependencies(), edge_b.dependencies()].concat()
+            }
+            PointDefinition::EdgePlaneIntersection { edge, plane } => {
+                [edge.dependencies(), plane.dependencies()].concat()
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::workbench::Workbench;
+use std::collections::{HashMap, HashSet};
+
+// Which steps build on which, worked out from the planes sketches sit on, the sketches
+// extrusions and holes are made from, and the solids they merge with
+#[derive(Tsify, Debug, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DependencyGraph {
+    // every step ID, in history order
+    pub steps: Vec<String>,
+    // step ID -> the IDs of the earlier steps it builds on
+    pub dependencies: HashMap<String, Vec<String>>,
+    // step ID -> the references it makes which no earlier step provides
+    pub broken: HashMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new(workbench: &Workbench) -> Self {
+        let mut graph = DependencyGraph {
+            steps: vec![],
+            dependencies: HashMap::new(),
+            broken: HashMap::new(),
+        };
+
+        for (index, step) in workbench.history.iter().enumerate() {
+            graph.steps.push(step.unique_id.clone());
+            graph
+                .dependencies
+                .insert(step.unique_id.clone(), workbench.step_dependencies(index));
+
+            let missing = workbench.missing_dependencies(index);
+            if !missing.is_empty() {
+                graph.broken.insert(step.unique_id.clone(), missing);
+            }
+        }
+
+        graph
+    }
+
+    // The steps that build directly on this one, in history order
+    pub fn dependents(&self, step_id: &str) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|other| self.dependencies[*other].iter().any(|id| id == step_id))
+            .cloned()
+            .collect()
+    }
+
+    // Every step that would be affected by removing this one, directly or not, in history order
+    pub fn downstream(&self, step_id: &str) -> Vec<String> {
+        let mut affected: HashSet<&str> = HashSet::from([step_id]);
+
+        // dependencies always come earlier, so one pass in history order is enough
+        for other in self.steps.iter() {
+            if self.dependencies[other]
+                .iter()
+                .any(|id| affected.contains(id.as_str()))
+            {
+                affected.insert(other.as_str());
+            }
+        }
+
+        self.steps
+            .iter()
+            .filter(|other| *other != step_id && affected.contains(other.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbench::tests::block_with_hole;
+
+    use super::*;
+
+    #[test]
+    fn holes_depend_on_their_sketch_and_the_solid_they_drill() {
+        let wb = block_with_hole();
+        let graph = DependencyGraph::new(&wb);
+
+        assert_eq!(graph.dependencies["Sketch-0"], vec!["Plane-0"]);
+        assert_eq!(graph.dependents("Extrusion-0"), vec!["Hole-0"]);
+        assert_eq!(
+            graph.downstream("Plane-0"),
+            vec!["Sketch-0", "Extrusion-0", "Sketch-1", "Hole-0"]
+        );
+        assert_eq!(graph.downstream("Hole-0"), Vec::<String>::new());
+        assert!(graph.broken.is_empty());
+    }
+}
@@ -2,19 +2,53 @@ use thiserror::Error;
 
 use crate::sketch::SketchFeatureType;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, strum::IntoStaticStr)]
 pub enum CADmiumError {
 	// Message errors
 	#[error("The workbench ID {0} was not found")]
 	WorkbenchIDNotFound(u64),
 	#[error("The workbench name {0} was not found")]
 	WorkbenchNameNotFound(String),
+	#[error("The workbench ID {0} is the only one left, so it can't be deleted")]
+	LastWorkbench(u64),
 	#[error("The step ID {0} was not found")]
 	StepIDNotFound(String),
 	#[error("The step name {0} was not found")]
 	StepNameNotFound(String),
+	#[error("The step {0} can't come before {1}, which it depends on")]
+	StepBeforeDependency(String, String),
 	#[error("The sketch ID {0} was not found")]
 	SketchIDNotFound(u64),
+	#[error("The assembly ID {0} was not found")]
+	AssemblyIDNotFound(u64),
+	#[error("The instance ID {0} was not found")]
+	InstanceNotFound(String),
+	#[error("The mate ID {0} was not found")]
+	MateNotFound(String),
+	#[error("The assembly ID {0} contains this assembly, so it can't be placed inside it")]
+	AssemblyCycle(u64),
+	#[error("There is nothing to undo")]
+	NothingToUndo,
+	#[error("There is nothing to redo")]
+	NothingToRedo,
+	#[error("Couldn't restore the project: {0}")]
+	RestoreFailed(String),
+	#[error("{0} can't be sent inside a batch")]
+	NotAllowedInBatch(String),
+	#[error("The batch reference {0} doesn't match an earlier result")]
+	BatchReferenceNotFound(String),
+
+	// Realization errors
+	#[error("This step refers to {0}, which doesn't exist before it")]
+	MissingDependency(String),
+	#[error("This step builds on {0}, which was suppressed or failed")]
+	DependencyUnavailable(String),
+	#[error("The sketch {0} isn't on a plane yet")]
+	SketchWithoutPlane(String),
+	#[error("The plane {0} was not found")]
+	PlaneNotFound(String),
+	#[error("Couldn't {0}")]
+	BooleanFailed(String),
 
 	// StepData errors
 	#[error("The step {0} data type is not as expected")]
@@ -30,7 +64,86 @@ pub enum CADmiumError {
 	#[error("The {0} with ID {1} has an end point that doesn't exist in the current sketch")]
 	SketchFeatureMissingEnd(SketchFeatureType, u64),
 
+	// Geometry reference errors
+	#[error("The solid {0} was not found")]
+	SolidNotFound(String),
+	#[error("The solid {0} has no face named {1}")]
+	FaceNotFound(String, String),
+	#[error("The solid {0} has no edge named {1}")]
+	EdgeNotFound(String, String),
+	#[error("The solid {0} has no vertex named {1}")]
+	VertexNotFound(String, String),
+	#[error("The sketch {0} no longer has a face matching {1}")]
+	SketchFaceNotFound(String, String),
+	#[error("The sketch {0} has changed, so its face {1} was matched to the closest face left")]
+	SketchFaceChanged(String, String),
+	#[error("The sketch {0} has more than one face that could be {1}, so it was left out")]
+	SketchFaceAmbiguous(String, String),
+	#[error("The {0} with ID {1} was not found in the sketch")]
+	SketchFeatureNotFound(SketchFeatureType, u64),
+	#[error("Invalid geometry: {0}")]
+	InvalidGeometry(String),
+
+	// Hole errors
+	#[error("There is no standard hole size called {0}")]
+	HoleStandardNotFound(String),
+	#[error("The hole references point ID {0} which doesn't exist in its sketch")]
+	HolePointNotFound(u64),
+	#[error("The hole can't be made: {0}")]
+	InvalidHole(String),
+
 
 	#[error("This function is not implemented yet")]
 	NotImplemented,
 }
+
+impl CADmiumError {
+	// The IDs and names this error is about, for clients to point at
+	pub fn ids(&self) -> Vec<String> {
+		use CADmiumError::*;
+
+		match self {
+			WorkbenchIDNotFound(id)
+			| LastWorkbench(id)
+			| SketchIDNotFound(id)
+			| AssemblyIDNotFound(id)
+			| AssemblyCycle(id)
+			| HolePointNotFound(id) => {
+				vec![id.to_string()]
+			}
+			WorkbenchNameNotFound(id)
+			| StepIDNotFound(id)
+			| StepNameNotFound(id)
+			| InstanceNotFound(id)
+			| MateNotFound(id)
+			| DependencyUnavailable(id)
+			| SketchWithoutPlane(id)
+			| PlaneNotFound(id)
+			| IncorrectStepDataType(id)
+			| SolidNotFound(id)
+			| HoleStandardNotFound(id)
+			| BatchReferenceNotFound(id) => vec![id.to_owned()],
+			MissingDependency(ids) => ids.split(", ").map(str::to_owned).collect(),
+			StepBeforeDependency(a, b)
+			| FaceNotFound(a, b)
+			| EdgeNotFound(a, b)
+			| VertexNotFound(a, b)
+			| SketchFaceNotFound(a, b)
+			| SketchFaceChanged(a, b)
+			| SketchFaceAmbiguous(a, b) => vec![a.to_owned(), b.to_owned()],
+			SketchFeatureAlreadyExists(_, id)
+			| SketchFeatureIDTooLow(_, id)
+			| SketchFeatureMissingStart(_, id)
+			| SketchFeatureMissingEnd(_, id)
+			| SketchFeatureNotFound(_, id) => vec![id.to_string()],
+			BooleanFailed(_)
+			| InvalidGeometry(_)
+			| InvalidHole(_)
+			| RestoreFailed(_)
+			| NotAllowedInBatch(_)
+			| NothingToUndo
+			| NothingToRedo
+			| NotImplemented => vec![],
+		}
+	}
+}
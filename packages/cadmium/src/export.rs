@@ -0,0 +1,419 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use crate::archetypes::Vector3;
+use crate::material::Material;
+use crate::solid::Solid;
+
+// glTF is in meters with Y up, while models are drawn in millimeters with Z up
+const GLTF_SCALE: f64 = 0.001;
+const GLTF_ROTATION: [f64; 4] = [-FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2];
+
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_UNSIGNED_INT: u32 = 5125;
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+// 3MF vertices closer than this are the same vertex
+const WELD_TOLERANCE: f64 = 1e-6;
+
+// The index of this material in the list, adding it if it isn't there yet
+fn material_index<'a>(materials: &mut Vec<&'a Material>, material: &'a Material) -> usize {
+    match materials.iter().position(|m| *m == material) {
+        Some(index) => index,
+        None => {
+            materials.push(material);
+            materials.len() - 1
+        }
+    }
+}
+
+// A binary glTF (.glb) with a mesh for each solid, made from the solid's render mesh and
+// colored by its material
+pub fn to_glb(solids: &[(String, &Solid)]) -> Vec<u8> {
+    let mut buffer: Vec<u8> = vec![];
+    let mut buffer_views: Vec<Value> = vec![];
+    let mut accessors: Vec<Value> = vec![];
+    let mut meshes: Vec<Value> = vec![];
+    let mut nodes: Vec<Value> = vec![];
+    let mut materials: Vec<&Material> = vec![];
+
+    // appends the bytes to the buffer and returns the index of their buffer view
+    let mut add_view = |bytes: Vec<u8>, target: u32| {
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        buffer.extend(bytes);
+        buffer_views.len() - 1
+    };
+
+    for (id, solid) in solids.iter() {
+        if solid.indices.is_empty() {
+            continue;
+        }
+        let material = material_index(&mut materials, &solid.material);
+
+        let as_f32 = |v: &Vector3| [v.x as f32, v.y as f32, v.z as f32];
+        let positions: Vec<[f32; 3]> = solid.vertices.iter().map(as_f32).collect();
+        let normals: Vec<[f32; 3]> = solid.normals.iter().map(as_f32).collect();
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for position in positions.iter() {
+            for (axis, value) in position.iter().enumerate() {
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+
+        let bytes = |values: &[[f32; 3]]| -> Vec<u8> {
+            values
+                .iter()
+                .flatten()
+                .flat_map(|x| x.to_le_bytes())
+                .collect()
+        };
+        let view = add_view(bytes(&positions), GLTF_ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": GLTF_FLOAT,
+            "count": positions.len(),
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        }));
+        let view = add_view(bytes(&normals), GLTF_ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": GLTF_FLOAT,
+            "count": normals.len(),
+            "type": "VEC3",
+        }));
+        let indices: Vec<u8> = solid
+            .indices
+            .iter()
+            .flat_map(|index| (*index as u32).to_le_bytes())
+            .collect();
+        let view = add_view(indices, GLTF_ELEMENT_ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": view,
+            "componentType": GLTF_UNSIGNED_INT,
+            "count": solid.indices.len(),
+            "type": "SCALAR",
+        }));
+
+        let first = accessors.len() - 3;
+        meshes.push(json!({
+            "name": id,
+            "primitives": [{
+                "attributes": { "POSITION": first, "NORMAL": first + 1 },
+                "indices": first + 2,
+                "material": material,
+            }],
+        }));
+        nodes.push(json!({ "name": id, "mesh": meshes.len() - 1 }));
+    }
+
+    // one node on top to turn and scale everything into glTF's frame
+    let children: Vec<usize> = (1..=nodes.len()).collect();
+    let scale = [GLTF_SCALE; 3];
+    nodes.insert(
+        0,
+        json!({
+            "name": "CADmium",
+            "scale": scale,
+            "rotation": GLTF_ROTATION,
+            "children": children,
+        }),
+    );
+
+    let materials: Vec<Value> = materials
+        .iter()
+        .map(|material| {
+            let [r, g, b] = material.color;
+            json!({
+                "name": material.name,
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [r, g, b, 1.0],
+                    "metallicFactor": material.metalness,
+                    "roughnessFactor": material.roughness,
+                },
+            })
+        })
+        .collect();
+    let buffers: Vec<Value> = if buffer.is_empty() {
+        vec![]
+    } else {
+        vec![json!({ "byteLength": buffer.len() })]
+    };
+
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "CADmium" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": buffers,
+    });
+    // glTF doesn't allow empty arrays, which only happen when there are no solids
+    if let Some(document) = document.as_object_mut() {
+        document.retain(|_, value| value.as_array().is_none_or(|a| !a.is_empty()));
+    }
+
+    let mut json_chunk = serde_json::to_vec(&document).unwrap();
+    while json_chunk.len() % 4 != 0 {
+        json_chunk.push(b' ');
+    }
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+
+    let mut chunks = vec![(0x4E4F534A_u32, json_chunk)];
+    if !buffer.is_empty() {
+        chunks.push((0x004E4942, buffer));
+    }
+    let length = 12 + chunks.iter().map(|(_, c)| 8 + c.len()).sum::<usize>();
+
+    let mut glb = vec![];
+    glb.extend(b"glTF");
+    glb.extend(2_u32.to_le_bytes());
+    glb.extend((length as u32).to_le_bytes());
+    for (kind, chunk) in chunks {
+        glb.extend((chunk.len() as u32).to_le_bytes());
+        glb.extend(kind.to_le_bytes());
+        glb.extend(chunk);
+    }
+    glb
+}
+
+// A 3MF package with an object for each solid, in millimeters, with its material's color,
+// roughness and metalness
+pub fn to_3mf(solids: &[(String, &Solid)]) -> Vec<u8> {
+    let mut materials: Vec<&Material> = vec![];
+    let mut objects = String::new();
+    let mut items = String::new();
+
+    for (index, (id, solid)) in solids.iter().enumerate() {
+        let material = material_index(&mut materials, &solid.material);
+
+        // the render mesh repeats vertices for every triangle, but 3MF meshes must be
+        // closed, so corners in the same place are merged into one vertex
+        let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut vertices: Vec<&Vector3> = vec![];
+        let mut triangles: Vec<[usize; 3]> = vec![];
+        for corners in solid.indices.chunks_exact(3) {
+            let triangle = [corners[0], corners[1], corners[2]].map(|corner| {
+                let v = &solid.vertices[corner];
+                let key = [v.x, v.y, v.z].map(|x| (x / WELD_TOLERANCE).round() as i64);
+                *welded.entry(key).or_insert_with(|| {
+                    vertices.push(v);
+                    vertices.len() - 1
+                })
+            });
+            let [a, b, c] = triangle;
+            if a != b && b != c && c != a {
+                triangles.push(triangle);
+            }
+        }
+
+        // 1 and 2 are the display properties and materials
+        let object_id = index + 3;
+        objects.push_str(&format!(
+            "  <object id=\"{}\" type=\"model\" name=\"{}\" pid=\"2\" pindex=\"{}\">\n   <mesh>\n    <vertices>\n",
+            object_id,
+            escape_xml(id),
+            material
+        ));
+        for v in vertices {
+            objects.push_str(&format!(
+                "     <vertex x=\"{}\" y=\"{}\" z=\"{}\" />\n",
+                v.x, v.y, v.z
+            ));
+        }
+        objects.push_str("    </vertices>\n    <triangles>\n");
+        for [a, b, c] in triangles {
+            objects.push_str(&format!(
+                "     <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\" />\n",
+                a, b, c
+            ));
+        }
+        objects.push_str("    </triangles>\n   </mesh>\n  </object>\n");
+        items.push_str(&format!("  <item objectid=\"{}\" />\n", object_id));
+    }
+
+    let mut resources = String::new();
+    if !materials.is_empty() {
+        resources.push_str("  <m:pbmetallicdisplayproperties id=\"1\">\n");
+        for material in materials.iter() {
+            resources.push_str(&format!(
+                "   <m:pbmetallic name=\"{}\" metallicness=\"{}\" roughness=\"{}\" />\n",
+                escape_xml(&material.name),
+                material.metalness,
+                material.roughness
+            ));
+        }
+        resources.push_str("  </m:pbmetallicdisplayproperties>\n");
+        resources.push_str("  <basematerials id=\"2\" displaypropertiesid=\"1\">\n");
+        for material in materials.iter() {
+            resources.push_str(&format!(
+                "   <base name=\"{}\" displaycolor=\"{}\" />\n",
+                escape_xml(&material.name),
+                material.hex_color()
+            ));
+        }
+        resources.push_str("  </basematerials>\n");
+    }
+    resources.push_str(&objects);
+
+    let model = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <model unit=\"millimeter\" xml:lang=\"en-US\" \
+         xmlns=\"http://schemas.microsoft.com/3dmanufacturing/core/2015/02\" \
+         xmlns:m=\"http://schemas.microsoft.com/3dmanufacturing/material/2015/02\">\n \
+         <resources>\n{} </resources>\n <build>\n{} </build>\n</model>\n",
+        resources, items
+    );
+    let content_types = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+        <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\" />\
+        <Default Extension=\"model\" ContentType=\"application/vnd.ms-package.3dmanufacturing-3dmodel+xml\" />\
+        </Types>\n";
+    let relationships = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+        <Relationship Target=\"/3D/3dmodel.model\" Id=\"rel0\" \
+        Type=\"http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel\" />\
+        </Relationships>\n";
+
+    stored_zip(&[
+        ("[Content_Types].xml", content_types.as_bytes()),
+        ("_rels/.rels", relationships.as_bytes()),
+        ("3D/3dmodel.model", model.as_bytes()),
+    ])
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// A zip archive with the files stored uncompressed, which is all a 3MF package needs
+fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    // version 2.0, no flags, stored, and a timestamp of 1980-01-01 00:00
+    let common = |entry: &mut Vec<u8>, name: &str, data: &[u8]| {
+        entry.extend(20_u16.to_le_bytes());
+        entry.extend(0_u16.to_le_bytes());
+        entry.extend(0_u16.to_le_bytes());
+        entry.extend(0_u16.to_le_bytes());
+        entry.extend(0x21_u16.to_le_bytes());
+        entry.extend(crc32fast::hash(data).to_le_bytes());
+        entry.extend((data.len() as u32).to_le_bytes());
+        entry.extend((data.len() as u32).to_le_bytes());
+        entry.extend((name.len() as u16).to_le_bytes());
+        entry.extend(0_u16.to_le_bytes());
+    };
+
+    let mut archive = vec![];
+    let mut directory = vec![];
+    for (name, data) in files.iter() {
+        let offset = archive.len() as u32;
+        archive.extend(0x04034b50_u32.to_le_bytes());
+        common(&mut archive, name, data);
+        archive.extend(name.as_bytes());
+        archive.extend(*data);
+
+        directory.extend(0x02014b50_u32.to_le_bytes());
+        directory.extend(20_u16.to_le_bytes());
+        common(&mut directory, name, data);
+        // no comment, first disk, no attributes
+        directory.extend(0_u16.to_le_bytes());
+        directory.extend(0_u16.to_le_bytes());
+        directory.extend(0_u16.to_le_bytes());
+        directory.extend(0_u32.to_le_bytes());
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    archive.extend(&directory);
+    archive.extend(0x06054b50_u32.to_le_bytes());
+    archive.extend(0_u16.to_le_bytes());
+    archive.extend(0_u16.to_le_bytes());
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend(0_u16.to_le_bytes());
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::Message;
+    use crate::project::Project;
+    use crate::workbench::tests::block_with_hole;
+
+    use super::*;
+
+    #[test]
+    fn materials_are_saved_weigh_solids_and_are_exported() {
+        let mut p = Project::new("Test Project");
+        p.workbenches[0] = block_with_hole();
+        let steel = Material {
+            name: "Steel".to_owned(),
+            density: 7.85,
+            color: [0.5, 0.5, 0.55],
+            roughness: 0.3,
+            metalness: 1.0,
+        };
+        Message::SetMaterial {
+            workbench_id: 0,
+            solid_id: Some("Extrusion-0:0".to_owned()),
+            material: Some(steel.clone()),
+        }
+        .handle(&mut p)
+        .unwrap();
+
+        let p = Project::from_json(&p.json());
+        let realized = p.get_realization(0, 1000).unwrap();
+        let solid = &realized.solids["Extrusion-0:0"];
+        assert_eq!(solid.material, steel);
+
+        let by_material = realized
+            .solid_mass_properties("Extrusion-0:0", None, 0.01)
+            .unwrap();
+        let by_volume = realized
+            .solid_mass_properties("Extrusion-0:0", Some(1.0), 0.01)
+            .unwrap();
+        assert!((by_material.mass - 7.85 * by_volume.mass).abs() < 1e-6 * by_material.mass);
+
+        let solids = vec![("Extrusion-0:0".to_owned(), solid)];
+        let glb = to_glb(&solids);
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+        let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        assert_eq!(document["materials"][0]["name"], "Steel");
+        assert_eq!(
+            document["materials"][0]["pbrMetallicRoughness"]["metallicFactor"],
+            1.0
+        );
+
+        let package = to_3mf(&solids);
+        assert_eq!(&package[0..4], &[0x50, 0x4b, 0x03, 0x04]);
+        let text = String::from_utf8_lossy(&package);
+        assert!(text.contains("<base name=\"Steel\" displaycolor=\"#BCBCC4\" />"));
+        assert!(text.contains("metallicness=\"1\" roughness=\"0.3\""));
+    }
+}
@@ -13,6 +13,8 @@ use truck_shapeops::ShapeOpsSurface;
 use truck_topology::Shell;
 
 use crate::archetypes::{Point3, Vector3};
+use crate::error::CADmiumError;
+use crate::naming::resolve_sketch_faces;
 use crate::project::{RealPlane, RealSketch};
 use crate::sketch::{arc_to_points, Face, Sketch};
 
@@ -25,6 +27,9 @@ use truck_topology::Solid as TruckSolid;
 pub struct Extrusion {
     pub sketch_id: String,
     pub face_ids: Vec<u64>,
+    // persistent names of the same sketch faces, which survive the faces being re-sorted
+    #[serde(default)]
+    pub face_names: Vec<String>,
     pub length: f64,
     pub offset: f64,
     pub direction: Direction,
@@ -51,12 +56,41 @@ impl Extrusion {
         Extrusion {
             sketch_id,
             face_ids,
+            face_names: vec![],
             length,
             offset,
             direction,
             mode,
         }
     }
+
+    // The indices of the sketch faces to extrude right now. Extrusions saved before faces
+    // had names fall back on their indices. Faces that had to be matched loosely are
+    // pushed onto warnings
+    pub fn resolve_face_ids(
+        &self,
+        sketch: &RealSketch,
+        warnings: &mut Vec<CADmiumError>,
+    ) -> Result<Vec<u64>, CADmiumError> {
+        if !self.face_names.is_empty() {
+            return resolve_sketch_faces(
+                &self.sketch_id,
+                &sketch.faces,
+                &self.face_names,
+                warnings,
+            );
+        }
+
+        for face_id in self.face_ids.iter() {
+            if *face_id as usize >= sketch.faces.len() {
+                return Err(CADmiumError::SketchFaceNotFound(
+                    self.sketch_id.clone(),
+                    face_id.to_string(),
+                ));
+            }
+        }
+        Ok(self.face_ids.clone())
+    }
 }
 
 #[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,720 @@
+// Numerical helpers for recovering analytic geometry (planes, lines, circles, cylinders)
+// from tessellated faces and edges. Tessellation vertices lie exactly on the underlying
+// surface or curve, so these fits only need to tolerate floating point noise.
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::archetypes::{Plane, Point3, Vector3};
+use crate::error::CADmiumError;
+use crate::solid::FaceMesh;
+
+// How far off a point may be, relative to the size of the thing being fit
+const FIT_TOLERANCE: f64 = 1e-6;
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct InfiniteLine {
+    pub origin: Point3,
+    pub direction: Vector3,
+}
+
+impl InfiniteLine {
+    pub fn closest_point(&self, point: &Point3) -> Point3 {
+        let t = point.minus(&self.origin).dot(&self.direction);
+        self.origin.plus(self.direction.times(t)).to_point3()
+    }
+
+    pub fn distance_to(&self, point: &Point3) -> f64 {
+        self.closest_point(point).distance_to(point)
+    }
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Circle {
+    pub center: Point3,
+    pub normal: Vector3,
+    pub radius: f64,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Cylinder {
+    pub origin: Point3,
+    pub axis: Vector3,
+    pub radius: f64,
+}
+
+fn extent(points: &[Point3]) -> f64 {
+    let mut max_distance: f64 = 0.0;
+    if let Some(first) = points.first() {
+        for p in points.iter() {
+            max_distance = max_distance.max(first.distance_to(p));
+        }
+    }
+    max_distance
+}
+
+pub fn centroid(points: &[Point3]) -> Point3 {
+    let n = points.len().max(1) as f64;
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
+    for p in points.iter() {
+        sum = sum.plus(Vector3::new(p.x, p.y, p.z));
+    }
+    sum.times(1.0 / n).to_point3()
+}
+
+// Newell's method: a robust normal for a (nearly) planar loop of points
+fn newell_normal(points: &[Point3]) -> Vector3 {
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    normal
+}
+
+fn solve3(m: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+
+    let d = det(m);
+    if d.abs() < 1e-300 {
+        return None;
+    }
+
+    let mut result = [0.0; 3];
+    for (col, r) in result.iter_mut().enumerate() {
+        let mut mc = m;
+        for row in 0..3 {
+            mc[row][col] = b[row];
+        }
+        *r = det(mc) / d;
+    }
+    Some(result)
+}
+
+// Kåsa's algebraic circle fit, on points that are already in 2D
+fn fit_circle_2d(points: &[(f64, f64)]) -> Option<(f64, f64, f64)> {
+    // minimize sum((x^2 + y^2) + D x + E y + F)^2
+    let mut m = [[0.0; 3]; 3];
+    let mut b = [0.0; 3];
+    for (x, y) in points.iter() {
+        let row = [*x, *y, 1.0];
+        let rhs = -(x * x + y * y);
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] += row[i] * row[j];
+            }
+            b[i] += row[i] * rhs;
+        }
+    }
+
+    let [d, e, f] = solve3(m, b)?;
+    let cx = -d / 2.0;
+    let cy = -e / 2.0;
+    let r2 = cx * cx + cy * cy - f;
+    if r2 <= 0.0 {
+        return None;
+    }
+    Some((cx, cy, r2.sqrt()))
+}
+
+pub fn fit_plane(face: &FaceMesh) -> Result<Plane, CADmiumError> {
+    // area weighted normal and centroid of all the triangles
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    let mut weighted_center = Vector3::new(0.0, 0.0, 0.0);
+    let mut total_area = 0.0;
+    for (a, b, c) in face.triangle_points() {
+        let cross = b.minus(a).cross(&c.minus(a));
+        let area = cross.length() / 2.0;
+        normal = normal.plus(cross);
+        let center =
+            Vector3::new(a.x + b.x + c.x, a.y + b.y + c.y, a.z + b.z + c.z).times(1.0 / 3.0);
+        weighted_center = weighted_center.plus(center.times(area));
+        total_area += area;
+    }
+
+    if total_area == 0.0 || normal.length() == 0.0 {
+        return Err(CADmiumError::InvalidGeometry(format!(
+            "face {} has no area",
+            face.face_id
+        )));
+    }
+
+    let origin = weighted_center.times(1.0 / total_area).to_point3();
+    let plane = Plane::from_normal(origin, normal);
+
+    let tolerance = FIT_TOLERANCE * (1.0 + extent(&face.positions));
+    if face
+        .positions
+        .iter()
+        .any(|p| plane.distance_to(p).abs() > tolerance)
+    {
+        return Err(CADmiumError::InvalidGeometry(format!(
+            "face {} is not planar",
+            face.face_id
+        )));
+    }
+
+    Ok(plane)
+}
+
+pub fn fit_cylinder(face: &FaceMesh) -> Result<Cylinder, CADmiumError> {
+    let not_cylindrical =
+        || CADmiumError::InvalidGeometry(format!("face {} is not cylindrical", face.face_id));
+
+    // Every surface normal of a cylinder is perpendicular to its axis, so the axis
+    // is the cross product of the two least parallel normals
+    let normals: Vec<Vector3> = face
+        .normals
+        .iter()
+        .filter(|n| n.length() > 0.0)
+        .map(|n| n.normalize())
+        .collect();
+    let first = normals.first().ok_or_else(not_cylindrical)?;
+    let mut axis = Vector3::new(0.0, 0.0, 0.0);
+    for n in normals.iter() {
+        let candidate = first.cross(n);
+        if candidate.length() > axis.length() {
+            axis = candidate;
+        }
+    }
+    if axis.length() < 1e-6 {
+        return Err(not_cylindrical());
+    }
+    let axis = axis.normalize();
+
+    if normals.iter().any(|n| n.dot(&axis).abs() > 1e-4) {
+        return Err(not_cylindrical());
+    }
+
+    // project every point into the plane perpendicular to the axis and fit a circle there
+    let basis = Plane::from_normal(face.positions[0].clone(), axis.clone());
+    let projected: Vec<(f64, f64)> = face
+        .positions
+        .iter()
+        .map(|p| {
+            let p2 = basis.project(p);
+            (p2.x, p2.y)
+        })
+        .collect();
+    let (cx, cy, radius) = fit_circle_2d(&projected).ok_or_else(not_cylindrical)?;
+
+    let tolerance = FIT_TOLERANCE * (1.0 + radius);
+    if projected
+        .iter()
+        .any(|(x, y)| ((x - cx).hypot(y - cy) - radius).abs() > tolerance * 100.0)
+    {
+        return Err(not_cylindrical());
+    }
+
+    let origin = basis
+        .origin
+        .plus(basis.primary.times(cx))
+        .plus(basis.secondary.times(cy))
+        .to_point3();
+
+    Ok(Cylinder {
+        origin,
+        axis,
+        radius,
+    })
+}
+
+pub fn fit_line(points: &[Point3]) -> Result<InfiniteLine, CADmiumError> {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) if first.distance_to(last) > 0.0 => (first, last),
+        _ => {
+            return Err(CADmiumError::InvalidGeometry(
+                "a line needs two distinct points".to_owned(),
+            ))
+        }
+    };
+
+    let line = InfiniteLine {
+        origin: first.clone(),
+        direction: last.minus(first).normalize(),
+    };
+
+    let tolerance = FIT_TOLERANCE * (1.0 + extent(points));
+    if points.iter().any(|p| line.distance_to(p) > tolerance) {
+        return Err(CADmiumError::InvalidGeometry(
+            "the curve is not straight".to_owned(),
+        ));
+    }
+
+    Ok(line)
+}
+
+pub fn fit_circle(points: &[Point3]) -> Result<Circle, CADmiumError> {
+    let not_circular = || CADmiumError::InvalidGeometry("the curve is not circular".to_owned());
+
+    if points.len() < 3 {
+        return Err(not_circular());
+    }
+
+    // An open arc's chord closes the loop for Newell's method, which still gives the right normal
+    let normal = newell_normal(points);
+    if normal.length() < 1e-12 {
+        return Err(not_circular());
+    }
+    let basis = Plane::from_normal(centroid(points), normal);
+    let projected: Vec<(f64, f64)> = points
+        .iter()
+        .map(|p| {
+            let p2 = basis.project(p);
+            (p2.x, p2.y)
+        })
+        .collect();
+
+    let (cx, cy, radius) = fit_circle_2d(&projected).ok_or_else(not_circular)?;
+
+    let tolerance = FIT_TOLERANCE * (1.0 + radius) * 100.0;
+    let off_circle = projected
+        .iter()
+        .any(|(x, y)| ((x - cx).hypot(y - cy) - radius).abs() > tolerance);
+    let off_plane = points
+        .iter()
+        .any(|p| basis.distance_to(p).abs() > tolerance);
+    if off_circle || off_plane {
+        return Err(not_circular());
+    }
+
+    let center = basis
+        .origin
+        .plus(basis.primary.times(cx))
+        .plus(basis.secondary.times(cy))
+        .to_point3();
+
+    Ok(Circle {
+        center,
+        normal: basis.tertiary,
+        radius,
+    })
+}
+
+pub fn closest_point_on_segment(a: &Point3, b: &Point3, p: &Point3) -> Point3 {
+    let ab = b.minus(a);
+    let length_squared = ab.dot(&ab);
+    if length_squared == 0.0 {
+        return a.clone();
+    }
+    let t = (p.minus(a).dot(&ab) / length_squared).clamp(0.0, 1.0);
+    a.plus(ab.times(t)).to_point3()
+}
+
+// Ericson's region test, from Real-Time Collision Detection
+pub fn closest_point_on_triangle(a: &Point3, b: &Point3, c: &Point3, p: &Point3) -> Point3 {
+    let ab = b.minus(a);
+    let ac = c.minus(a);
+    let ap = p.minus(a);
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a.clone();
+    }
+
+    let bp = p.minus(b);
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b.clone();
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return a.plus(ab.times(v)).to_point3();
+    }
+
+    let cp = p.minus(c);
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c.clone();
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return a.plus(ac.times(w)).to_point3();
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return b.plus(c.minus(b).times(w)).to_point3();
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    let v = vb * denominator;
+    let w = vc * denominator;
+    a.plus(ab.times(v)).plus(ac.times(w)).to_point3()
+}
+
+// The closest point on a polyline to p, along with the polyline's direction there
+pub fn closest_point_on_polyline(points: &[Point3], p: &Point3) -> Option<(Point3, Vector3)> {
+    let mut best: Option<(f64, Point3, Vector3)> = None;
+    for pair in points.windows(2) {
+        let candidate = closest_point_on_segment(&pair[0], &pair[1], p);
+        let distance = candidate.distance_to(p);
+        if best.as_ref().is_none_or(|(d, _, _)| distance < *d) {
+            best = Some((distance, candidate, pair[1].minus(&pair[0]).normalize()));
+        }
+    }
+    best.map(|(_, point, tangent)| (point, tangent))
+}
+
+pub fn polyline_length(points: &[Point3]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| pair[0].distance_to(&pair[1]))
+        .sum()
+}
+
+// The point halfway along a polyline, measured by arc length
+pub fn polyline_midpoint(points: &[Point3]) -> Option<Point3> {
+    let half = polyline_length(points) / 2.0;
+    let mut travelled = 0.0;
+    for pair in points.windows(2) {
+        let length = pair[0].distance_to(&pair[1]);
+        if travelled + length >= half && length > 0.0 {
+            let t = (half - travelled) / length;
+            return Some(pair[0].plus(pair[1].minus(&pair[0]).times(t)).to_point3());
+        }
+        travelled += length;
+    }
+    points.first().cloned()
+}
+
+// Where a line pierces a plane, or None if the line runs parallel to it
+pub fn line_plane_intersection(line: &InfiniteLine, plane: &Plane) -> Option<Point3> {
+    let denominator = line.direction.dot(&plane.tertiary);
+    if denominator.abs() < 1e-9 {
+        return None;
+    }
+    let t = -plane.distance_to(&line.origin) / denominator;
+    Some(line.origin.plus(line.direction.times(t)).to_point3())
+}
+
+// The line shared by two planes, or None if they are parallel
+pub fn plane_plane_intersection(a: &Plane, b: &Plane) -> Option<InfiniteLine> {
+    let direction = a.tertiary.cross(&b.tertiary);
+    if direction.length() < 1e-9 {
+        return None;
+    }
+    let direction = direction.normalize();
+
+    // any line in plane a that isn't parallel to b will cross b somewhere on the shared line
+    let across = InfiniteLine {
+        origin: a.origin.clone(),
+        direction: direction.cross(&a.tertiary),
+    };
+    let origin = line_plane_intersection(&across, b)?;
+    Some(InfiniteLine { origin, direction })
+}
+
+// The closest pair of points between two lines, one on each, or None if they are parallel
+pub fn closest_points_between_lines(
+    a: &InfiniteLine,
+    b: &InfiniteLine,
+) -> Option<(Point3, Point3)> {
+    let w = a.origin.minus(&b.origin);
+    let ab = a.direction.dot(&b.direction);
+    let denominator = 1.0 - ab * ab;
+    if denominator.abs() < 1e-12 {
+        return None;
+    }
+    let d = a.direction.dot(&w);
+    let e = b.direction.dot(&w);
+    let s = (ab * e - d) / denominator;
+    let t = (e - ab * d) / denominator;
+    Some((
+        a.origin.plus(a.direction.times(s)).to_point3(),
+        b.origin.plus(b.direction.times(t)).to_point3(),
+    ))
+}
+
+// An axis-aligned bounding box
+#[derive(Debug, Clone)]
+pub struct Bounds {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl Bounds {
+    pub fn around<'p>(points: impl Iterator<Item = &'p Point3>) -> Self {
+        let mut bounds = Bounds {
+            min: [f64::MAX; 3],
+            max: [f64::MIN; 3],
+        };
+        for p in points {
+            for (axis, value) in [p.x, p.y, p.z].into_iter().enumerate() {
+                bounds.min[axis] = bounds.min[axis].min(value);
+                bounds.max[axis] = bounds.max[axis].max(value);
+            }
+        }
+        bounds
+    }
+
+    // The shortest distance between the two boxes, zero if they overlap
+    pub fn distance_to(&self, other: &Bounds) -> f64 {
+        (0..3)
+            .map(|axis| {
+                let gap = (other.min[axis] - self.max[axis]).max(self.min[axis] - other.max[axis]);
+                gap.max(0.0).powi(2)
+            })
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    // Whether the other box lies entirely within this one
+    pub fn contains(&self, other: &Bounds) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.min[axis] && other.max[axis] <= self.max[axis])
+    }
+
+    // How far along the ray it enters the box, zero if it starts inside, or None if it misses
+    pub fn entered_by(&self, origin: &Point3, direction: &Vector3) -> Option<f64> {
+        let origin = [origin.x, origin.y, origin.z];
+        let direction = [direction.x, direction.y, direction.z];
+        let (mut near, mut far) = (0.0_f64, f64::MAX);
+        for axis in 0..3 {
+            if direction[axis].abs() < 1e-12 {
+                if origin[axis] < self.min[axis] || origin[axis] > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let a = (self.min[axis] - origin[axis]) / direction[axis];
+            let b = (self.max[axis] - origin[axis]) / direction[axis];
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        (near <= far).then_some(near)
+    }
+}
+
+// The closest pair of points between the segments p0-p1 and q0-q1, one on each
+pub fn closest_points_between_segments(
+    p0: &Point3,
+    p1: &Point3,
+    q0: &Point3,
+    q1: &Point3,
+) -> (Point3, Point3) {
+    let d1 = p1.minus(p0);
+    let d2 = q1.minus(q0);
+    let r = p0.minus(q0);
+    let a = d1.dot(&d1);
+    let e = d2.dot(&d2);
+    let f = d2.dot(&r);
+
+    let (s, t) = if a <= 1e-18 && e <= 1e-18 {
+        (0.0, 0.0)
+    } else if a <= 1e-18 {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(&r);
+        if e <= 1e-18 {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(&d2);
+            let denominator = a * e - b * b;
+            let mut s = if denominator > 1e-18 {
+                ((b * f - c * e) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+
+    (
+        p0.plus(d1.times(s)).to_point3(),
+        q0.plus(d2.times(t)).to_point3(),
+    )
+}
+
+// A piece of something tessellated: a point, a segment of a polyline or a triangle of a mesh
+#[derive(Debug, Clone)]
+pub enum Simplex {
+    Point(Point3),
+    Segment(Point3, Point3),
+    Triangle(Point3, Point3, Point3),
+}
+
+impl Simplex {
+    pub fn corners(&self) -> Vec<&Point3> {
+        match self {
+            Simplex::Point(p) => vec![p],
+            Simplex::Segment(a, b) => vec![a, b],
+            Simplex::Triangle(a, b, c) => vec![a, b, c],
+        }
+    }
+
+    pub fn edges(&self) -> Vec<(&Point3, &Point3)> {
+        match self {
+            Simplex::Point(_) => vec![],
+            Simplex::Segment(a, b) => vec![(a, b)],
+            Simplex::Triangle(a, b, c) => vec![(a, b), (b, c), (c, a)],
+        }
+    }
+
+    pub fn closest_point(&self, p: &Point3) -> Point3 {
+        match self {
+            Simplex::Point(point) => point.clone(),
+            Simplex::Segment(a, b) => closest_point_on_segment(a, b, p),
+            Simplex::Triangle(a, b, c) => closest_point_on_triangle(a, b, c, p),
+        }
+    }
+}
+
+// Two sets of simplices that don't cross are closest either at a corner of one over a
+// simplex of the other, or at a pair of edges
+pub fn closest_points(a: &[Simplex], b: &[Simplex]) -> Option<(f64, Point3, Point3)> {
+    let bounds_b: Vec<Bounds> = b
+        .iter()
+        .map(|sb| Bounds::around(sb.corners().into_iter()))
+        .collect();
+    let mut best: Option<(f64, Point3, Point3)> = None;
+
+    for sa in a.iter() {
+        let bounds_a = Bounds::around(sa.corners().into_iter());
+        for (sb, bounds) in b.iter().zip(bounds_b.iter()) {
+            if best
+                .as_ref()
+                .is_some_and(|(d, _, _)| bounds_a.distance_to(bounds) >= *d)
+            {
+                continue;
+            }
+
+            let mut consider = |pa: Point3, pb: Point3| {
+                let distance = pa.distance_to(&pb);
+                if best.as_ref().is_none_or(|(d, _, _)| distance < *d) {
+                    best = Some((distance, pa, pb));
+                }
+            };
+            for p in sa.corners() {
+                consider(p.clone(), sb.closest_point(p));
+            }
+            for p in sb.corners() {
+                consider(sa.closest_point(p), p.clone());
+            }
+            for (a0, a1) in sa.edges() {
+                for (b0, b1) in sb.edges() {
+                    let (pa, pb) = closest_points_between_segments(a0, a1, b0, b1);
+                    consider(pa, pb);
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_through_points() {
+        let points: Vec<Point3> = (0..12)
+            .map(|i| {
+                let theta = i as f64 * 0.3;
+                Point3::new(1.0 + 2.0 * theta.cos(), 5.0, -3.0 + 2.0 * theta.sin())
+            })
+            .collect();
+
+        let circle = fit_circle(&points).unwrap();
+        assert!((circle.radius - 2.0).abs() < 1e-9);
+        assert!(circle.center.distance_to(&Point3::new(1.0, 5.0, -3.0)) < 1e-9);
+        assert!((circle.normal.y.abs() - 1.0).abs() < 1e-9);
+
+        assert!(fit_line(&points).is_err());
+    }
+
+    #[test]
+    fn straight_lines() {
+        let points = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(3.0, 3.0, 3.0),
+        ];
+        let line = fit_line(&points).unwrap();
+        assert!((line.direction.x - 1.0 / 3.0_f64.sqrt()).abs() < 1e-12);
+        assert!(fit_circle(&points).is_err());
+
+        let mid = polyline_midpoint(&points).unwrap();
+        assert!(mid.distance_to(&Point3::new(1.5, 1.5, 1.5)) < 1e-12);
+    }
+
+    #[test]
+    fn closest_points_on_a_triangle() {
+        let a = Point3::new(0.0, 0.0, 0.0);
+        let b = Point3::new(4.0, 0.0, 0.0);
+        let c = Point3::new(0.0, 4.0, 0.0);
+
+        let above = closest_point_on_triangle(&a, &b, &c, &Point3::new(1.0, 1.0, 3.0));
+        assert!(above.distance_to(&Point3::new(1.0, 1.0, 0.0)) < 1e-12);
+
+        let past_hypotenuse = closest_point_on_triangle(&a, &b, &c, &Point3::new(3.0, 3.0, 0.0));
+        assert!(past_hypotenuse.distance_to(&Point3::new(2.0, 2.0, 0.0)) < 1e-12);
+
+        let past_corner = closest_point_on_triangle(&a, &b, &c, &Point3::new(-1.0, -2.0, 0.0));
+        assert!(past_corner.distance_to(&a) < 1e-12);
+    }
+
+    #[test]
+    fn rays_entering_a_box() {
+        let corners = [Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 1.0, 1.0)];
+        let bounds = Bounds::around(corners.iter());
+        let down = Vector3::new(0.0, 0.0, -1.0);
+
+        let from_above = bounds.entered_by(&Point3::new(1.0, 0.5, 5.0), &down);
+        assert!((from_above.unwrap() - 4.0).abs() < 1e-12);
+        let from_inside = bounds.entered_by(&Point3::new(1.0, 0.5, 0.5), &down);
+        assert_eq!(from_inside, Some(0.0));
+        assert!(bounds
+            .entered_by(&Point3::new(3.0, 0.5, 5.0), &down)
+            .is_none());
+        assert!(bounds
+            .entered_by(&Point3::new(1.0, 0.5, -1.0), &down)
+            .is_none());
+    }
+
+    #[test]
+    fn intersections() {
+        let line = plane_plane_intersection(&Plane::front(), &Plane::right()).unwrap();
+        assert!((line.direction.z.abs() - 1.0).abs() < 1e-12);
+        assert!(line.distance_to(&Point3::new(0.0, 0.0, 0.0)) < 1e-12);
+        assert!(plane_plane_intersection(&Plane::top(), &Plane::top()).is_none());
+
+        let vertical = InfiniteLine {
+            origin: Point3::new(2.0, 3.0, 7.0),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+        let pierce = line_plane_intersection(&vertical, &Plane::top()).unwrap();
+        assert!(pierce.distance_to(&Point3::new(2.0, 3.0, 0.0)) < 1e-12);
+
+        let skew = InfiniteLine {
+            origin: Point3::new(0.0, 0.0, 1.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        let (on_vertical, on_skew) = closest_points_between_lines(&vertical, &skew).unwrap();
+        assert!(on_vertical.distance_to(&Point3::new(2.0, 3.0, 1.0)) < 1e-12);
+        assert!(on_skew.distance_to(&Point3::new(2.0, 0.0, 1.0)) < 1e-12);
+    }
+}
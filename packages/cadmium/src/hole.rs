@@ -0,0 +1,546 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use truck_modeling::{builder, Edge, Vertex, Wire};
+use truck_polymesh::InnerSpace;
+use truck_polymesh::Point3 as TruckPoint3;
+use truck_polymesh::Rad;
+use truck_polymesh::Vector3 as TruckVector3;
+use truck_topology::Solid as TruckSolid;
+
+use crate::archetypes::{Point3, Vector3};
+use crate::error::CADmiumError;
+use crate::extrusion::Direction;
+use crate::project::{RealPlane, RealSketch};
+use crate::solid::Solid;
+
+// The punch is started a little above the face it is drilled into so that the
+// boolean doesn't have to deal with two coincident faces
+const HOLE_OVERSHOOT: f64 = 0.01;
+
+const INCH: f64 = 25.4;
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Hole {
+    pub sketch_id: String,
+    pub point_ids: Vec<u64>,
+    pub diameter: f64,
+    pub hole_type: HoleType,
+    pub depth: HoleDepth,
+    pub direction: Direction,
+    pub merge_scope: Vec<String>,
+    // e.g. "M6" or "1/4-20" when the hole was created from the standard table
+    pub designation: Option<String>,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum HoleType {
+    Simple,
+    Counterbore {
+        diameter: f64,
+        depth: f64,
+    },
+    Countersink {
+        diameter: f64,
+        angle: f64, // included angle of the cone, in radians
+    },
+    Tapped {
+        pitch: f64,
+        thread_depth: f64,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum HoleDepth {
+    Blind(f64),
+    ThroughAll,
+}
+
+#[derive(Tsify, Debug, Clone, Copy, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum HoleFit {
+    Close,
+    Normal,
+    Loose,
+}
+
+#[derive(Tsify, Debug, Clone, Copy, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum HoleKind {
+    Simple,
+    Counterbore,
+    Countersink,
+    Tapped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnitSystem {
+    Metric,
+    Imperial,
+}
+
+// Every dimension in this table is in mm, imperial sizes are converted on the way in
+#[derive(Debug, Clone)]
+pub struct HoleStandard {
+    pub designation: &'static str,
+    pub system: UnitSystem,
+    pub pitch: f64,
+    pub tap_drill: f64,
+    pub clearance: [f64; 3], // close, normal, loose
+    pub counterbore_diameter: f64,
+    pub counterbore_depth: f64,
+    pub countersink_diameter: f64,
+    pub countersink_angle: f64, // degrees
+}
+
+macro_rules! metric {
+    ($name:expr, $pitch:expr, $tap:expr, [$c:expr, $n:expr, $l:expr], $cbd:expr, $cbh:expr, $csd:expr) => {
+        HoleStandard {
+            designation: $name,
+            system: UnitSystem::Metric,
+            pitch: $pitch,
+            tap_drill: $tap,
+            clearance: [$c, $n, $l],
+            counterbore_diameter: $cbd,
+            counterbore_depth: $cbh,
+            countersink_diameter: $csd,
+            countersink_angle: 90.0,
+        }
+    };
+}
+
+macro_rules! imperial {
+    ($name:expr, $tpi:expr, $tap:expr, [$c:expr, $n:expr, $l:expr], $cbd:expr, $cbh:expr, $csd:expr) => {
+        HoleStandard {
+            designation: $name,
+            system: UnitSystem::Imperial,
+            pitch: INCH / $tpi,
+            tap_drill: $tap * INCH,
+            clearance: [$c * INCH, $n * INCH, $l * INCH],
+            counterbore_diameter: $cbd * INCH,
+            counterbore_depth: $cbh * INCH,
+            countersink_diameter: $csd * INCH,
+            countersink_angle: 82.0,
+        }
+    };
+}
+
+// Clearance holes follow ISO 273 (metric) and ASME B18.2.8 (imperial). Counterbores are
+// sized for socket head cap screws and countersinks for flat head screws.
+#[rustfmt::skip]
+pub const HOLE_STANDARDS: &[HoleStandard] = &[
+    metric!("M2", 0.4, 1.6, [2.2, 2.4, 2.6], 4.4, 2.2, 4.4),
+    metric!("M2.5", 0.45, 2.05, [2.7, 2.9, 3.1], 5.5, 2.7, 5.5),
+    metric!("M3", 0.5, 2.5, [3.2, 3.4, 3.6], 6.5, 3.3, 6.72),
+    metric!("M4", 0.7, 3.3, [4.3, 4.5, 4.8], 8.0, 4.4, 8.96),
+    metric!("M5", 0.8, 4.2, [5.3, 5.5, 5.8], 10.0, 5.4, 11.2),
+    metric!("M6", 1.0, 5.0, [6.4, 6.6, 7.0], 11.0, 6.5, 13.44),
+    metric!("M8", 1.25, 6.8, [8.4, 9.0, 10.0], 15.0, 8.6, 17.92),
+    metric!("M10", 1.5, 8.5, [10.5, 11.0, 12.0], 18.0, 10.8, 22.4),
+    metric!("M12", 1.75, 10.2, [13.0, 13.5, 14.5], 20.0, 13.0, 26.88),
+    metric!("M16", 2.0, 14.0, [17.0, 17.5, 18.5], 26.0, 17.5, 33.6),
+    metric!("M20", 2.5, 17.5, [21.0, 22.0, 24.0], 33.0, 21.5, 40.32),
+    imperial!("#4-40", 40.0, 0.0890, [0.1160, 0.1285, 0.1440], 0.219, 0.125, 0.255),
+    imperial!("#6-32", 32.0, 0.1065, [0.1440, 0.1610, 0.1695], 0.281, 0.151, 0.307),
+    imperial!("#8-32", 32.0, 0.1360, [0.1695, 0.1770, 0.1910], 0.312, 0.177, 0.359),
+    imperial!("#10-24", 24.0, 0.1495, [0.1960, 0.2010, 0.2210], 0.375, 0.203, 0.411),
+    imperial!("1/4-20", 20.0, 0.2010, [0.2570, 0.2660, 0.2810], 0.438, 0.265, 0.531),
+    imperial!("5/16-18", 18.0, 0.2570, [0.3230, 0.3320, 0.3440], 0.531, 0.328, 0.656),
+    imperial!("3/8-16", 16.0, 0.3125, [0.3860, 0.3970, 0.4060], 0.625, 0.390, 0.781),
+    imperial!("1/2-13", 13.0, 0.4219, [0.5156, 0.5312, 0.5469], 0.812, 0.515, 1.031),
+];
+
+pub fn find_standard(designation: &str) -> Option<&'static HoleStandard> {
+    HOLE_STANDARDS
+        .iter()
+        .find(|standard| standard.designation == designation)
+}
+
+impl Hole {
+    pub fn new(
+        sketch_id: String,
+        point_ids: Vec<u64>,
+        diameter: f64,
+        hole_type: HoleType,
+        depth: HoleDepth,
+        merge_scope: Vec<String>,
+    ) -> Self {
+        Hole {
+            sketch_id,
+            point_ids,
+            diameter,
+            hole_type,
+            depth,
+            direction: Direction::NegativeNormal,
+            merge_scope,
+            designation: None,
+        }
+    }
+
+    pub fn from_standard(
+        sketch_id: String,
+        point_ids: Vec<u64>,
+        designation: &str,
+        fit: HoleFit,
+        kind: HoleKind,
+        depth: HoleDepth,
+        merge_scope: Vec<String>,
+    ) -> Result<Self, CADmiumError> {
+        let standard = find_standard(designation)
+            .ok_or(CADmiumError::HoleStandardNotFound(designation.to_owned()))?;
+
+        let clearance = match fit {
+            HoleFit::Close => standard.clearance[0],
+            HoleFit::Normal => standard.clearance[1],
+            HoleFit::Loose => standard.clearance[2],
+        };
+
+        let (diameter, hole_type) = match kind {
+            HoleKind::Simple => (clearance, HoleType::Simple),
+            HoleKind::Counterbore => (
+                clearance,
+                HoleType::Counterbore {
+                    diameter: standard.counterbore_diameter,
+                    depth: standard.counterbore_depth,
+                },
+            ),
+            HoleKind::Countersink => (
+                clearance,
+                HoleType::Countersink {
+                    diameter: standard.countersink_diameter,
+                    angle: standard.countersink_angle.to_radians(),
+                },
+            ),
+            HoleKind::Tapped => {
+                // by convention the full thread stops a few pitches short of a blind hole's bottom
+                let thread_depth = match depth {
+                    HoleDepth::Blind(d) => (d - 3.0 * standard.pitch).max(0.0),
+                    HoleDepth::ThroughAll => 0.0,
+                };
+                (
+                    standard.tap_drill,
+                    HoleType::Tapped {
+                        pitch: standard.pitch,
+                        thread_depth,
+                    },
+                )
+            }
+        };
+
+        let mut hole = Hole::new(
+            sketch_id,
+            point_ids,
+            diameter,
+            hole_type,
+            depth,
+            merge_scope,
+        );
+        hole.designation = Some(designation.to_owned());
+        Ok(hole)
+    }
+
+    // A human readable hole callout, like what you would put on a drawing
+    pub fn callout(&self) -> String {
+        let depth = match self.depth {
+            HoleDepth::Blind(d) => format!("↧{:.2}", d),
+            HoleDepth::ThroughAll => "THRU".to_owned(),
+        };
+
+        let base = match &self.hole_type {
+            HoleType::Simple => format!("⌀{:.2} {}", self.diameter, depth),
+            HoleType::Counterbore {
+                diameter,
+                depth: cb_depth,
+            } => format!(
+                "⌀{:.2} {}, ⌴⌀{:.2} ↧{:.2}",
+                self.diameter, depth, diameter, cb_depth
+            ),
+            HoleType::Countersink { diameter, angle } => format!(
+                "⌀{:.2} {}, ⌵⌀{:.2} X {:.0}°",
+                self.diameter,
+                depth,
+                diameter,
+                angle.to_degrees()
+            ),
+            HoleType::Tapped {
+                pitch,
+                thread_depth,
+            } => {
+                let thread = match &self.designation {
+                    Some(designation) => designation.clone(),
+                    None => format!("P{:.2}", pitch),
+                };
+                if *thread_depth > 0.0 {
+                    format!(
+                        "{} ↧{:.2}, ⌀{:.2} {}",
+                        thread, thread_depth, self.diameter, depth
+                    )
+                } else {
+                    format!("{} THRU, ⌀{:.2} {}", thread, self.diameter, depth)
+                }
+            }
+        };
+
+        match (&self.designation, &self.hole_type) {
+            (Some(designation), HoleType::Simple)
+            | (Some(designation), HoleType::Counterbore { .. })
+            | (Some(designation), HoleType::Countersink { .. }) => {
+                format!("{} ({})", base, designation)
+            }
+            _ => base,
+        }
+    }
+
+    // The hole's cross section as (radius, depth) pairs, starting and ending on the axis.
+    // Depth is measured from the face the hole is drilled into, positive going into the part
+    pub fn profile(&self, depth: f64) -> Result<Vec<(f64, f64)>, CADmiumError> {
+        let r = self.diameter / 2.0;
+        let top = -HOLE_OVERSHOOT;
+
+        let mut profile = vec![(0.0, top)];
+        match &self.hole_type {
+            HoleType::Simple | HoleType::Tapped { .. } => {
+                profile.push((r, top));
+            }
+            HoleType::Counterbore {
+                diameter,
+                depth: cb_depth,
+            } => {
+                if *diameter <= self.diameter {
+                    return Err(CADmiumError::InvalidHole(format!(
+                        "the counterbore diameter {} must be larger than the hole diameter {}",
+                        diameter, self.diameter
+                    )));
+                }
+                if *cb_depth >= depth {
+                    return Err(CADmiumError::InvalidHole(format!(
+                        "the counterbore depth {} must be less than the hole depth {}",
+                        cb_depth, depth
+                    )));
+                }
+                profile.push((diameter / 2.0, top));
+                profile.push((diameter / 2.0, *cb_depth));
+                profile.push((r, *cb_depth));
+            }
+            HoleType::Countersink { diameter, angle } => {
+                if *diameter <= self.diameter {
+                    return Err(CADmiumError::InvalidHole(format!(
+                        "the countersink diameter {} must be larger than the hole diameter {}",
+                        diameter, self.diameter
+                    )));
+                }
+                let half_angle_tan = (angle / 2.0).tan();
+                let cs_radius = diameter / 2.0;
+                let cs_depth = (cs_radius - r) / half_angle_tan;
+                if cs_depth >= depth {
+                    return Err(CADmiumError::InvalidHole(format!(
+                        "the countersink depth {} must be less than the hole depth {}",
+                        cs_depth, depth
+                    )));
+                }
+                profile.push((cs_radius + HOLE_OVERSHOOT * half_angle_tan, top));
+                profile.push((r, cs_depth));
+            }
+        }
+        profile.push((r, depth));
+        profile.push((0.0, depth));
+
+        Ok(profile)
+    }
+
+    pub fn direction_vector(&self, plane: &RealPlane) -> Vector3 {
+        match &self.direction {
+            Direction::Normal => plane.plane.tertiary.clone(),
+            Direction::NegativeNormal => plane.plane.tertiary.times(-1.0),
+            Direction::Specified(vector) => vector.clone(),
+        }
+    }
+
+    pub fn locations(&self, sketch: &RealSketch) -> Result<Vec<Point3>, CADmiumError> {
+        self.point_ids
+            .iter()
+            .map(|point_id| {
+                sketch
+                    .points
+                    .get(point_id)
+                    .cloned()
+                    .ok_or(CADmiumError::HolePointNotFound(*point_id))
+            })
+            .collect()
+    }
+
+    // Builds one solid per hole location, shaped like the material that the hole removes
+    pub fn punches(
+        &self,
+        plane: &RealPlane,
+        sketch: &RealSketch,
+        targets: &Vec<&Solid>,
+    ) -> Result<
+        Vec<TruckSolid<TruckPoint3, truck_modeling::Curve, truck_modeling::Surface>>,
+        CADmiumError,
+    > {
+        let direction = self.direction_vector(plane);
+        let axis = TruckVector3::new(direction.x, direction.y, direction.z).normalize();
+
+        // any vector perpendicular to the axis will do for sweeping out the profile
+        let radial = if axis.cross(TruckVector3::unit_z()).magnitude() > 0.1 {
+            axis.cross(TruckVector3::unit_z()).normalize()
+        } else {
+            axis.cross(TruckVector3::unit_x()).normalize()
+        };
+
+        let mut punches = vec![];
+        for location in self.locations(sketch)? {
+            let origin = TruckPoint3::new(location.x, location.y, location.z);
+
+            let depth = match self.depth {
+                HoleDepth::Blind(d) => d,
+                HoleDepth::ThroughAll => {
+                    // go just past the deepest vertex of anything we might be drilling into
+                    let mut deepest: f64 = 0.0;
+                    for target in targets.iter() {
+                        for v in target.vertices.iter() {
+                            let along =
+                                TruckVector3::new(v.x - origin.x, v.y - origin.y, v.z - origin.z)
+                                    .dot(axis);
+                            deepest = deepest.max(along);
+                        }
+                    }
+                    deepest + 1.0
+                }
+            };
+
+            let vertices: Vec<Vertex> = self
+                .profile(depth)?
+                .iter()
+                .map(|(r, t)| builder::vertex(origin + radial * *r + axis * *t))
+                .collect();
+            let edges: Vec<Edge> = vertices
+                .windows(2)
+                .map(|pair| builder::line(&pair[0], &pair[1]))
+                .collect();
+            let wire: Wire = edges.into_iter().collect();
+
+            // the profile runs from the top of the axis to the bottom, so we sweep
+            // around the axis pointing back up for the shell to face outwards
+            let shell = builder::cone(&wire, -axis, Rad(2.0 * PI));
+            punches.push(TruckSolid::new(vec![shell]));
+        }
+
+        Ok(punches)
+    }
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RealHole {
+    pub locations: Vec<Point3>,
+    pub axis: Vector3,
+    pub diameter: f64,
+    pub callout: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_sizes() {
+        let m6 = find_standard("M6").unwrap();
+        assert_eq!(m6.system, UnitSystem::Metric);
+        assert_eq!(m6.clearance[1], 6.6);
+
+        let quarter = find_standard("1/4-20").unwrap();
+        assert_eq!(quarter.system, UnitSystem::Imperial);
+        assert!((quarter.pitch - 1.27).abs() < 1e-9);
+
+        assert!(find_standard("M7").is_none());
+    }
+
+    #[test]
+    fn counterbore_from_standard() {
+        let hole = Hole::from_standard(
+            "Sketch-0".to_owned(),
+            vec![1],
+            "M6",
+            HoleFit::Normal,
+            HoleKind::Counterbore,
+            HoleDepth::ThroughAll,
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(hole.diameter, 6.6);
+        let profile = hole.profile(20.0).unwrap();
+        assert_eq!(profile.len(), 6);
+        assert_eq!(profile[1].0, 5.5);
+        assert_eq!(profile[3], (3.3, 6.5));
+        assert_eq!(hole.callout(), "⌀6.60 THRU, ⌴⌀11.00 ↧6.50 (M6)");
+
+        // the counterbore has to fit inside the hole's depth, and be wider than it
+        assert!(matches!(
+            hole.profile(6.5),
+            Err(CADmiumError::InvalidHole(_))
+        ));
+        let narrow = Hole {
+            hole_type: HoleType::Counterbore {
+                diameter: 6.6,
+                depth: 6.5,
+            },
+            ..hole
+        };
+        assert!(matches!(
+            narrow.profile(20.0),
+            Err(CADmiumError::InvalidHole(_))
+        ));
+    }
+
+    #[test]
+    fn countersink_from_standard() {
+        let hole = Hole::from_standard(
+            "Sketch-0".to_owned(),
+            vec![1],
+            "M6",
+            HoleFit::Normal,
+            HoleKind::Countersink,
+            HoleDepth::ThroughAll,
+            vec![],
+        )
+        .unwrap();
+
+        // a 90 degree countersink 13.44 across goes 3.42 deep into a 6.6 hole
+        let profile = hole.profile(20.0).unwrap();
+        assert_eq!(profile.len(), 5);
+        assert!((profile[2].1 - 3.42).abs() < 1e-9);
+        assert!(matches!(
+            hole.profile(3.0),
+            Err(CADmiumError::InvalidHole(_))
+        ));
+    }
+
+    #[test]
+    fn tapped_from_standard() {
+        let hole = Hole::from_standard(
+            "Sketch-0".to_owned(),
+            vec![1],
+            "M4",
+            HoleFit::Normal,
+            HoleKind::Tapped,
+            HoleDepth::Blind(10.0),
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(hole.diameter, 3.3);
+        match hole.hole_type {
+            HoleType::Tapped { thread_depth, .. } => assert!((thread_depth - 7.9).abs() < 1e-9),
+            _ => panic!("Expected a tapped hole"),
+        }
+    }
+}
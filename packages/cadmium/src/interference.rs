@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use truck_meshalgo::tessellation::{MeshableShape, MeshedShape};
+use truck_shapeops::and as solid_and;
+
+use crate::archetypes::Point3;
+use crate::geometry::{closest_points, Bounds, Simplex};
+use crate::solid::{mesh_volume, Solid};
+
+// the same tolerance the booleans in the rest of the crate use
+const BOOLEAN_TOLERANCE: f64 = 0.1;
+// overlaps smaller than this are just faces touching
+const VOLUME_EPSILON: f64 = 1e-6;
+// meshes closer than this meet
+const DISTANCE_EPSILON: f64 = 1e-9;
+
+// Two bodies that overlap, and by how much. The volume is None when the bodies' meshes meet
+// but intersecting them failed, so they may overlap by an amount that couldn't be measured
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Interference {
+    pub a: String,
+    pub b: String,
+    pub volume: Option<f64>,
+}
+
+// The gap between two bodies that don't overlap, and where it is narrowest
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Clearance {
+    pub a: String,
+    pub b: String,
+    pub distance: f64,
+    pub point_a: Point3,
+    pub point_b: Point3,
+}
+
+// Every pair of bodies appears in exactly one of the two lists
+#[derive(Tsify, Debug, Clone, Default, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct InterferenceReport {
+    pub interferences: Vec<Interference>,
+    pub clearances: Vec<Clearance>,
+}
+
+struct Body<'a> {
+    id: &'a str,
+    solid: &'a Solid,
+    triangles: Vec<Simplex>,
+    bounds: Bounds,
+}
+
+impl<'a> Body<'a> {
+    fn new(id: &'a str, solid: &'a Solid, tolerance: f64) -> Self {
+        let triangles: Vec<Simplex> = solid
+            .face_meshes(tolerance)
+            .iter()
+            .flat_map(|mesh| {
+                mesh.triangle_points()
+                    .map(|(a, b, c)| Simplex::Triangle(a.clone(), b.clone(), c.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let bounds = Bounds::around(triangles.iter().flat_map(Simplex::corners));
+        Body {
+            id,
+            solid,
+            triangles,
+            bounds,
+        }
+    }
+}
+
+// Compares every pair of bodies. Overlapping bodies are intersected to find out how much
+// they share; the rest are searched triangle by triangle for their closest points, using
+// meshes made with `tolerance`
+pub fn check(bodies: &[(String, &Solid)], tolerance: f64) -> InterferenceReport {
+    let bodies: Vec<Body> = bodies
+        .iter()
+        .map(|(id, solid)| Body::new(id, solid, tolerance))
+        .collect();
+
+    let mut report = InterferenceReport::default();
+    for (i, a) in bodies.iter().enumerate() {
+        for b in bodies.iter().skip(i + 1) {
+            let volume = if a.bounds.distance_to(&b.bounds) == 0.0 {
+                overlap_volume(a.solid, b.solid, tolerance)
+            } else {
+                Some(0.0)
+            };
+            compare(a, b, volume, &mut report);
+        }
+    }
+    report
+}
+
+// Adds a pair of bodies to the report, given the volume they share, or None if it couldn't
+// be found
+fn compare(a: &Body, b: &Body, volume: Option<f64>, report: &mut InterferenceReport) {
+    let interference = |volume| Interference {
+        a: a.id.to_owned(),
+        b: b.id.to_owned(),
+        volume,
+    };
+
+    if let Some(volume) = volume.filter(|volume| *volume > VOLUME_EPSILON) {
+        report.interferences.push(interference(Some(volume)));
+        return;
+    }
+
+    // without the boolean, meshes that meet, or one body inside the other, can't be told
+    // apart from bodies that overlap
+    let nested = a.bounds.contains(&b.bounds) || b.bounds.contains(&a.bounds);
+    let closest = closest_points(&a.triangles, &b.triangles);
+    match closest {
+        Some((distance, ..)) if volume.is_none() && (nested || distance < DISTANCE_EPSILON) => {
+            report.interferences.push(interference(None));
+        }
+        Some((distance, point_a, point_b)) => report.clearances.push(Clearance {
+            a: a.id.to_owned(),
+            b: b.id.to_owned(),
+            distance,
+            point_a,
+            point_b,
+        }),
+        // a body with nothing to mesh has no clearance to speak of
+        None => {}
+    }
+}
+
+// The volume of the intersection of two solids, or None if the boolean fails
+fn overlap_volume(a: &Solid, b: &Solid, tolerance: f64) -> Option<f64> {
+    solid_and(&a.truck_solid, &b.truck_solid, BOOLEAN_TOLERANCE)
+        .map(|shared| mesh_volume(&shared.triangulation(tolerance).to_polygon()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbench::tests::add_block;
+    use crate::workbench::Workbench;
+
+    use super::*;
+
+    // three 10 x 10 x 10 blocks: the first two 5 apart, the third overlapping the first
+    // and 1 away from the second
+    fn three_blocks() -> Workbench {
+        let mut wb = Workbench::new("Test Workbench");
+        let corners = [(0.0, 0.0), (15.0, 0.0), (4.0, 4.0)];
+        for (index, corner) in corners.into_iter().enumerate() {
+            let name = format!("Ext{}", index);
+            add_block(&mut wb, &name, corner, [10.0, 10.0, 10.0]);
+        }
+        wb
+    }
+
+    #[test]
+    fn overlapping_blocks_interfere_and_the_rest_have_clearance() {
+        let wb = three_blocks();
+        let report = wb.realize(1000).interference(0.01);
+
+        assert_eq!(report.interferences.len(), 1);
+        let overlap = &report.interferences[0];
+        assert_eq!(
+            (overlap.a.as_str(), overlap.b.as_str()),
+            ("Extrusion-0:0", "Extrusion-2:0")
+        );
+        assert!((overlap.volume.unwrap() - 360.0).abs() < 1.0);
+
+        assert_eq!(report.clearances.len(), 2);
+        let gap = |a: &str, b: &str| {
+            report
+                .clearances
+                .iter()
+                .find(|c| c.a == a && c.b == b)
+                .unwrap()
+                .distance
+        };
+        assert!((gap("Extrusion-0:0", "Extrusion-1:0") - 5.0).abs() < 1e-6);
+        assert!((gap("Extrusion-1:0", "Extrusion-2:0") - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bodies_that_cant_be_intersected_are_not_given_a_clearance() {
+        let wb = three_blocks();
+        let realization = wb.realize(1000);
+        let body = |id: &'static str| Body::new(id, &realization.solids[id], 0.01);
+        let (first, second, third) = (
+            body("Extrusion-0:0"),
+            body("Extrusion-1:0"),
+            body("Extrusion-2:0"),
+        );
+
+        // as if the boolean had failed for every pair
+        let mut report = InterferenceReport::default();
+        compare(&first, &third, None, &mut report);
+        compare(&first, &second, None, &mut report);
+
+        assert_eq!(report.interferences.len(), 1);
+        assert_eq!(report.interferences[0].b, "Extrusion-2:0");
+        assert!(report.interferences[0].volume.is_none());
+        // bodies that are apart still are
+        assert_eq!(report.clearances.len(), 1);
+        assert!((report.clearances[0].distance - 5.0).abs() < 1e-6);
+    }
+}
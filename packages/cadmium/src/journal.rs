@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+use crate::project::Project;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // milliseconds since the Unix epoch
+    pub timestamp: f64,
+    pub message: Message,
+}
+
+// Every message applied to a project, in order. Replaying it on a new project with the
+// same name rebuilds the model exactly, so it is kept apart from the project's own JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Journal {
+    pub project_name: String,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new(project_name: &str) -> Self {
+        Journal {
+            project_name: project_name.to_owned(),
+            entries: vec![],
+        }
+    }
+
+    pub fn record(&mut self, message: &Message) {
+        self.entries.push(JournalEntry {
+            timestamp: now(),
+            message: message.clone(),
+        });
+    }
+
+    pub fn json(&self) -> String {
+        let result = serde_json::to_string(self);
+        match result {
+            Ok(json) => json,
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Journal, anyhow::Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    // Builds the project again from nothing. The replayed project has its own journal,
+    // with the same entries as this one
+    pub fn replay(&self) -> Result<Project, anyhow::Error> {
+        let mut project = Project::new(&self.project_name);
+        for (index, entry) in self.entries.iter().enumerate() {
+            entry
+                .message
+                .handle(&mut project)
+                .map_err(|e| e.context(format!("Journal entry {} failed to replay", index)))?;
+        }
+        project.journal.entries = self.entries.clone();
+        Ok(project)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaying_a_journal_rebuilds_the_project() {
+        let mut p = Project::new("Test Project");
+        let messages = [
+            r#"{ "NewSketchOnPlane": { "workbench_id": 0, "sketch_name": "Sketch 1", "plane_id": "Plane-0" } }"#,
+            r#"{ "NewPointOnSketch2": { "workbench_id": 0, "sketch_id": "Sketch-0", "x": 0.0, "y": 0.0, "hidden": false } }"#,
+            r#"{ "NewPointOnSketch2": { "workbench_id": 0, "sketch_id": "Sketch-0", "x": 1.0, "y": 0.0, "hidden": false } }"#,
+            r#"{ "NewLineOnSketch": { "workbench_id": 0, "sketch_id": "Sketch-0", "start_point_id": 1, "end_point_id": 2 } }"#,
+            r#""Undo""#,
+            r#"{ "RenameWorkbench": { "workbench_id": 0, "new_name": "Part" } }"#,
+        ];
+        for message in messages {
+            Message::from_json(message).unwrap().handle(&mut p).unwrap();
+        }
+
+        // messages that fail are left out
+        assert!(Message::Redo.handle(&mut p).is_err());
+        assert_eq!(p.journal.entries.len(), messages.len());
+
+        let journal = Journal::from_json(&p.journal.json()).unwrap();
+        let replayed = journal.replay().unwrap();
+        // compared as values, since the order of map entries in the JSON isn't fixed
+        let as_value = |project: &Project| -> serde_json::Value {
+            serde_json::from_str(&project.json()).unwrap()
+        };
+        assert_eq!(as_value(&replayed), as_value(&p));
+        assert_eq!(replayed.journal.entries.len(), messages.len());
+    }
+}
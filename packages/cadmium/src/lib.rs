@@ -3,19 +3,37 @@ use wasm_bindgen::prelude::*;
 extern crate console_error_panic_hook;
 
 pub mod archetypes;
+pub mod assembly;
+pub mod changes;
+pub mod datum;
+pub mod dependency;
 pub mod error;
+pub mod export;
 pub mod extrusion;
+pub mod geometry;
+pub mod hole;
+pub mod interference;
+pub mod journal;
+pub mod mass;
+pub mod material;
+pub mod mates;
+pub mod measure;
 pub mod message;
+pub mod naming;
+pub mod picking;
 pub mod project;
 pub mod realization;
 pub mod solid;
 pub mod sketch;
 pub mod step;
+pub mod undo;
 pub mod workbench;
 
 #[wasm_bindgen]
 pub struct Project {
     native: project::Project,
+    // called with a ChangeSet after each message that changes something
+    subscribers: Vec<js_sys::Function>,
 }
 
 #[wasm_bindgen]
@@ -26,6 +44,7 @@ impl Project {
 
         Project {
             native: project::Project::new(name),
+            subscribers: vec![],
         }
     }
 
@@ -52,7 +71,29 @@ impl Project {
     #[wasm_bindgen]
     pub fn from_json(json: String) -> Project {
         let p = project::Project::from_json(&json);
-        Project { native: p }
+        Project {
+            native: p,
+            subscribers: vec![],
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn journal_json(&self) -> String {
+        self.native.journal().json()
+    }
+
+    // Rebuilds a project from a journal saved with journal_json
+    #[wasm_bindgen]
+    pub fn replay(journal: String) -> Result<Project, JsError> {
+        let journal =
+            journal::Journal::from_json(&journal).map_err(|e| JsError::new(&e.to_string()))?;
+        let p = journal
+            .replay()
+            .map_err(|e| JsError::new(&format!("{:#}", e)))?;
+        Ok(Project {
+            native: p,
+            subscribers: vec![],
+        })
     }
 
     #[wasm_bindgen]
@@ -61,23 +102,90 @@ impl Project {
     }
 
     #[wasm_bindgen]
-    pub fn get_realization(&self, workbench_id: u32, max_steps: u32) -> Realization {
+    pub fn get_realization(
+        &self,
+        workbench_id: u32,
+        max_steps: u32,
+    ) -> Result<Realization, JsError> {
         let realized = self
             .native
-            .get_realization(workbench_id as u64, max_steps as u64);
+            .get_realization(workbench_id as u64, max_steps as u64)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+
+        Ok(Realization { native: realized })
+    }
 
-        Realization { native: realized }
+    #[wasm_bindgen]
+    pub fn get_assembly_realization(
+        &self,
+        assembly_id: u32,
+    ) -> Result<AssemblyRealization, JsError> {
+        let realized = assembly::RealAssembly::new(&self.native, assembly_id as u64)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(AssemblyRealization { native: realized })
     }
 
     #[wasm_bindgen]
-    pub fn get_workbench(&self, workbench_index: u32) -> String {
-        let wb = &self.native.workbenches[workbench_index as usize];
-        wb.json()
+    pub fn get_workbench(&self, workbench_id: u32) -> Result<String, JsError> {
+        let wb = self
+            .native
+            .get_workbench_by_id(workbench_id as u64)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(wb.json())
+    }
+
+    #[wasm_bindgen]
+    pub fn get_dependency_graph(
+        &self,
+        workbench_id: u32,
+    ) -> Result<dependency::DependencyGraph, JsError> {
+        let wb = self
+            .native
+            .get_workbench_by_id(workbench_id as u64)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(wb.dependency_graph())
+    }
+
+    // In the order the workbenches are listed
+    #[wasm_bindgen(getter)]
+    pub fn workbench_ids(&self) -> Vec<u32> {
+        self.native
+            .workbenches
+            .iter()
+            .map(|wb| wb.id as u32)
+            .collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn active_workbench(&self) -> u32 {
+        self.native.active_workbench as u32
     }
 
     #[wasm_bindgen]
     pub fn send_message(&mut self, message: Message) -> MessageResult {
-        message.handle(&mut self.native).into()
+        if self.subscribers.is_empty() {
+            return message.handle(&mut self.native).into();
+        }
+
+        let (result, changes) = message.handle_with_changes(&mut self.native);
+        if !changes.is_empty() {
+            let changes = JsValue::from(changes);
+            for callback in self.subscribers.iter() {
+                // a subscriber that throws shouldn't stop the others hearing about it
+                let _ = callback.call1(&JsValue::NULL, &changes);
+            }
+        }
+        result.into()
+    }
+
+    #[wasm_bindgen]
+    pub fn subscribe(&mut self, callback: js_sys::Function) {
+        self.subscribers.push(callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn unsubscribe_all(&mut self) {
+        self.subscribers.clear();
     }
 
     // #[wasm_bindgen(getter)]
@@ -116,4 +224,95 @@ impl Realization {
     pub fn solid_to_step(&self, solid_name: String) -> String {
         self.native.solid_to_step(&solid_name)
     }
+
+    #[wasm_bindgen]
+    pub fn interference(&self, tolerance: f64) -> interference::InterferenceReport {
+        self.native.interference(tolerance)
+    }
+
+    #[wasm_bindgen]
+    pub fn solid_mass_properties(
+        &self,
+        solid_name: String,
+        density: Option<f64>,
+        tolerance: f64,
+    ) -> Result<mass::MassProperties, JsError> {
+        self.native
+            .solid_mass_properties(&solid_name, density, tolerance)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn mass_properties(&self, density: Option<f64>, tolerance: f64) -> mass::MassProperties {
+        self.native.mass_properties(density, tolerance)
+    }
+
+    #[wasm_bindgen]
+    pub fn pick(
+        &self,
+        origin: archetypes::Point3,
+        direction: archetypes::Vector3,
+        tolerance: f64,
+        spread: f64,
+    ) -> picking::PickResult {
+        self.native.pick(&origin, &direction, tolerance, spread)
+    }
+
+    #[wasm_bindgen]
+    pub fn measure(
+        &self,
+        a: measure::EntityReference,
+        b: Option<measure::EntityReference>,
+    ) -> Result<measure::Measurement, JsError> {
+        self.native
+            .measure(&a, b.as_ref())
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn to_glb(&self) -> Vec<u8> {
+        self.native.to_glb()
+    }
+
+    #[wasm_bindgen]
+    pub fn to_3mf(&self) -> Vec<u8> {
+        self.native.to_3mf()
+    }
+}
+
+#[wasm_bindgen]
+pub struct AssemblyRealization {
+    native: assembly::RealAssembly,
+}
+
+#[wasm_bindgen]
+impl AssemblyRealization {
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        let result = serde_json::to_string(&self.native);
+        match result {
+            Ok(json) => json,
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn to_step(&self) -> String {
+        self.native.to_step_string()
+    }
+
+    #[wasm_bindgen]
+    pub fn to_glb(&self) -> Vec<u8> {
+        self.native.to_glb()
+    }
+
+    #[wasm_bindgen]
+    pub fn to_3mf(&self) -> Vec<u8> {
+        self.native.to_3mf()
+    }
+
+    #[wasm_bindgen]
+    pub fn interference(&self, tolerance: f64) -> interference::InterferenceReport {
+        self.native.interference(tolerance)
+    }
 }
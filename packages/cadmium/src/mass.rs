@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use truck_polymesh::PolygonMesh;
+
+use crate::archetypes::{Point3, Vector3};
+
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MassProperties {
+    pub volume: f64,
+    pub mass: f64,
+    pub surface_area: f64,
+    pub center_of_mass: Point3,
+    // the inertia tensor about the center of mass, row by row
+    pub inertia: [[f64; 3]; 3],
+    // smallest first, each with the unit axis it turns about
+    pub principal_moments: [f64; 3],
+    pub principal_axes: [Vector3; 3],
+}
+
+// Volume integrals of 1, x, y, z, x², y², z², xy, yz and zx over one or more closed meshes,
+// each weighted by its density. See David Eberly, "Polyhedral Mass Properties (Revisited)"
+#[derive(Debug, Clone, Default)]
+pub struct MassIntegrals {
+    volume: f64,
+    weighted: [f64; 10],
+    surface_area: f64,
+}
+
+// Eberly's subexpressions for one coordinate of a triangle's corners
+fn subexpressions(w0: f64, w1: f64, w2: f64) -> (f64, f64, f64, [f64; 3]) {
+    let temp0 = w0 + w1;
+    let f1 = temp0 + w2;
+    let temp1 = w0 * w0;
+    let temp2 = temp1 + w1 * temp0;
+    let f2 = temp2 + w2 * f1;
+    let f3 = w0 * temp1 + w1 * temp2 + w2 * f2;
+    let g = [
+        f2 + w0 * (f1 + w0),
+        f2 + w1 * (f1 + w1),
+        f2 + w2 * (f1 + w2),
+    ];
+    (f1, f2, f3, g)
+}
+
+impl MassIntegrals {
+    // The mesh must be closed. Its triangles may face either way, as long as they agree
+    pub fn add_mesh(&mut self, mesh: &PolygonMesh, density: f64) {
+        let mut integrals = [0.0; 10];
+        let mut area = 0.0;
+
+        for [i0, i1, i2] in mesh.tri_faces().iter() {
+            let p0 = mesh.positions()[i0.pos];
+            let p1 = mesh.positions()[i1.pos];
+            let p2 = mesh.positions()[i2.pos];
+
+            let (a1, b1, c1) = (p1.x - p0.x, p1.y - p0.y, p1.z - p0.z);
+            let (a2, b2, c2) = (p2.x - p0.x, p2.y - p0.y, p2.z - p0.z);
+            let d0 = b1 * c2 - b2 * c1;
+            let d1 = a2 * c1 - a1 * c2;
+            let d2 = a1 * b2 - a2 * b1;
+            area += (d0 * d0 + d1 * d1 + d2 * d2).sqrt() / 2.0;
+
+            let (f1x, f2x, f3x, gx) = subexpressions(p0.x, p1.x, p2.x);
+            let (_, f2y, f3y, gy) = subexpressions(p0.y, p1.y, p2.y);
+            let (_, f2z, f3z, gz) = subexpressions(p0.z, p1.z, p2.z);
+
+            integrals[0] += d0 * f1x;
+            integrals[1] += d0 * f2x;
+            integrals[2] += d1 * f2y;
+            integrals[3] += d2 * f2z;
+            integrals[4] += d0 * f3x;
+            integrals[5] += d1 * f3y;
+            integrals[6] += d2 * f3z;
+            integrals[7] += d0 * (p0.y * gx[0] + p1.y * gx[1] + p2.y * gx[2]);
+            integrals[8] += d1 * (p0.z * gy[0] + p1.z * gy[1] + p2.z * gy[2]);
+            integrals[9] += d2 * (p0.x * gz[0] + p1.x * gz[1] + p2.x * gz[2]);
+        }
+
+        let factors = [
+            1.0 / 6.0,
+            1.0 / 24.0,
+            1.0 / 24.0,
+            1.0 / 24.0,
+            1.0 / 60.0,
+            1.0 / 60.0,
+            1.0 / 60.0,
+            1.0 / 120.0,
+            1.0 / 120.0,
+            1.0 / 120.0,
+        ];
+        // inward-facing triangles give everything the opposite sign
+        let sign = integrals[0].signum();
+        for ((total, integral), factor) in self.weighted.iter_mut().zip(integrals).zip(factors) {
+            *total += sign * integral * factor * density;
+        }
+        self.volume += sign * integrals[0] * factors[0];
+        self.surface_area += area;
+    }
+
+    pub fn properties(&self) -> MassProperties {
+        let w = &self.weighted;
+        let mass = w[0];
+        let (x, y, z) = if mass > 0.0 {
+            (w[1] / mass, w[2] / mass, w[3] / mass)
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+
+        let xx = w[5] + w[6] - mass * (y * y + z * z);
+        let yy = w[4] + w[6] - mass * (z * z + x * x);
+        let zz = w[4] + w[5] - mass * (x * x + y * y);
+        let xy = -(w[7] - mass * x * y);
+        let yz = -(w[8] - mass * y * z);
+        let zx = -(w[9] - mass * z * x);
+        let inertia = [[xx, xy, zx], [xy, yy, yz], [zx, yz, zz]];
+
+        let (principal_moments, axes) = symmetric_eigen(inertia);
+        MassProperties {
+            volume: self.volume,
+            mass,
+            surface_area: self.surface_area,
+            center_of_mass: Point3::new(x, y, z),
+            inertia,
+            principal_moments,
+            principal_axes: axes.map(|[x, y, z]| Vector3::new(x, y, z)),
+        }
+    }
+}
+
+// The eigenvalues of a symmetric matrix, smallest first, and their unit eigenvectors,
+// found with Jacobi rotations
+fn symmetric_eigen(m: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut a = m;
+    // the columns are the eigenvectors
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let scale: f64 = m.iter().flatten().map(|x| x * x).sum();
+
+    for _ in 0..50 {
+        let off_diagonal = a[0][1].powi(2) + a[0][2].powi(2) + a[1][2].powi(2);
+        if off_diagonal <= 1e-30 * scale {
+            break;
+        }
+
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q] == 0.0 {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            let rotate = |x: f64, y: f64| (c * x - s * y, s * x + c * y);
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                (row[p], row[q]) = rotate(row[p], row[q]);
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for k in 0..3 {
+                (a[p][k], a[q][k]) = rotate(row_p[k], row_q[k]);
+            }
+        }
+    }
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|&i, &j| a[i][i].total_cmp(&a[j][j]));
+    (
+        order.map(|i| a[i][i]),
+        order.map(|i| [v[0][i], v[1][i], v[2][i]]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbench::tests::block;
+
+    #[test]
+    fn a_block_has_the_textbook_mass_properties() {
+        let (wb, _) = block();
+        let realized = wb.realize(1000);
+        let props = realized
+            .solid_mass_properties("Extrusion-0:0", Some(2.0), 0.01)
+            .unwrap();
+        assert!((props.volume - 8000.0).abs() < 1e-6);
+        assert!((props.mass - 16000.0).abs() < 1e-6);
+        assert!((props.surface_area - 2800.0).abs() < 1e-6);
+
+        // the middle of the block
+        let vertices = &realized.solids["Extrusion-0:0"].vertices;
+        let middle = |axis: fn(&crate::archetypes::Vector3) -> f64| {
+            let low = vertices.iter().map(axis).fold(f64::MAX, f64::min);
+            let high = vertices.iter().map(axis).fold(f64::MIN, f64::max);
+            (low + high) / 2.0
+        };
+        assert!((props.center_of_mass.x - middle(|v| v.x)).abs() < 1e-6);
+        assert!((props.center_of_mass.y - middle(|v| v.y)).abs() < 1e-6);
+        assert!((props.center_of_mass.z - middle(|v| v.z)).abs() < 1e-6);
+
+        // m (b² + c²) / 12 about each axis, smallest about the longest side
+        let expected =
+            [500.0, 1700.0, 2000.0].map(|sum_of_squares| 16000.0 * sum_of_squares / 12.0);
+        for (moment, expected) in props.principal_moments.iter().zip(expected) {
+            assert!((moment - expected).abs() < 1e-6 * expected);
+        }
+        let long_side = &props.principal_axes[0];
+        let sketch_x = &realized.planes["Plane-0"].plane.primary;
+        assert!((long_side.dot(sketch_x).abs() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+// What a solid is made of: how heavy it is, and how it should look
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Material {
+    pub name: String,
+    // mass per unit volume, in whatever units the rest of the model uses
+    pub density: f64,
+    // linear RGB, each from 0 to 1
+    pub color: [f64; 3],
+    // 0 is polished, 1 is completely matte
+    pub roughness: f64,
+    // 0 for plastics and the like, 1 for bare metal
+    pub metalness: f64,
+}
+
+impl Default for Material {
+    // a plain grey plastic with unit density, so mass comes out equal to volume
+    fn default() -> Self {
+        Material {
+            name: "Default".to_owned(),
+            density: 1.0,
+            color: [0.8, 0.8, 0.8],
+            roughness: 0.5,
+            metalness: 0.0,
+        }
+    }
+}
+
+impl Material {
+    // The color as "#RRGGBB" in sRGB, the way most file formats want it
+    pub fn hex_color(&self) -> String {
+        let [r, g, b] = self.color.map(|channel| {
+            let linear = channel.clamp(0.0, 1.0);
+            let srgb = if linear <= 0.0031308 {
+                12.92 * linear
+            } else {
+                1.055 * linear.powf(1.0 / 2.4) - 0.055
+            };
+            (srgb * 255.0).round() as u8
+        });
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+}
@@ -0,0 +1,691 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::archetypes::{Plane, Vector3};
+use crate::assembly::{Assembly, Component, Transform};
+use crate::datum::FaceReference;
+use crate::error::CADmiumError;
+use crate::geometry::fit_cylinder;
+use crate::project::Project;
+use crate::realization::Realization;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// How far off a mate may be, in model units or radians, and still count as satisfied
+pub const MATE_TOLERANCE: f64 = 1e-6;
+const MAX_ITERATIONS: usize = 200;
+// the step used to work out how each mate changes as an instance moves
+const FINITE_DIFFERENCE: f64 = 1e-7;
+
+// A face on one of the assembly's own part instances, by the face's persistent name
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MateReference {
+    pub instance_id: String,
+    pub face: FaceReference,
+}
+
+// Planar faces are used as their plane. Cylindrical faces are used as their axis,
+// through the middle of the face
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum MateKind {
+    // the instance stays where its transform puts it
+    Fixed {
+        instance_id: String,
+    },
+    // the faces touch, facing each other
+    Coincident {
+        a: MateReference,
+        b: MateReference,
+    },
+    // the axes line up
+    Concentric {
+        a: MateReference,
+        b: MateReference,
+    },
+    // the faces face each other, with b this far out in front of a
+    Distance {
+        a: MateReference,
+        b: MateReference,
+        distance: f64,
+    },
+    // the angle (radians, 0 to PI) between the face normals
+    Angle {
+        a: MateReference,
+        b: MateReference,
+        angle: f64,
+    },
+    // concentric with the middles level, so b can only spin about the axis
+    Revolute {
+        a: MateReference,
+        b: MateReference,
+    },
+    // concentric without spinning, so b can only slide along the axis
+    Slider {
+        a: MateReference,
+        b: MateReference,
+    },
+}
+
+impl MateKind {
+    // How many degrees of freedom the mate takes away
+    pub fn degrees_of_freedom(&self) -> usize {
+        match self {
+            MateKind::Fixed { .. } => 6,
+            MateKind::Coincident { .. } | MateKind::Distance { .. } => 3,
+            MateKind::Concentric { .. } => 4,
+            // parallel faces can't tip either way, only spin about their normal
+            MateKind::Angle { angle, .. } if is_parallel(*angle) => 2,
+            MateKind::Angle { .. } => 1,
+            MateKind::Revolute { .. } | MateKind::Slider { .. } => 5,
+        }
+    }
+
+    pub fn instance_ids(&self) -> Vec<&str> {
+        match self {
+            MateKind::Fixed { instance_id } => vec![instance_id],
+            MateKind::Coincident { a, b }
+            | MateKind::Concentric { a, b }
+            | MateKind::Distance { a, b, .. }
+            | MateKind::Angle { a, b, .. }
+            | MateKind::Revolute { a, b }
+            | MateKind::Slider { a, b } => vec![&a.instance_id, &b.instance_id],
+        }
+    }
+
+    fn references(&self) -> Option<(&MateReference, &MateReference)> {
+        match self {
+            MateKind::Fixed { .. } => None,
+            MateKind::Coincident { a, b }
+            | MateKind::Concentric { a, b }
+            | MateKind::Distance { a, b, .. }
+            | MateKind::Angle { a, b, .. }
+            | MateKind::Revolute { a, b }
+            | MateKind::Slider { a, b } => Some((a, b)),
+        }
+    }
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Mate {
+    pub id: String,
+    pub name: String,
+    pub kind: MateKind,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MateSolution {
+    // instance ID -> where the mates put it
+    pub transforms: HashMap<String, Transform>,
+    // mates that contradict what earlier mates already fix
+    pub over_constrained: Vec<String>,
+    // mates that only repeat what earlier mates already fix, which is harmless
+    pub redundant: Vec<String>,
+    // mates the solver couldn't satisfy
+    pub unsatisfied: Vec<String>,
+}
+
+// A face, reduced to a point on it, its normal or axis, and a direction across it
+#[derive(Debug, Clone)]
+struct Feature {
+    origin: Vector3,
+    normal: Vector3,
+    primary: Vector3,
+}
+
+impl Feature {
+    fn resolve(face: &FaceReference, realized: &Realization) -> Result<Self, CADmiumError> {
+        if let Ok(plane) = face.plane(realized) {
+            return Ok(Feature {
+                origin: Vector3::new(plane.origin.x, plane.origin.y, plane.origin.z),
+                normal: plane.tertiary,
+                primary: plane.primary,
+            });
+        }
+
+        let mesh = face.mesh(realized)?;
+        let cylinder = fit_cylinder(&mesh)?;
+        let along: Vec<f64> = mesh
+            .positions
+            .iter()
+            .map(|p| p.minus(&cylinder.origin).dot(&cylinder.axis))
+            .collect();
+        let low = along.iter().cloned().fold(f64::MAX, f64::min);
+        let high = along.iter().cloned().fold(f64::MIN, f64::max);
+
+        let basis = Plane::from_normal(cylinder.origin.clone(), cylinder.axis.clone());
+        Ok(Feature {
+            origin: cylinder
+                .origin
+                .plus(cylinder.axis.times((low + high) / 2.0)),
+            normal: cylinder.axis,
+            primary: basis.primary,
+        })
+    }
+
+    fn placed(&self, transform: &Transform) -> Feature {
+        let origin = transform.apply_point(&self.origin.to_point3());
+        Feature {
+            origin: Vector3::new(origin.x, origin.y, origin.z),
+            normal: transform.apply_vector(&self.normal),
+            primary: transform.apply_vector(&self.primary),
+        }
+    }
+}
+
+// Whether an angle mate makes its faces parallel, facing the same way or opposite ways
+fn is_parallel(angle: f64) -> bool {
+    angle.abs() < MATE_TOLERANCE || (angle - PI).abs() < MATE_TOLERANCE
+}
+
+fn components(v: &Vector3) -> [f64; 3] {
+    [v.x, v.y, v.z]
+}
+
+// How far the placed features are from satisfying the mate. Zero when it is satisfied
+fn residuals(kind: &MateKind, a: &Feature, b: &Feature) -> Vec<f64> {
+    let offset = b.origin.minus(&a.origin);
+    let facing = components(&a.normal.plus(b.normal.clone()));
+    let aligned = components(&a.normal.cross(&b.normal));
+    let on_axis = components(&offset.cross(&a.normal));
+    let height = offset.dot(&a.normal);
+
+    match kind {
+        MateKind::Fixed { .. } => vec![],
+        MateKind::Coincident { .. } => [&facing[..], &[height]].concat(),
+        MateKind::Distance { distance, .. } => [&facing[..], &[height - distance]].concat(),
+        // the angle itself changes as fast however far apart the normals are, which their
+        // dot product doesn't near 0 and PI. Right at those it has a kink instead, so there
+        // the normals are matched, or opposed, component by component
+        MateKind::Angle { angle, .. } if angle.abs() < MATE_TOLERANCE => {
+            components(&b.normal.minus(&a.normal)).to_vec()
+        }
+        MateKind::Angle { angle, .. } if is_parallel(*angle) => facing.to_vec(),
+        MateKind::Angle { angle, .. } => {
+            let cross = a.normal.cross(&b.normal).length();
+            vec![cross.atan2(a.normal.dot(&b.normal)) - angle]
+        }
+        MateKind::Concentric { .. } => [aligned, on_axis].concat(),
+        MateKind::Revolute { .. } => [&aligned[..], &on_axis[..], &[height]].concat(),
+        MateKind::Slider { .. } => {
+            let twist = b.primary.dot(&a.normal.cross(&a.primary));
+            [&aligned[..], &on_axis[..], &[twist]].concat()
+        }
+    }
+}
+
+// Moves a transform by a small rotation (a rotation vector, about the instance's own
+// origin) followed by a small translation
+fn nudged(transform: &Transform, delta: &[f64]) -> Transform {
+    let spin = Vector3::new(delta[0], delta[1], delta[2]);
+    let angle = spin.length();
+    let rotated = if angle > 0.0 {
+        let turn = Transform::from_axis_angle(&spin, angle);
+        turn.compose(&Transform {
+            translation: [0.0, 0.0, 0.0],
+            ..transform.clone()
+        })
+    } else {
+        transform.clone()
+    };
+
+    let t = &transform.translation;
+    Transform {
+        translation: [t[0] + delta[3], t[1] + delta[4], t[2] + delta[5]],
+        ..rotated
+    }
+}
+
+struct Problem<'a> {
+    mates: &'a [Mate],
+    // for each mate, the features it joins, in the coordinates of their parts
+    features: Vec<Option<(Feature, Feature)>>,
+    // the instances the solver may move, each with six unknowns
+    free: Vec<String>,
+}
+
+impl Problem<'_> {
+    // Every mate's residuals one after another, and which rows belong to which mate
+    fn evaluate(&self, transforms: &HashMap<String, Transform>) -> (Vec<f64>, Vec<usize>) {
+        let mut all = vec![];
+        let mut rows = vec![];
+        for (mate, features) in self.mates.iter().zip(self.features.iter()) {
+            let mut r = vec![];
+            if let (Some((a, b)), Some((fa, fb))) = (mate.kind.references(), features) {
+                let a = fa.placed(&transforms[&a.instance_id]);
+                let b = fb.placed(&transforms[&b.instance_id]);
+                r = residuals(&mate.kind, &a, &b);
+            }
+            rows.push(r.len());
+            all.extend(r);
+        }
+        (all, rows)
+    }
+
+    // Row-major, one row per residual and six columns per free instance
+    fn jacobian(&self, transforms: &HashMap<String, Transform>, base: &[f64]) -> Vec<Vec<f64>> {
+        let mut jacobian = vec![vec![0.0; self.free.len() * 6]; base.len()];
+        for (i, instance_id) in self.free.iter().enumerate() {
+            for k in 0..6 {
+                let mut delta = [0.0; 6];
+                delta[k] = FINITE_DIFFERENCE;
+                let mut moved = transforms.clone();
+                moved.insert(
+                    instance_id.clone(),
+                    nudged(&transforms[instance_id], &delta),
+                );
+
+                let (shifted, _) = self.evaluate(&moved);
+                for (row, (s, b)) in shifted.iter().zip(base.iter()).enumerate() {
+                    jacobian[row][i * 6 + k] = (s - b) / FINITE_DIFFERENCE;
+                }
+            }
+        }
+        jacobian
+    }
+}
+
+fn norm(v: &[f64]) -> f64 {
+    v.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+// Gaussian elimination with partial pivoting. None if the matrix is singular
+fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = a[col].clone();
+        let pivot_b = b[col];
+        for (row, rhs) in a.iter_mut().zip(b.iter_mut()).skip(col + 1) {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *x -= factor * p;
+            }
+            *rhs -= factor * pivot_b;
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+// Works out where the mates put each of the assembly's instances, without changing it.
+// Instances with a Fixed mate stay put; if there are none, the first instance does
+pub fn solve(project: &Project, assembly: &Assembly) -> Result<MateSolution, CADmiumError> {
+    let mut transforms: HashMap<String, Transform> = assembly
+        .instances
+        .iter()
+        .map(|instance| (instance.id.clone(), instance.transform.clone()))
+        .collect();
+
+    let mut redundant = vec![];
+    let mut fixed: Vec<&str> = vec![];
+    let mut realizations: HashMap<u64, Realization> = HashMap::new();
+    let mut features = vec![];
+
+    for mate in assembly.mates.iter() {
+        for instance_id in mate.kind.instance_ids() {
+            if !transforms.contains_key(instance_id) {
+                return Err(CADmiumError::InstanceNotFound(instance_id.to_owned()));
+            }
+        }
+
+        if let MateKind::Fixed { instance_id } = &mate.kind {
+            if fixed.contains(&instance_id.as_str()) {
+                redundant.push(mate.id.clone());
+            }
+            fixed.push(instance_id);
+        }
+
+        let Some((a, b)) = mate.kind.references() else {
+            features.push(None);
+            continue;
+        };
+
+        let mut resolve = |reference: &MateReference| -> Result<Feature, CADmiumError> {
+            let instance = assembly.get_instance(&reference.instance_id)?;
+            let Component::Part { workbench_id } = instance.component else {
+                return Err(CADmiumError::InvalidGeometry(format!(
+                    "mates can only join parts, and {} is an assembly",
+                    instance.id
+                )));
+            };
+            if !realizations.contains_key(&workbench_id) {
+                let wb = project.get_workbench_by_id(workbench_id)?;
+                realizations.insert(workbench_id, wb.realize(wb.history.len() as u64));
+            }
+            Feature::resolve(&reference.face, &realizations[&workbench_id])
+        };
+        features.push(Some((resolve(a)?, resolve(b)?)));
+    }
+
+    if fixed.is_empty() {
+        if let Some(first) = assembly.instances.first() {
+            fixed.push(&first.id);
+        }
+    }
+
+    let mut free: Vec<String> = vec![];
+    for mate in assembly.mates.iter() {
+        for instance_id in mate.kind.instance_ids() {
+            if !fixed.contains(&instance_id) && !free.iter().any(|id| id == instance_id) {
+                free.push(instance_id.to_owned());
+            }
+        }
+    }
+
+    let problem = Problem {
+        mates: &assembly.mates,
+        features,
+        free,
+    };
+
+    // Levenberg-Marquardt: Gauss-Newton steps, damped whenever a step makes things worse
+    let mut damping = 1e-3;
+    let (mut r, mut rows) = problem.evaluate(&transforms);
+    for _ in 0..MAX_ITERATIONS {
+        if norm(&r) < MATE_TOLERANCE * 1e-3 || problem.free.is_empty() || damping > 1e12 {
+            break;
+        }
+
+        let j = problem.jacobian(&transforms, &r);
+        let n = problem.free.len() * 6;
+        let mut jtj = vec![vec![0.0; n]; n];
+        let mut jtr = vec![0.0; n];
+        for (row, residual) in j.iter().zip(r.iter()) {
+            for (p, (jtj_row, jtr_p)) in jtj.iter_mut().zip(jtr.iter_mut()).enumerate() {
+                *jtr_p -= row[p] * residual;
+                for (x, q) in jtj_row.iter_mut().zip(row.iter()) {
+                    *x += row[p] * q;
+                }
+            }
+        }
+        for (p, jtj_row) in jtj.iter_mut().enumerate() {
+            jtj_row[p] += damping * (1.0 + jtj_row[p]);
+        }
+
+        let Some(step) = solve_linear(jtj, jtr) else {
+            damping *= 10.0;
+            continue;
+        };
+        let mut trial = transforms.clone();
+        for (i, instance_id) in problem.free.iter().enumerate() {
+            trial.insert(
+                instance_id.clone(),
+                nudged(&transforms[instance_id], &step[i * 6..i * 6 + 6]),
+            );
+        }
+
+        let (trial_r, trial_rows) = problem.evaluate(&trial);
+        if norm(&trial_r) < norm(&r) {
+            transforms = trial;
+            r = trial_r;
+            rows = trial_rows;
+            damping = (damping / 10.0).max(1e-12);
+        } else {
+            damping *= 10.0;
+        }
+    }
+
+    // a mate is redundant if its rows add fewer independent directions to the Jacobian
+    // than the degrees of freedom it claims to take away. If it isn't satisfied either,
+    // it fights the earlier mates and over-constrains the assembly
+    let j = problem.jacobian(&transforms, &r);
+    let mut basis: Vec<Vec<f64>> = vec![];
+    let mut over_constrained = vec![];
+    let mut unsatisfied = vec![];
+    let mut row = 0;
+    for (mate, count) in assembly.mates.iter().zip(rows.iter()) {
+        if norm(&r[row..row + count]) > MATE_TOLERANCE {
+            unsatisfied.push(mate.id.clone());
+        }
+        if let MateKind::Fixed { .. } = mate.kind {
+            row += count;
+            continue;
+        }
+
+        let mut added = 0;
+        for candidate in j[row..row + count].iter() {
+            let scale = norm(candidate);
+            let mut remainder = candidate.clone();
+            for direction in basis.iter() {
+                let along: f64 = remainder.iter().zip(direction).map(|(x, d)| x * d).sum();
+                for (x, d) in remainder.iter_mut().zip(direction) {
+                    *x -= along * d;
+                }
+            }
+            let length = norm(&remainder);
+            if scale > 0.0 && length > 1e-5 * scale {
+                basis.push(remainder.iter().map(|x| x / length).collect());
+                added += 1;
+            }
+        }
+        if added < mate.kind.degrees_of_freedom() {
+            if unsatisfied.contains(&mate.id) {
+                over_constrained.push(mate.id.clone());
+            } else {
+                redundant.push(mate.id.clone());
+            }
+        }
+        row += count;
+    }
+
+    Ok(MateSolution {
+        transforms,
+        over_constrained,
+        redundant,
+        unsatisfied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembly::RealAssembly;
+    use crate::workbench::tests::block_with_hole;
+
+    use super::*;
+
+    #[test]
+    fn mates_stack_one_block_on_another() {
+        let mut p = Project::new("Test Project");
+        let part = p.add_workbench(block_with_hole());
+        let realized = p.get_realization(part, 1000).unwrap();
+        let solid = &realized.solids["Extrusion-0:0"];
+
+        // the block's faces either side of it along the sketch normal, and its hole
+        let face = |name: &String| FaceReference {
+            solid_id: "Extrusion-0:0".to_owned(),
+            face_name: name.clone(),
+        };
+        let planes: Vec<(FaceReference, Plane)> = solid
+            .face_names
+            .iter()
+            .filter_map(|name| Some((face(name), face(name).plane(&realized).ok()?)))
+            .collect();
+        let normal = planes
+            .iter()
+            .map(|(_, plane)| plane.tertiary.clone())
+            .find(|n| n.dot(&realized.planes["Plane-0"].plane.tertiary).abs() > 0.99)
+            .unwrap();
+        let height = |plane: &Plane| {
+            let o = &plane.origin;
+            Vector3::new(o.x, o.y, o.z).dot(&normal)
+        };
+        let outer = |sign: f64| {
+            planes
+                .iter()
+                .filter(|(_, plane)| plane.tertiary.dot(&normal) * sign > 0.99)
+                .max_by(|(_, x), (_, y)| (height(x) * sign).total_cmp(&(height(y) * sign)))
+                .unwrap()
+                .0
+                .clone()
+        };
+        let (top, bottom) = (outer(1.0), outer(-1.0));
+        let hole = solid
+            .face_names
+            .iter()
+            .map(face)
+            .find(|f| {
+                f.mesh(&realized)
+                    .map(|mesh| fit_cylinder(&mesh).is_ok())
+                    .unwrap_or(false)
+            })
+            .unwrap();
+
+        let assembly = p.add_assembly("Stack");
+        let lower = p
+            .add_instance(
+                assembly,
+                "Lower",
+                Component::Part { workbench_id: part },
+                Transform::identity(),
+            )
+            .unwrap();
+        let mut start = Transform::from_axis_angle(&Vector3::new(1.0, 1.0, 0.0), 0.3);
+        start.translation = [15.0, -40.0, 70.0];
+        let upper = p
+            .add_instance(
+                assembly,
+                "Upper",
+                Component::Part { workbench_id: part },
+                start,
+            )
+            .unwrap();
+
+        let on = |instance_id: &String, face: &FaceReference| MateReference {
+            instance_id: instance_id.clone(),
+            face: face.clone(),
+        };
+        let a = p.get_assembly_by_id_mut(assembly).unwrap();
+        a.add_mate(
+            "Stacked",
+            MateKind::Coincident {
+                a: on(&lower, &top),
+                b: on(&upper, &bottom),
+            },
+        );
+        a.add_mate(
+            "Holes lined up",
+            MateKind::Concentric {
+                a: on(&lower, &hole),
+                b: on(&upper, &hole),
+            },
+        );
+
+        let solution = p.solve_mates(assembly).unwrap();
+        assert!(solution.unsatisfied.is_empty());
+        assert!(solution.over_constrained.is_empty());
+        // both mates keep the faces parallel, so the second only partly adds anything
+        assert_eq!(solution.redundant, vec!["Mate-1"]);
+
+        // the upper block now sits exactly one block higher
+        let real = RealAssembly::new(&p, assembly).unwrap();
+        let highest = |s: &crate::solid::Solid| {
+            s.vertices
+                .iter()
+                .map(|v| v.dot(&normal))
+                .fold(f64::MIN, f64::max)
+        };
+        let lowest = |s: &crate::solid::Solid| {
+            s.vertices
+                .iter()
+                .map(|v| v.dot(&normal))
+                .fold(f64::MAX, f64::min)
+        };
+        let (below, above) = (&real.solids[0].solid, &real.solids[1].solid);
+        assert!((lowest(above) - highest(below)).abs() < 1e-3);
+
+        // a distance between the same faces contradicts the coincident mate
+        let a = p.get_assembly_by_id_mut(assembly).unwrap();
+        let gap = a.add_mate(
+            "Gap",
+            MateKind::Distance {
+                a: on(&lower, &top),
+                b: on(&upper, &bottom),
+                distance: 5.0,
+            },
+        );
+        let solution = p.solve_mates(assembly).unwrap();
+        assert_eq!(solution.over_constrained, vec![gap]);
+        assert!(!solution.unsatisfied.is_empty());
+    }
+
+    #[test]
+    fn angle_mates_solve_at_any_angle_including_parallel() {
+        let mut p = Project::new("Test Project");
+        let part = p.add_workbench(block_with_hole());
+        let realized = p.get_realization(part, 1000).unwrap();
+        let (face, plane) = realized.solids["Extrusion-0:0"]
+            .face_names
+            .iter()
+            .find_map(|name| {
+                let face = FaceReference {
+                    solid_id: "Extrusion-0:0".to_owned(),
+                    face_name: name.clone(),
+                };
+                let plane = face.plane(&realized).ok()?;
+                Some((face, plane))
+            })
+            .unwrap();
+
+        let assembly = p.add_assembly("Angles");
+        let add = |p: &mut Project, name: &str, transform: Transform| {
+            let component = Component::Part { workbench_id: part };
+            p.add_instance(assembly, name, component, transform)
+                .unwrap()
+        };
+        let still = add(&mut p, "Still", Transform::identity());
+        let turning = add(
+            &mut p,
+            "Turning",
+            Transform::from_axis_angle(&Vector3::new(1.0, 2.0, 3.0), 0.4),
+        );
+        let on = |instance_id: &String| MateReference {
+            instance_id: instance_id.clone(),
+            face: face.clone(),
+        };
+        p.get_assembly_by_id_mut(assembly).unwrap().add_mate(
+            "Still",
+            MateKind::Fixed {
+                instance_id: still.clone(),
+            },
+        );
+
+        // each solve starts from where the last one left the faces, so from parallel too
+        for angle in [0.0, std::f64::consts::FRAC_PI_3, PI] {
+            let a = p.get_assembly_by_id_mut(assembly).unwrap();
+            let mate_id = a.add_mate(
+                "Angle",
+                MateKind::Angle {
+                    a: on(&still),
+                    b: on(&turning),
+                    angle,
+                },
+            );
+            let solution = p.solve_mates(assembly).unwrap();
+            assert!(solution.unsatisfied.is_empty());
+            assert!(solution.over_constrained.is_empty());
+
+            let turned = solution.transforms[&turning].apply_vector(&plane.tertiary);
+            assert!((turned.dot(&plane.tertiary) - angle.cos()).abs() < 1e-6);
+
+            let a = p.get_assembly_by_id_mut(assembly).unwrap();
+            a.remove_mate(&mate_id).unwrap();
+        }
+    }
+}
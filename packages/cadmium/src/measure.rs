@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::archetypes::{Point3, Vector3};
+use crate::datum::{EdgeReference, FaceReference, PointReference};
+use crate::error::CADmiumError;
+use crate::geometry::{closest_points, fit_circle, fit_cylinder, polyline_length, Simplex};
+use crate::realization::Realization;
+
+// Anything that can be picked and measured
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum EntityReference {
+    Point { point: PointReference },
+    Edge { edge: EdgeReference },
+    Face { face: FaceReference },
+}
+
+// Whatever makes sense to measure about one entity or between two. The rest stay None
+#[derive(Tsify, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Measurement {
+    // between two entities, the shortest distance and the points at either end of it.
+    // For a single point, point_a is where it is
+    pub distance: Option<f64>,
+    pub point_a: Option<Point3>,
+    pub point_b: Option<Point3>,
+    // the angle (radians, 0 to PI / 2) between two straight edges or planar faces, or one
+    // of each. Parallel is 0 either way
+    pub angle: Option<f64>,
+    // of a single edge
+    pub length: Option<f64>,
+    // of a single circular edge or cylindrical face. Only circles have a center
+    pub radius: Option<f64>,
+    pub center: Option<Point3>,
+    // of a single face
+    pub area: Option<f64>,
+}
+
+// Which way a straight edge runs or a planar face points
+enum Orientation {
+    Along(Vector3),
+    Normal(Vector3),
+}
+
+impl EntityReference {
+    fn simplices(&self, realized: &Realization) -> Result<Vec<Simplex>, CADmiumError> {
+        match self {
+            EntityReference::Point { point } => Ok(vec![Simplex::Point(point.resolve(realized)?)]),
+            EntityReference::Edge { edge } => Ok(edge
+                .polyline(realized)?
+                .windows(2)
+                .map(|pair| Simplex::Segment(pair[0].clone(), pair[1].clone()))
+                .collect()),
+            EntityReference::Face { face } => Ok(face
+                .mesh(realized)?
+                .triangle_points()
+                .map(|(a, b, c)| Simplex::Triangle(a.clone(), b.clone(), c.clone()))
+                .collect()),
+        }
+    }
+
+    fn orientation(&self, realized: &Realization) -> Option<Orientation> {
+        match self {
+            EntityReference::Point { .. } => None,
+            EntityReference::Edge { edge } => edge
+                .line(realized)
+                .ok()
+                .map(|line| Orientation::Along(line.direction.normalize())),
+            EntityReference::Face { face } => face
+                .plane(realized)
+                .ok()
+                .map(|plane| Orientation::Normal(plane.tertiary.normalize())),
+        }
+    }
+}
+
+// Measures one entity on its own
+pub fn measure_one(
+    realized: &Realization,
+    entity: &EntityReference,
+) -> Result<Measurement, CADmiumError> {
+    let mut measurement = Measurement::default();
+    match entity {
+        EntityReference::Point { point } => {
+            measurement.point_a = Some(point.resolve(realized)?);
+        }
+        EntityReference::Edge { edge } => {
+            let points = edge.polyline(realized)?;
+            measurement.length = Some(polyline_length(&points));
+            if let Ok(circle) = fit_circle(&points) {
+                measurement.radius = Some(circle.radius);
+                measurement.center = Some(circle.center);
+            }
+        }
+        EntityReference::Face { face } => {
+            let mesh = face.mesh(realized)?;
+            measurement.area = Some(mesh.area());
+            if let Ok(cylinder) = fit_cylinder(&mesh) {
+                measurement.radius = Some(cylinder.radius);
+            }
+        }
+    }
+    Ok(measurement)
+}
+
+// Measures the distance between two entities and, where both have a direction, the angle.
+// Distances are searched over the tessellated entities, so they are only as exact as the
+// tessellation, and entities that cut through each other come out as touching at best
+pub fn measure_between(
+    realized: &Realization,
+    a: &EntityReference,
+    b: &EntityReference,
+) -> Result<Measurement, CADmiumError> {
+    let (distance, point_a, point_b) =
+        closest_points(&a.simplices(realized)?, &b.simplices(realized)?).ok_or(
+            CADmiumError::InvalidGeometry("there is nothing to measure between".to_owned()),
+        )?;
+
+    let angle = match (a.orientation(realized), b.orientation(realized)) {
+        (Some(Orientation::Along(u)), Some(Orientation::Along(v)))
+        | (Some(Orientation::Normal(u)), Some(Orientation::Normal(v))) => {
+            Some(u.dot(&v).abs().min(1.0).acos())
+        }
+        // an edge parallel to a face is perpendicular to its normal
+        (Some(Orientation::Along(u)), Some(Orientation::Normal(v)))
+        | (Some(Orientation::Normal(v)), Some(Orientation::Along(u))) => {
+            Some(u.dot(&v).abs().min(1.0).asin())
+        }
+        _ => None,
+    };
+
+    Ok(Measurement {
+        distance: Some(distance),
+        point_a: Some(point_a),
+        point_b: Some(point_b),
+        angle,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::workbench::tests::block;
+
+    use super::*;
+
+    #[test]
+    fn measure_a_block_and_a_circle() {
+        let (mut wb, block_sketch) = block();
+        let circle_sketch_id = wb.add_sketch_to_plane("Sketch 2", "Plane-0");
+        let s = wb.get_sketch_mut("Sketch 2").unwrap();
+        let center = s.add_point(60.0, 10.0);
+        let circle_id = s.add_circle(center, 5.0);
+
+        let realized = wb.realize(1000);
+        let solid = &realized.solids["Extrusion-0:0"];
+        let faces: Vec<EntityReference> = solid
+            .face_names
+            .iter()
+            .map(|name| EntityReference::Face {
+                face: FaceReference {
+                    solid_id: "Extrusion-0:0".to_owned(),
+                    face_name: name.clone(),
+                },
+            })
+            .collect();
+
+        // the two big faces are 10 apart, and square to all of the others
+        let areas: Vec<f64> = faces
+            .iter()
+            .map(|face| measure_one(&realized, face).unwrap().area.unwrap())
+            .collect();
+        let big: Vec<&EntityReference> = faces
+            .iter()
+            .zip(areas.iter())
+            .filter(|(_, area)| (*area - 800.0).abs() < 1e-6)
+            .map(|(face, _)| face)
+            .collect();
+        assert_eq!(big.len(), 2);
+        let between = measure_between(&realized, big[0], big[1]).unwrap();
+        assert!((between.distance.unwrap() - 10.0).abs() < 1e-6);
+        assert!(between.angle.unwrap().abs() < 1e-6);
+
+        let side = faces
+            .iter()
+            .zip(areas.iter())
+            .find(|(_, area)| (*area - 400.0).abs() < 1e-6)
+            .unwrap()
+            .0;
+        let square = measure_between(&realized, big[0], side).unwrap();
+        assert!(square.distance.unwrap().abs() < 1e-6);
+        assert!((square.angle.unwrap() - FRAC_PI_2).abs() < 1e-6);
+
+        // every edge of the block is 40, 20 or 10 long
+        let mut lengths: Vec<f64> = solid
+            .edge_names()
+            .iter()
+            .map(|name| {
+                let edge = EntityReference::Edge {
+                    edge: EdgeReference::SolidEdge {
+                        solid_id: "Extrusion-0:0".to_owned(),
+                        edge_name: name.clone(),
+                    },
+                };
+                measure_one(&realized, &edge).unwrap().length.unwrap()
+            })
+            .collect();
+        lengths.sort_by(f64::total_cmp);
+        lengths.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+        assert_eq!(lengths.len(), 3);
+        assert!((lengths[0] - 10.0).abs() < 1e-6 && (lengths[2] - 40.0).abs() < 1e-6);
+
+        let circle = EntityReference::Edge {
+            edge: EdgeReference::SketchCircle {
+                sketch_id: circle_sketch_id,
+                circle_id,
+            },
+        };
+        let measured = measure_one(&realized, &circle).unwrap();
+        assert!((measured.radius.unwrap() - 5.0).abs() < 1e-6);
+        // from a corner of the sketch to the nearest point on the circle
+        let corner = EntityReference::Point {
+            point: PointReference::SketchPoint {
+                sketch_id: block_sketch.sketch_id,
+                point_id: block_sketch.corners[1],
+            },
+        };
+        let to_circle = measure_between(&realized, &corner, &circle).unwrap();
+        let expected = (20.0_f64.powi(2) + 10.0_f64.powi(2)).sqrt() - 5.0;
+        assert!((to_circle.distance.unwrap() - expected).abs() < 0.01);
+    }
+}
@@ -1,40 +1,222 @@
-use itertools::Itertools as _;
-
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 
 use crate::archetypes::PlaneDescription;
+use crate::assembly::{Component, Transform};
+use crate::datum::{AxisDefinition, FaceReference, PlaneDefinition, PointDefinition};
+use crate::changes::{ChangeSet, ProjectSnapshot};
 use crate::error::CADmiumError;
 use crate::extrusion::{Direction, Extrusion, ExtrusionMode};
+use crate::hole::{Hole, HoleDepth, HoleFit, HoleKind, HoleType};
+use crate::mass::MassProperties;
+use crate::material::Material;
+use crate::measure::{EntityReference, Measurement};
+use crate::mates::MateKind;
 use crate::project::Project;
 use crate::step::StepData;
+use crate::workbench::Workbench;
 
 #[derive(Tsify, Debug, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum MessageResult {
     #[serde(rename = "success")]
-    Success(String),
+    Success(MessageSuccess),
     #[serde(rename = "error")]
-    Error(String),
+    Error(MessageError),
 }
 
-impl From<Result<String, anyhow::Error>> for MessageResult {
-    fn from(result: Result<String, anyhow::Error>) -> Self {
+impl From<Result<MessageSuccess, anyhow::Error>> for MessageResult {
+    fn from(result: Result<MessageSuccess, anyhow::Error>) -> Self {
         match result {
-            // TODO: The Success should be a stable enum
-            Ok(msg) => MessageResult::Success(format!("{{ {} }}", msg)),
-            Err(e) => MessageResult::Error(e.backtrace().to_string()),
+            Ok(success) => MessageResult::Success(success),
+            Err(e) => MessageResult::Error(e.into()),
         }
     }
 }
 
-#[derive(Tsify, Debug, Serialize, Deserialize)]
+// What each kind of message reports back when it works
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum MessageSuccess {
+    // there is nothing more to say than that it worked
+    Done,
+    Renamed {
+        name: String,
+    },
+    StepCreated {
+        id: String,
+    },
+    StepUpdated {
+        id: String,
+    },
+    StepDeleted {
+        id: String,
+        // the steps that build on the deleted one, which were either deleted too or left broken
+        dependents: Vec<String>,
+        cascade: bool,
+        dry_run: bool,
+    },
+    LengthUpdated {
+        id: String,
+        length: f64,
+    },
+    RollbackMoved {
+        rollback_index: usize,
+    },
+    SketchEntityCreated {
+        id: u64,
+    },
+    RectangleCreated {
+        point_ids: Vec<u64>,
+        line_ids: Vec<u64>,
+    },
+    SketchStepped {
+        max_change: f64,
+    },
+    Batch {
+        results: Vec<MessageSuccess>,
+    },
+    UndoState {
+        can_undo: bool,
+        can_redo: bool,
+    },
+    WorkbenchCreated {
+        id: u64,
+    },
+    WorkbenchDeleted {
+        id: u64,
+    },
+    AssemblyCreated {
+        id: u64,
+    },
+    InstanceCreated {
+        id: String,
+    },
+    MateCreated {
+        id: String,
+    },
+    MatesSolved {
+        over_constrained: Vec<String>,
+        redundant: Vec<String>,
+        unsatisfied: Vec<String>,
+    },
+    MassProperties {
+        properties: MassProperties,
+    },
+    Measured {
+        measurement: Measurement,
+    },
+}
+
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MessageError {
+    // the CADmiumError variant, like "StepIDNotFound", or "Other" for anything else
+    pub kind: String,
+    pub message: String,
+    // the IDs and names the error is about
+    pub ids: Vec<String>,
+}
+
+impl From<anyhow::Error> for MessageError {
+    fn from(e: anyhow::Error) -> Self {
+        // {:#} includes any context, like which message of a batch failed
+        let message = format!("{:#}", e);
+        match e.downcast_ref::<CADmiumError>() {
+            Some(error) => MessageError {
+                kind: <&'static str>::from(error).to_owned(),
+                message,
+                ids: error.ids(),
+            },
+            None => MessageError {
+                kind: "Other".to_owned(),
+                message,
+                ids: vec![],
+            },
+        }
+    }
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Message {
     RenameWorkbench {
         workbench_id: u64,
         new_name: String,
     },
+    NewWorkbench {
+        name: String,
+    },
+    DuplicateWorkbench {
+        workbench_id: u64,
+        new_name: String,
+    },
+    DeleteWorkbench {
+        workbench_id: u64,
+    },
+    SetActiveWorkbench {
+        workbench_id: u64,
+    },
+    NewAssembly {
+        name: String,
+    },
+    DeleteAssembly {
+        assembly_id: u64,
+    },
+    AddInstance {
+        assembly_id: u64,
+        name: String,
+        component: Component,
+        #[serde(default)]
+        transform: Transform,
+    },
+    SetInstanceTransform {
+        assembly_id: u64,
+        instance_id: String,
+        transform: Transform,
+    },
+    RemoveInstance {
+        assembly_id: u64,
+        instance_id: String,
+    },
+    AddMate {
+        assembly_id: u64,
+        name: String,
+        kind: MateKind,
+    },
+    RemoveMate {
+        assembly_id: u64,
+        mate_id: String,
+    },
+    // moves the instances to where the mates put them
+    SolveMates {
+        assembly_id: u64,
+    },
+    // changes nothing: asks how heavy a solid is, or the whole workbench if no solid is given.
+    // Without a density, each solid's material is used. `tolerance` is how closely curved
+    // faces are followed
+    GetMassProperties {
+        workbench_id: u64,
+        solid_id: Option<String>,
+        #[serde(default)]
+        density: Option<f64>,
+        tolerance: f64,
+    },
+    // changes nothing: measures one entity, or the distance and angle between two
+    Measure {
+        workbench_id: u64,
+        a: EntityReference,
+        #[serde(default)]
+        b: Option<EntityReference>,
+    },
+    // without a solid, sets the material of the whole workbench. None clears it
+    SetMaterial {
+        workbench_id: u64,
+        #[serde(default)]
+        solid_id: Option<String>,
+        material: Option<Material>,
+    },
     RenameStep {
         workbench_id: u64,
         step_id: u64,
@@ -110,14 +292,56 @@ pub enum Message {
         sketch_name: String,
         plane_id: String,
     },
+    NewSketchOnFace {
+        workbench_id: u64,
+        sketch_name: String,
+        face: FaceReference,
+    },
     SetSketchPlane {
         workbench_id: u64,
         sketch_id: String,
+        #[serde(default)]
         plane_id: String,
+        // when given, the sketch is attached to this face and plane_id is ignored
+        #[serde(default)]
+        face: Option<FaceReference>,
     },
     DeleteStep {
         workbench_id: u64,
         step_name: String,
+        // also delete every step that builds on this one, rather than leaving them broken
+        #[serde(default)]
+        cascade: bool,
+        // only report which steps would be affected
+        #[serde(default)]
+        dry_run: bool,
+    },
+    MoveStepBefore {
+        workbench_id: u64,
+        step_id: String,
+        target_step_id: String,
+    },
+    MoveStepAfter {
+        workbench_id: u64,
+        step_id: String,
+        target_step_id: String,
+    },
+    SetRollback {
+        workbench_id: u64,
+        #[serde(default)]
+        after_step_id: Option<String>,
+    },
+    MoveRollback {
+        workbench_id: u64,
+        steps: i64,
+    },
+    SuppressStep {
+        workbench_id: u64,
+        step_id: String,
+    },
+    UnsuppressStep {
+        workbench_id: u64,
+        step_id: String,
     },
     NewExtrusion {
         workbench_id: u64,
@@ -143,6 +367,69 @@ pub enum Message {
         extrusion_name: String,
         length: f64,
     },
+    NewDatumPlane {
+        workbench_id: u64,
+        plane_name: String,
+        definition: PlaneDefinition,
+    },
+    UpdateDatumPlane {
+        workbench_id: u64,
+        plane_id: String,
+        definition: PlaneDefinition,
+    },
+    NewDatumPoint {
+        workbench_id: u64,
+        point_name: String,
+        definition: PointDefinition,
+    },
+    UpdateDatumPoint {
+        workbench_id: u64,
+        point_id: String,
+        definition: PointDefinition,
+    },
+    NewAxis {
+        workbench_id: u64,
+        axis_name: String,
+        definition: AxisDefinition,
+    },
+    UpdateAxis {
+        workbench_id: u64,
+        axis_id: String,
+        definition: AxisDefinition,
+    },
+    NewHole {
+        workbench_id: u64,
+        hole_name: String,
+        sketch_id: String,
+        point_ids: Vec<u64>,
+        diameter: f64,
+        hole_type: HoleType,
+        depth: HoleDepth,
+        merge_scope: Vec<String>,
+    },
+    NewStandardHole {
+        workbench_id: u64,
+        hole_name: String,
+        sketch_id: String,
+        point_ids: Vec<u64>,
+        designation: String,
+        fit: HoleFit,
+        kind: HoleKind,
+        depth: HoleDepth,
+        merge_scope: Vec<String>,
+    },
+    // Applies every message in order, or none of them if any fails. An object like
+    // { "$ref": 0, "field": "id" } anywhere in a message is replaced by the id field of the
+    // first message's result
+    Batch {
+        #[tsify(type = "any[]")]
+        messages: Vec<serde_json::Value>,
+    },
+    Undo,
+    Redo,
+    // the messages sent until EndUndoGroup are undone as one change
+    BeginUndoGroup,
+    EndUndoGroup,
 }
 
 impl Message {
@@ -157,11 +444,127 @@ impl Message {
         Ok(serde_json::from_str(json)?)
     }
 
-    pub fn handle(&self, project: &mut Project) -> Result<String, anyhow::Error> {
+    pub fn handle(&self, project: &mut Project) -> Result<MessageSuccess, anyhow::Error> {
+        let merge_key = self.undo_merge_key();
+        let needs_snapshot = project.undo_stack.needs_snapshot(merge_key.as_deref());
+
+        let result = if self.changes_project() && needs_snapshot {
+            // remember the project as it was so this change can be undone
+            let before = project.json();
+            let result = self.apply(project);
+            if result.is_ok() {
+                project.undo_stack.record(before, merge_key);
+            }
+            result
+        } else {
+            self.apply(project)
+        };
+
+        if result.is_ok() {
+            project.journal.record(self);
+        }
+        result
+    }
+
+    // Whether handling the message can change the project, and so needs an undo snapshot.
+    // Undo and redo change it too, but look after the undo stack themselves. Switching
+    // workbenches only changes what is being looked at, so it isn't undone either
+    fn changes_project(&self) -> bool {
+        match self {
+            Message::GetMassProperties { .. } | Message::Measure { .. } => false,
+            Message::DeleteStep { dry_run, .. } => !dry_run,
+            Message::Undo | Message::Redo | Message::BeginUndoGroup | Message::EndUndoGroup => false,
+            Message::SetActiveWorkbench { .. } => false,
+            _ => true,
+        }
+    }
+
+    // Solving a sketch takes many messages in a row, which are undone as one
+    fn undo_merge_key(&self) -> Option<String> {
+        match self {
+            Message::StepSketch { workbench_id, sketch_name, .. }
+            | Message::SolveSketch { workbench_id, sketch_name, .. } => {
+                Some(format!("Solve {}/{}", workbench_id, sketch_name))
+            }
+            _ => None,
+        }
+    }
+
+    // Like handle, but also works out what the message changed, for clients that update
+    // incrementally. This realizes every workbench before and after, so it costs more
+    pub fn handle_with_changes(
+        &self,
+        project: &mut Project,
+    ) -> (Result<MessageSuccess, anyhow::Error>, ChangeSet) {
+        let before = ProjectSnapshot::new(project);
+        let result = self.handle(project);
+        let changes = ChangeSet::between(&before, &ProjectSnapshot::new(project));
+        (result, changes)
+    }
+
+    fn apply(&self, project: &mut Project) -> Result<MessageSuccess, anyhow::Error> {
         match self {
+            Message::Batch { messages } => {
+                let before = project.json();
+                let mut results: Vec<MessageSuccess> = vec![];
+                // the same results as JSON, for later messages to refer to
+                let mut values: Vec<serde_json::Value> = vec![];
+
+                for (index, message) in messages.iter().enumerate() {
+                    let result = substitute_results(message, &values)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|message| Ok(serde_json::from_value::<Message>(message)?))
+                        .and_then(|message| match message {
+                            Message::Undo | Message::Redo => Err(
+                                CADmiumError::NotAllowedInBatch("Undo and Redo".to_owned()).into(),
+                            ),
+                            // the batch is already one change, and a group left open by a
+                            // failed batch would swallow the messages after it
+                            Message::BeginUndoGroup | Message::EndUndoGroup => {
+                                Err(CADmiumError::NotAllowedInBatch(
+                                    "BeginUndoGroup and EndUndoGroup".to_owned(),
+                                )
+                                .into())
+                            }
+                            message => message.apply(project),
+                        });
+
+                    match result {
+                        Ok(success) => {
+                            values.push(serde_json::to_value(&success)?);
+                            results.push(success);
+                        }
+                        Err(e) => {
+                            // put back everything the earlier messages did
+                            project.restore(&before)?;
+                            return Err(e.context(format!("Message {} of the batch failed", index)));
+                        }
+                    }
+                }
+
+                Ok(MessageSuccess::Batch { results })
+            }
+            Message::Undo => {
+                project.undo()?;
+                Ok(undo_state(project))
+            }
+            Message::Redo => {
+                project.redo()?;
+                Ok(undo_state(project))
+            }
+            Message::BeginUndoGroup => {
+                project.undo_stack.begin_group();
+                Ok(MessageSuccess::Done)
+            }
+            Message::EndUndoGroup => {
+                project.undo_stack.end_group();
+                Ok(MessageSuccess::Done)
+            }
             Message::RenameProject { new_name } => {
                 project.name = new_name.to_owned();
-                Ok(format!("\"name\": \"{}\"", new_name))
+                Ok(MessageSuccess::Renamed {
+                    name: new_name.to_owned(),
+                })
             }
             Message::RenameWorkbench {
                 workbench_id,
@@ -169,7 +572,122 @@ impl Message {
             } => {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 workbench.name = new_name.to_owned();
-                Ok(format!("\"name\": \"{}\"", new_name))
+                Ok(MessageSuccess::Renamed {
+                    name: new_name.to_owned(),
+                })
+            }
+            Message::NewWorkbench { name } => {
+                let id = project.add_workbench(Workbench::new(name));
+                Ok(MessageSuccess::WorkbenchCreated { id })
+            }
+            Message::DuplicateWorkbench {
+                workbench_id,
+                new_name,
+            } => {
+                let id = project.duplicate_workbench(*workbench_id, new_name)?;
+                Ok(MessageSuccess::WorkbenchCreated { id })
+            }
+            Message::DeleteWorkbench { workbench_id } => {
+                project.delete_workbench(*workbench_id)?;
+                Ok(MessageSuccess::WorkbenchDeleted { id: *workbench_id })
+            }
+            Message::SetActiveWorkbench { workbench_id } => {
+                project.set_active_workbench(*workbench_id)?;
+                Ok(MessageSuccess::Done)
+            }
+            Message::NewAssembly { name } => {
+                let id = project.add_assembly(name);
+                Ok(MessageSuccess::AssemblyCreated { id })
+            }
+            Message::DeleteAssembly { assembly_id } => {
+                project.delete_assembly(*assembly_id)?;
+                Ok(MessageSuccess::Done)
+            }
+            Message::AddInstance {
+                assembly_id,
+                name,
+                component,
+                transform,
+            } => {
+                let id =
+                    project.add_instance(*assembly_id, name, component.clone(), transform.clone())?;
+                Ok(MessageSuccess::InstanceCreated { id })
+            }
+            Message::SetInstanceTransform {
+                assembly_id,
+                instance_id,
+                transform,
+            } => {
+                let assembly = project.get_assembly_by_id_mut(*assembly_id)?;
+                assembly.get_instance_mut(instance_id)?.transform = transform.clone();
+                Ok(MessageSuccess::Done)
+            }
+            Message::RemoveInstance {
+                assembly_id,
+                instance_id,
+            } => {
+                let assembly = project.get_assembly_by_id_mut(*assembly_id)?;
+                assembly.remove_instance(instance_id)?;
+                Ok(MessageSuccess::Done)
+            }
+            Message::AddMate {
+                assembly_id,
+                name,
+                kind,
+            } => {
+                let assembly = project.get_assembly_by_id_mut(*assembly_id)?;
+                for instance_id in kind.instance_ids() {
+                    assembly.get_instance(instance_id)?;
+                }
+                let id = assembly.add_mate(name, kind.clone());
+                Ok(MessageSuccess::MateCreated { id })
+            }
+            Message::RemoveMate {
+                assembly_id,
+                mate_id,
+            } => {
+                let assembly = project.get_assembly_by_id_mut(*assembly_id)?;
+                assembly.remove_mate(mate_id)?;
+                Ok(MessageSuccess::Done)
+            }
+            Message::SolveMates { assembly_id } => {
+                let solution = project.solve_mates(*assembly_id)?;
+                Ok(MessageSuccess::MatesSolved {
+                    over_constrained: solution.over_constrained,
+                    redundant: solution.redundant,
+                    unsatisfied: solution.unsatisfied,
+                })
+            }
+            Message::GetMassProperties {
+                workbench_id,
+                solid_id,
+                density,
+                tolerance,
+            } => {
+                let wb = project.get_workbench_by_id(*workbench_id)?;
+                let realization = wb.realize(wb.history.len() as u64);
+                let properties = match solid_id {
+                    Some(solid_id) => {
+                        realization.solid_mass_properties(solid_id, *density, *tolerance)?
+                    }
+                    None => realization.mass_properties(*density, *tolerance),
+                };
+                Ok(MessageSuccess::MassProperties { properties })
+            }
+            Message::Measure { workbench_id, a, b } => {
+                let wb = project.get_workbench_by_id(*workbench_id)?;
+                let measurement = wb.realize(wb.history.len() as u64).measure(a, b.as_ref())?;
+                Ok(MessageSuccess::Measured { measurement })
+            }
+            Message::SetMaterial {
+                workbench_id,
+                solid_id,
+                material,
+            } => {
+                project
+                    .get_workbench_by_id_mut(*workbench_id)?
+                    .set_material(solid_id.as_deref(), material.clone())?;
+                Ok(MessageSuccess::Done)
             }
             Message::RenameStep {
                 workbench_id,
@@ -183,7 +701,9 @@ impl Message {
                     .ok_or(CADmiumError::StepIDNotFound(step_id.to_string()))?
                     .name = new_name.to_owned();
 
-                Ok(format!("\"name\": \"{}\"", new_name))
+                Ok(MessageSuccess::Renamed {
+                    name: new_name.to_owned(),
+                })
             }
             Message::DeleteLines {
                 workbench_id,
@@ -195,7 +715,7 @@ impl Message {
                 for line_id in line_ids {
                     sketch.delete_line_segment(*line_id);
                 }
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::DeleteArcs {
                 workbench_id,
@@ -207,7 +727,7 @@ impl Message {
                 for arc_id in arc_ids {
                     sketch.delete_arc(*arc_id);
                 }
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::DeleteCircles {
                 workbench_id,
@@ -219,7 +739,7 @@ impl Message {
                 for circle_id in circle_ids {
                     sketch.delete_circle(*circle_id);
                 }
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::NewPointOnSketch2 {
                 workbench_id,
@@ -237,7 +757,7 @@ impl Message {
                     point_id = sketch.add_point(*x, *y);
                 }
 
-                Ok(format!("\"id\": \"{}\"", point_id))
+                Ok(MessageSuccess::SketchEntityCreated { id: point_id })
             }
             Message::NewCircleBetweenPoints {
                 workbench_id,
@@ -248,7 +768,7 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_by_id_mut(sketch_id)?;
                 let circle_id = sketch.add_circle_between_points(*center_id, *edge_id);
-                Ok(format!("\"id\": \"{}\"", circle_id))
+                Ok(MessageSuccess::SketchEntityCreated { id: circle_id })
             }
             Message::NewRectangleBetweenPoints {
                 workbench_id,
@@ -259,11 +779,10 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_by_id_mut(sketch_id)?;
                 let (point_ids, line_ids) = sketch.add_rectangle_between_points(*start_id, *end_id);
-                Ok(format!(
-                    "\"point_ids\": [{}], \"line_ids\": [{}]",
-                    point_ids.iter().join(","),
-                    line_ids.iter().join(",")
-                ))
+                Ok(MessageSuccess::RectangleCreated {
+                    point_ids,
+                    line_ids,
+                })
             }
             Message::NewPointOnSketch {
                 workbench_id,
@@ -275,7 +794,7 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_by_id_mut(sketch_id)?;
                 sketch.add_point_with_id(*x, *y, *point_id)?;
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::NewLineOnSketch {
                 workbench_id,
@@ -286,7 +805,7 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_by_id_mut(sketch_id)?;
                 let line_id = sketch.add_segment(*start_point_id, *end_point_id);
-                Ok(format!("\"id\": \"{}\"", line_id))
+                Ok(MessageSuccess::SketchEntityCreated { id: line_id })
             }
             Message::DeleteLineSegment {
                 workbench_id,
@@ -296,7 +815,7 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_mut(sketch_name)?;
                 sketch.delete_line_segment(*line_segment_id);
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::StepSketch {
                 workbench_id,
@@ -309,7 +828,7 @@ impl Message {
                 for _ in 0..*steps {
                     max_change = sketch.take_a_step();
                 }
-                Ok(format!("{}", max_change))
+                Ok(MessageSuccess::SketchStepped { max_change })
             }
             Message::SolveSketch {
                 workbench_id,
@@ -319,7 +838,7 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
                 let sketch = workbench.get_sketch_mut(sketch_name)?;
                 sketch.solve(*max_steps);
-                Ok("".to_owned())
+                Ok(MessageSuccess::Done)
             }
             Message::NewSketchOnPlane {
                 workbench_id,
@@ -329,14 +848,35 @@ impl Message {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
 
                 let new_sketch_id = workbench.add_sketch_to_plane(&sketch_name, &plane_id);
-                Ok(format!("\"sketch_id\": \"{}\"", new_sketch_id))
+                Ok(MessageSuccess::StepCreated { id: new_sketch_id })
+            }
+            Message::NewSketchOnFace {
+                workbench_id,
+                sketch_name,
+                face,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+
+                let new_sketch_id = workbench.add_sketch_to_face(&sketch_name, face.to_owned());
+                Ok(MessageSuccess::StepCreated { id: new_sketch_id })
             }
             Message::SetSketchPlane {
                 workbench_id,
                 sketch_id,
                 plane_id: pid,
+                face,
             } => {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                if let Some(face) = face {
+                    // the face has to be there already by the time the sketch is realized
+                    let before = workbench.realize(workbench.step_index(&sketch_id)? as u64);
+                    face.index(&before).map_err(|_| {
+                        CADmiumError::FaceNotFound(
+                            face.solid_id.to_owned(),
+                            face.face_name.to_owned(),
+                        )
+                    })?;
+                }
                 let step = workbench.get_step_by_id_mut(&sketch_id)?;
                 let plane_description: &mut PlaneDescription = if let StepData::Sketch { plane_description, .. } = &mut step.data {
                     plane_description
@@ -344,27 +884,105 @@ impl Message {
                     return Err(CADmiumError::IncorrectStepDataType("Sketch".to_owned()).into());
                 };
 
-                match plane_description {
-                    PlaneDescription::PlaneId(ref mut plane_id) => {
-                        *plane_id = pid.to_owned();
-                        Ok(format!("\"plane_id\": \"{}\"", pid))
+                // Any attachment can be swapped for any other, including moving a sketch off of a face
+                match face {
+                    Some(face) => {
+                        *plane_description = PlaneDescription::Face(face.to_owned());
+                        Ok(MessageSuccess::StepUpdated { id: sketch_id.to_owned() })
+                    }
+                    None => {
+                        *plane_description = PlaneDescription::PlaneId(pid.to_owned());
+                        Ok(MessageSuccess::StepUpdated { id: sketch_id.to_owned() })
                     }
-                    _ => Err(CADmiumError::NotImplemented.into())
                 }
             }
             Message::DeleteStep {
                 workbench_id,
                 step_name,
+                cascade,
+                dry_run,
             } => {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
-                let index = workbench.history
+                let step_id = workbench
+                    .history
                     .iter()
-                    .position(|step| step.name == *step_name)
+                    .find(|step| step.name == *step_name)
+                    .map(|step| step.unique_id.clone())
                     .ok_or(CADmiumError::StepNameNotFound(step_name.to_owned()))?;
 
-                // Since the index was found and not given by the user, it should be safe to remove
-                workbench.history.remove(index);
-                Ok("".to_owned())
+                let affected = if *dry_run {
+                    workbench.dependency_graph().downstream(&step_id)
+                } else {
+                    workbench.delete_step(&step_id, *cascade)?
+                };
+                Ok(MessageSuccess::StepDeleted {
+                    id: step_id,
+                    dependents: affected,
+                    cascade: *cascade,
+                    dry_run: *dry_run,
+                })
+            }
+            Message::MoveStepBefore {
+                workbench_id,
+                step_id,
+                target_step_id,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.move_step(step_id, target_step_id, false)?;
+                Ok(MessageSuccess::StepUpdated {
+                    id: step_id.to_owned(),
+                })
+            }
+            Message::MoveStepAfter {
+                workbench_id,
+                step_id,
+                target_step_id,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.move_step(step_id, target_step_id, true)?;
+                Ok(MessageSuccess::StepUpdated {
+                    id: step_id.to_owned(),
+                })
+            }
+            Message::SetRollback {
+                workbench_id,
+                after_step_id,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.set_rollback(after_step_id.as_deref())?;
+                Ok(MessageSuccess::RollbackMoved {
+                    rollback_index: workbench.rollback_index(),
+                })
+            }
+            Message::MoveRollback {
+                workbench_id,
+                steps,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.move_rollback(*steps);
+                Ok(MessageSuccess::RollbackMoved {
+                    rollback_index: workbench.rollback_index(),
+                })
+            }
+            Message::SuppressStep {
+                workbench_id,
+                step_id,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.set_suppressed(step_id, true)?;
+                Ok(MessageSuccess::StepUpdated {
+                    id: step_id.to_owned(),
+                })
+            }
+            Message::UnsuppressStep {
+                workbench_id,
+                step_id,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                workbench.set_suppressed(step_id, false)?;
+                Ok(MessageSuccess::StepUpdated {
+                    id: step_id.to_owned(),
+                })
             }
             Message::NewExtrusion {
                 workbench_id,
@@ -385,7 +1003,9 @@ impl Message {
                     ExtrusionMode::New,
                 );
                 let extrusion_id = workbench.add_extrusion(extrusion_name, extrusion);
-                Ok(format!("\"id\": \"{}\"", extrusion_id))
+                Ok(MessageSuccess::StepCreated {
+                    id: format!("Extrusion-{}", extrusion_id),
+                })
             }
             Message::UpdateExtrusion {
                 workbench_id,
@@ -398,7 +1018,7 @@ impl Message {
                 direction,
             } => {
                 let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
-                let extrusion = Extrusion::new(
+                let mut extrusion = Extrusion::new(
                     sketch_id.to_owned(),
                     face_ids.to_owned(),
                     *length,
@@ -406,9 +1026,12 @@ impl Message {
                     direction.to_owned(),
                     ExtrusionMode::New,
                 );
+                extrusion.face_names = workbench.sketch_face_names(sketch_id, face_ids).unwrap_or_default();
                 let as_step_data = StepData::Extrusion { extrusion };
                 workbench.update_step_data(extrusion_id, as_step_data);
-                Ok(format!("\"id\": \"{}\"", extrusion_id))
+                Ok(MessageSuccess::StepUpdated {
+                    id: extrusion_id.to_owned(),
+                })
             }
             Message::UpdateExtrusionLength {
                 workbench_id,
@@ -420,11 +1043,182 @@ impl Message {
 
                 if let StepData::Extrusion { extrusion } = &mut step.data {
                     extrusion.length = *length;
-                    return Ok(format!("\"length\": {}", length));
+                    return Ok(MessageSuccess::LengthUpdated {
+                        id: step.unique_id.clone(),
+                        length: *length,
+                    });
                 }
 
                 Err(CADmiumError::IncorrectStepDataType("Extrusion".to_owned()).into())
             }
+            Message::NewDatumPlane {
+                workbench_id,
+                plane_name,
+                definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let plane_id = workbench.add_datum_plane(plane_name, definition.to_owned());
+                Ok(MessageSuccess::StepCreated { id: plane_id })
+            }
+            Message::UpdateDatumPlane {
+                workbench_id,
+                plane_id,
+                definition: new_definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let step = workbench.get_step_by_id_mut(plane_id)?;
+
+                if let StepData::DatumPlane { definition, .. } = &mut step.data {
+                    *definition = new_definition.to_owned();
+                    return Ok(MessageSuccess::StepUpdated {
+                        id: plane_id.to_owned(),
+                    });
+                }
+
+                Err(CADmiumError::IncorrectStepDataType("DatumPlane".to_owned()).into())
+            }
+            Message::NewDatumPoint {
+                workbench_id,
+                point_name,
+                definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let point_id = workbench.add_datum_point(point_name, definition.to_owned());
+                Ok(MessageSuccess::StepCreated { id: point_id })
+            }
+            Message::UpdateDatumPoint {
+                workbench_id,
+                point_id,
+                definition: new_definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let step = workbench.get_step_by_id_mut(point_id)?;
+
+                if let StepData::DatumPoint { definition } = &mut step.data {
+                    *definition = new_definition.to_owned();
+                    return Ok(MessageSuccess::StepUpdated {
+                        id: point_id.to_owned(),
+                    });
+                }
+
+                Err(CADmiumError::IncorrectStepDataType("DatumPoint".to_owned()).into())
+            }
+            Message::NewAxis {
+                workbench_id,
+                axis_name,
+                definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let axis_id = workbench.add_axis(axis_name, definition.to_owned());
+                Ok(MessageSuccess::StepCreated { id: axis_id })
+            }
+            Message::UpdateAxis {
+                workbench_id,
+                axis_id,
+                definition: new_definition,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let step = workbench.get_step_by_id_mut(axis_id)?;
+
+                if let StepData::Axis { definition, .. } = &mut step.data {
+                    *definition = new_definition.to_owned();
+                    return Ok(MessageSuccess::StepUpdated {
+                        id: axis_id.to_owned(),
+                    });
+                }
+
+                Err(CADmiumError::IncorrectStepDataType("Axis".to_owned()).into())
+            }
+            Message::NewHole {
+                workbench_id,
+                hole_name,
+                sketch_id,
+                point_ids,
+                diameter,
+                hole_type,
+                depth,
+                merge_scope,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let hole = Hole::new(
+                    sketch_id.to_owned(),
+                    point_ids.to_owned(),
+                    *diameter,
+                    hole_type.to_owned(),
+                    depth.to_owned(),
+                    merge_scope.to_owned(),
+                );
+                let hole_id = workbench.add_hole(hole_name, hole);
+                Ok(MessageSuccess::StepCreated { id: hole_id })
+            }
+            Message::NewStandardHole {
+                workbench_id,
+                hole_name,
+                sketch_id,
+                point_ids,
+                designation,
+                fit,
+                kind,
+                depth,
+                merge_scope,
+            } => {
+                let workbench = project.get_workbench_by_id_mut(*workbench_id)?;
+                let hole = Hole::from_standard(
+                    sketch_id.to_owned(),
+                    point_ids.to_owned(),
+                    designation,
+                    *fit,
+                    *kind,
+                    depth.to_owned(),
+                    merge_scope.to_owned(),
+                )?;
+                let hole_id = workbench.add_hole(hole_name, hole);
+                Ok(MessageSuccess::StepCreated { id: hole_id })
+            }
+        }
+    }
+}
+
+fn undo_state(project: &Project) -> MessageSuccess {
+    MessageSuccess::UndoState {
+        can_undo: project.undo_stack.can_undo(),
+        can_redo: project.undo_stack.can_redo(),
+    }
+}
+
+// Replaces objects like { "$ref": 2, "field": "id" } with that field of the third result so
+// far in a batch. Anything else, including strings that start with "$", is left as it is
+fn substitute_results(
+    value: &serde_json::Value,
+    results: &[serde_json::Value],
+) -> Result<serde_json::Value, CADmiumError> {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(fields) if fields.contains_key("$ref") => {
+            let found = match (fields.get("$ref"), fields.get("field"), fields.len()) {
+                (Some(Value::Number(index)), Some(Value::String(field)), 2) => index
+                    .as_u64()
+                    .and_then(|index| results.get(index as usize)?.get(field)),
+                _ => None,
+            };
+
+            found
+                .cloned()
+                .ok_or(CADmiumError::BatchReferenceNotFound(value.to_string()))
         }
+        Value::Array(items) => Ok(Value::Array(
+            items
+                .iter()
+                .map(|item| substitute_results(item, results))
+                .collect::<Result<_, _>>()?,
+        )),
+        Value::Object(fields) => Ok(Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| Ok((key.clone(), substitute_results(field, results)?)))
+                .collect::<Result<_, CADmiumError>>()?,
+        )),
+        other => Ok(other.clone()),
     }
 }
@@ -0,0 +1,440 @@
+// Persistent names for the geometry that features generate.
+//
+// Sketch faces are named by the sorted ids of the points around their exterior, so an
+// extrusion can find the same region again after the sketch is edited and its faces are
+// re-sorted. Solid faces are named after the step and the sketch entity that made them,
+// like "Extrusion-0/Side/L3-4" or "Extrusion-0/End/1-2-3-4", and keep those names through
+// later boolean operations. Edges and vertices are named by the faces that meet there.
+
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::archetypes::{Plane, Point3, Vector3};
+use crate::error::CADmiumError;
+use crate::geometry::{closest_point_on_segment, closest_point_on_triangle};
+use crate::project::RealSketch;
+use crate::sketch::{Face, Ring, Segment};
+use crate::solid::{face_plane, FaceMesh, Solid};
+use crate::step::Dependency;
+
+// The tessellation tolerance used when matching faces across boolean operations
+pub const NAMING_TOLERANCE: f64 = 0.01;
+
+// Solid vertices are built directly from sketch points, so they line up almost exactly
+const POINT_TOLERANCE: f64 = 1e-6;
+
+fn ring_point_ids(ring: &Ring) -> Vec<u64> {
+    match ring {
+        Ring::Circle(circle) => vec![circle.center, circle.top],
+        Ring::Segments(segments) => segments
+            .iter()
+            .flat_map(|segment| match segment {
+                Segment::Line(line) => vec![line.start, line.end],
+                Segment::Arc(arc) => vec![arc.start, arc.end],
+            })
+            .collect(),
+    }
+}
+
+pub fn sketch_face_name(face: &Face) -> String {
+    ring_point_ids(&face.exterior)
+        .into_iter()
+        .sorted()
+        .dedup()
+        .join("-")
+}
+
+fn name_to_point_ids(name: &str) -> HashSet<u64> {
+    name.split('-').filter_map(|id| id.parse().ok()).collect()
+}
+
+// What a face, edge or vertex name builds on: the steps that generated the faces it
+// mentions. Faces that couldn't be traced back to a step are named after their solid,
+// like "Extrusion-0:0/Face3", and depend on that solid instead. Step IDs never contain
+// a colon
+pub fn dependencies_in_name(name: &str) -> Vec<Dependency> {
+    name.split('|')
+        .filter_map(|face_name| face_name.split('/').next())
+        .unique()
+        .map(|prefix| match prefix.contains(':') {
+            true => Dependency::Solid(prefix.to_owned()),
+            false => Dependency::Step(prefix.to_owned()),
+        })
+        .collect()
+}
+
+// Finds the current index of each named sketch face. An exact match wins. Otherwise the
+// face that shares the largest fraction of its points with the named one is used, as
+// long as they share at least half of them and no other face shares as many. Partial
+// matches are pushed onto warnings, and so are names that two faces match equally well,
+// which are left out rather than guessed at
+pub fn resolve_sketch_faces(
+    sketch_id: &str,
+    faces: &[Face],
+    names: &[String],
+    warnings: &mut Vec<CADmiumError>,
+) -> Result<Vec<u64>, CADmiumError> {
+    let current: Vec<String> = faces.iter().map(sketch_face_name).collect();
+    let not_found =
+        |name: &String| CADmiumError::SketchFaceNotFound(sketch_id.to_owned(), name.to_owned());
+
+    let mut indices = vec![];
+    for name in names.iter() {
+        if let Some(index) = current.iter().position(|candidate| candidate == name) {
+            indices.push(index as u64);
+            continue;
+        }
+
+        let wanted = name_to_point_ids(name);
+        let scores: Vec<f64> = current
+            .iter()
+            .map(|candidate| {
+                let ids = name_to_point_ids(candidate);
+                let union = wanted.union(&ids).count().max(1) as f64;
+                wanted.intersection(&ids).count() as f64 / union
+            })
+            .collect();
+        let best = scores.iter().cloned().fold(0.0, f64::max);
+        if best < 0.5 {
+            return Err(not_found(name));
+        }
+
+        let closest: Vec<usize> = (0..scores.len())
+            .filter(|index| (scores[*index] - best).abs() < 1e-9)
+            .collect();
+        match closest[..] {
+            [index] => {
+                indices.push(index as u64);
+                warnings.push(CADmiumError::SketchFaceChanged(
+                    sketch_id.to_owned(),
+                    name.to_owned(),
+                ));
+            }
+            _ => warnings.push(CADmiumError::SketchFaceAmbiguous(
+                sketch_id.to_owned(),
+                name.to_owned(),
+            )),
+        }
+    }
+
+    // a step with none of its faces left has nothing to work with
+    match names.first() {
+        Some(name) if indices.is_empty() => Err(not_found(name)),
+        _ => Ok(indices),
+    }
+}
+
+// Later copies of a repeated name get a "#2", "#3"... suffix so every name is unique
+pub fn unique_names(names: Vec<String>) -> Vec<String> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let count = seen.entry(name.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                name
+            } else {
+                format!("{}#{}", name, count)
+            }
+        })
+        .collect()
+}
+
+// The label of the sketch segment whose sweep produced a side face, given the face's
+// vertices pushed back onto the sketch plane
+fn side_label(sketch: &RealSketch, face: &Face, base: &[Point3], planar: bool) -> Option<String> {
+    let near = |a: &Point3, b: &Point3| a.distance_to(b) < POINT_TOLERANCE;
+    let on_circle = |center: &Point3, radius: f64| {
+        base.iter()
+            .all(|p| (p.distance_to(center) - radius).abs() < POINT_TOLERANCE)
+    };
+
+    // (label, is straight, how many of the face's vertices are the segment's endpoints)
+    let mut candidates: Vec<(String, bool, usize)> = vec![];
+    for ring in std::iter::once(&face.exterior).chain(face.holes.iter()) {
+        match ring {
+            Ring::Circle(circle) => {
+                if let Some(center) = sketch.points.get(&circle.center) {
+                    if on_circle(center, circle.radius) {
+                        candidates.push((format!("C{}", circle.center), false, 0));
+                    }
+                }
+            }
+            Ring::Segments(segments) => {
+                for segment in segments.iter() {
+                    let (start_id, end_id) = match segment {
+                        Segment::Line(line) => (line.start, line.end),
+                        Segment::Arc(arc) => (arc.start, arc.end),
+                    };
+                    let (start, end) =
+                        match (sketch.points.get(&start_id), sketch.points.get(&end_id)) {
+                            (Some(start), Some(end)) => (start, end),
+                            _ => continue,
+                        };
+                    let endpoint_hits = base
+                        .iter()
+                        .filter(|p| near(p, start) || near(p, end))
+                        .count();
+                    let (low, high) = (start_id.min(end_id), start_id.max(end_id));
+
+                    match segment {
+                        Segment::Line(_) => {
+                            if base
+                                .iter()
+                                .all(|p| near(&closest_point_on_segment(start, end, p), p))
+                            {
+                                candidates.push((
+                                    format!("L{}-{}", low, high),
+                                    true,
+                                    endpoint_hits,
+                                ));
+                            }
+                        }
+                        Segment::Arc(arc) => {
+                            if let Some(center) = sketch.points.get(&arc.center) {
+                                if on_circle(center, center.distance_to(start)) {
+                                    candidates.push((
+                                        format!("A{}-{}-{}", arc.center, low, high),
+                                        false,
+                                        endpoint_hits,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .max_by_key(|(_, straight, hits)| (*straight == planar, *hits))
+        .map(|(label, _, _)| label)
+}
+
+// Names every face of a freshly extruded solid. The caps are named after the sketch face
+// and the sides after the sketch segment they were swept from. `direction` is the
+// extrusion direction, and the caps sit (start, end) multiples of it off the plane
+pub fn name_extrusion_faces(
+    solid: &mut Solid,
+    step_id: &str,
+    plane: &Plane,
+    sketch: &RealSketch,
+    face: &Face,
+    direction: &Vector3,
+    (start, end): (f64, f64),
+) {
+    let speed = direction.dot(&plane.tertiary);
+    if speed.abs() < 1e-12 {
+        // an extrusion along the sketch plane has no volume to name
+        return;
+    }
+
+    let cap_name = sketch_face_name(face);
+    let at =
+        |height: f64, target: f64| (height - target).abs() < POINT_TOLERANCE * (1.0 + target.abs());
+
+    let names = solid
+        .faces()
+        .iter()
+        .enumerate()
+        .map(|(face_index, truck_face)| {
+            let mut points = vec![];
+            for wire in truck_face.boundaries().iter() {
+                for vertex in wire.vertex_iter() {
+                    let p = vertex.point();
+                    points.push(Point3::new(p.x, p.y, p.z));
+                }
+            }
+            let heights: Vec<f64> = points
+                .iter()
+                .map(|p| plane.distance_to(p) / speed)
+                .collect();
+
+            if heights.iter().all(|h| at(*h, start)) {
+                format!("{}/Start/{}", step_id, cap_name)
+            } else if heights.iter().all(|h| at(*h, end)) {
+                format!("{}/End/{}", step_id, cap_name)
+            } else {
+                let base: Vec<Point3> = points
+                    .iter()
+                    .zip(heights.iter())
+                    .filter(|(_, h)| at(**h, start))
+                    .map(|(p, h)| p.plus(direction.times(-h)).to_point3())
+                    .collect();
+                let planar = face_plane(truck_face).is_some();
+                match side_label(sketch, face, &base, planar) {
+                    Some(label) => format!("{}/Side/{}", step_id, label),
+                    None => format!("{}/Side/{}", step_id, face_index),
+                }
+            }
+        })
+        .collect();
+
+    solid.face_names = unique_names(names);
+}
+
+// The centroid of a face's largest triangle, along with that triangle's normal
+fn sample(mesh: &FaceMesh) -> Option<(Point3, Vector3)> {
+    mesh.triangle_points()
+        .map(|(a, b, c)| {
+            let normal = b.minus(a).cross(&c.minus(a));
+            let centroid = Point3::new(
+                (a.x + b.x + c.x) / 3.0,
+                (a.y + b.y + c.y) / 3.0,
+                (a.z + b.z + c.z) / 3.0,
+            );
+            (normal.length(), centroid, normal)
+        })
+        .filter(|(area, _, _)| *area > 0.0)
+        .max_by(|(a, _, _), (b, _, _)| a.total_cmp(b))
+        .map(|(_, centroid, normal)| (centroid, normal.normalize()))
+}
+
+// After a boolean operation every face of the result is a piece of some face of the
+// inputs. Each one takes the name of the input face it lies on
+pub fn inherit_face_names(solid: &mut Solid, sources: &[&Solid]) {
+    let source_meshes: Vec<(&Solid, Vec<FaceMesh>)> = sources
+        .iter()
+        .map(|source| (*source, source.face_meshes(NAMING_TOLERANCE)))
+        .collect();
+
+    let names = solid
+        .face_meshes(NAMING_TOLERANCE)
+        .iter()
+        .map(|mesh| {
+            let fallback = format!("{}/Face{}", solid.name, mesh.face_id);
+            let (point, normal) = match sample(mesh) {
+                Some(sample) => sample,
+                None => return fallback,
+            };
+
+            let mut best: Option<(f64, String)> = None;
+            for (source, meshes) in source_meshes.iter() {
+                for source_mesh in meshes.iter() {
+                    for (a, b, c) in source_mesh.triangle_points() {
+                        // subtracting turns the tool inside out, so either facing is fine
+                        let source_normal = b.minus(a).cross(&c.minus(a));
+                        if source_normal.length() == 0.0
+                            || source_normal.normalize().dot(&normal).abs() < 0.9
+                        {
+                            continue;
+                        }
+
+                        let distance =
+                            closest_point_on_triangle(a, b, c, &point).distance_to(&point);
+                        if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                            best = Some((distance, source.face_name(source_mesh.face_id)));
+                        }
+                    }
+                }
+            }
+
+            match best {
+                Some((distance, name)) if distance < 4.0 * NAMING_TOLERANCE => name,
+                _ => fallback,
+            }
+        })
+        .collect();
+
+    solid.face_names = unique_names(names);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::Line2;
+
+    fn square(ids: [u64; 4]) -> Face {
+        let segments = (0..4)
+            .map(|i| {
+                Segment::Line(Line2 {
+                    start: ids[i],
+                    end: ids[(i + 1) % 4],
+                })
+            })
+            .collect();
+        Face::from_ring(&Ring::Segments(segments))
+    }
+
+    #[test]
+    fn sketch_faces_are_found_again_after_reordering() {
+        let small = square([4, 5, 6, 7]);
+        let big = square([0, 1, 2, 3]);
+        let names = vec![sketch_face_name(&big)];
+        assert_eq!(names[0], "0-1-2-3");
+
+        let mut warnings = vec![];
+        let faces = vec![small.clone(), big.clone()];
+        assert_eq!(
+            resolve_sketch_faces("Sketch-0", &faces, &names, &mut warnings).unwrap(),
+            vec![1]
+        );
+        assert!(warnings.is_empty());
+
+        // one of the big square's corners was swapped for a new point
+        let faces = vec![square([0, 1, 2, 8]), small.clone()];
+        assert_eq!(
+            resolve_sketch_faces("Sketch-0", &faces, &names, &mut warnings).unwrap(),
+            vec![0]
+        );
+        assert!(matches!(
+            warnings[..],
+            [CADmiumError::SketchFaceChanged(..)]
+        ));
+
+        let faces = vec![square([10, 11, 12, 13])];
+        assert!(resolve_sketch_faces("Sketch-0", &faces, &names, &mut warnings).is_err());
+    }
+
+    #[test]
+    fn sketch_faces_split_in_two_are_not_guessed_at() {
+        let small = square([4, 5, 6, 7]);
+        let names = vec!["0-1-2-3".to_owned(), sketch_face_name(&small)];
+
+        // either half of the old face shares as much of it as the other
+        let mut warnings = vec![];
+        let faces = vec![square([0, 1, 2, 8]), square([0, 1, 2, 9]), small];
+        assert_eq!(
+            resolve_sketch_faces("Sketch-0", &faces, &names, &mut warnings).unwrap(),
+            vec![2]
+        );
+        assert!(matches!(
+            warnings[..],
+            [CADmiumError::SketchFaceAmbiguous(..)]
+        ));
+
+        let names = &names[..1];
+        assert!(resolve_sketch_faces("Sketch-0", &faces, names, &mut warnings).is_err());
+    }
+
+    #[test]
+    fn edge_names_mention_their_steps() {
+        let edge = "Extrusion-0/End/1-2-3-4|Hole-2/7/Face1";
+        assert_eq!(
+            dependencies_in_name(edge),
+            vec![
+                Dependency::Step("Extrusion-0".to_owned()),
+                Dependency::Step("Hole-2".to_owned())
+            ]
+        );
+
+        let vertex = "Extrusion-0:0/Face3|Extrusion-0/Side/L1-2";
+        assert_eq!(
+            dependencies_in_name(vertex),
+            vec![
+                Dependency::Solid("Extrusion-0:0".to_owned()),
+                Dependency::Step("Extrusion-0".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_names_are_made_unique() {
+        let names = unique_names(vec!["a".to_owned(), "b".to_owned(), "a".to_owned()]);
+        assert_eq!(names, vec!["a", "b", "a#2"]);
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::archetypes::{Point3, Vector3};
+use crate::datum::EdgeReference;
+use crate::geometry::{closest_point_on_polyline, closest_points_between_segments, Bounds};
+use crate::realization::Realization;
+
+// Where a ray first enters a solid. Edges and vertices count as hit when the hit point is
+// within the tolerance of them
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SolidHit {
+    pub solid_id: String,
+    // the face's position in face_meshes, and its persistent name
+    pub face_id: u64,
+    pub face_name: String,
+    pub edge_name: Option<String>,
+    pub vertex_name: Option<String>,
+    pub point: Point3,
+    pub normal: Vector3,
+    // along the ray, in units of its direction
+    pub distance: f64,
+}
+
+// A sketch point, line, circle or arc that the ray passes within the tolerance of, at its
+// distance along the ray
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SketchHit {
+    pub sketch_id: String,
+    // like "Point-3" or "Line-1"
+    pub entity_id: String,
+    // the point on the entity nearest the ray
+    pub point: Point3,
+    pub distance: f64,
+    // how far the ray passes from the entity
+    pub miss: f64,
+}
+
+#[derive(Tsify, Debug, Clone, Default, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PickResult {
+    pub solid: Option<SolidHit>,
+    // nearest the ray first, leaving out any hidden behind the solid
+    pub sketch_entities: Vec<SketchHit>,
+}
+
+// Möller–Trumbore: how far along the ray it crosses the triangle, with the barycentric
+// coordinates of the crossing
+fn ray_triangle(
+    origin: &Point3,
+    direction: &Vector3,
+    a: &Point3,
+    b: &Point3,
+    c: &Point3,
+) -> Option<(f64, f64, f64)> {
+    let e1 = b.minus(a);
+    let e2 = c.minus(a);
+    let p = direction.cross(&e2);
+    let determinant = e1.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+
+    let s = origin.minus(a);
+    let u = s.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(&q) * inverse;
+    (t > 0.0).then_some((t, u, v))
+}
+
+// Finds the nearest solid face the ray hits, and the sketch entities it passes close to.
+// Something counts as close when it is within `tolerance + spread * distance`, the distance
+// being how far along the ray it is. An orthographic camera only needs the tolerance, the
+// size of a few pixels in model units; a perspective camera only needs the spread, the size
+// of those pixels at one unit of depth
+pub fn pick(
+    realized: &Realization,
+    origin: &Point3,
+    direction: &Vector3,
+    tolerance: f64,
+    spread: f64,
+) -> PickResult {
+    let direction = direction.normalize();
+    let mut result = PickResult::default();
+    let tolerance_at = |distance: f64| tolerance + spread * distance;
+
+    let mut solid_ids: Vec<&String> = realized.solids.keys().collect();
+    solid_ids.sort();
+    for solid_id in solid_ids {
+        // the mesh the solid is drawn with, so the ray hits what the user sees
+        let solid = &realized.solids[solid_id];
+        let points: Vec<Point3> = solid.vertices.iter().map(Vector3::to_point3).collect();
+        let entry = Bounds::around(points.iter()).entered_by(origin, &direction);
+        if entry.is_none_or(|t| result.solid.as_ref().is_some_and(|hit| hit.distance <= t)) {
+            continue;
+        }
+
+        for (triangle, face_id) in solid.indices.chunks(3).zip(solid.triangle_faces.iter()) {
+            let [i, j, k] = [triangle[0], triangle[1], triangle[2]];
+            let (a, b, c) = (&points[i], &points[j], &points[k]);
+            let Some((t, u, v)) = ray_triangle(origin, &direction, a, b, c) else {
+                continue;
+            };
+            if result.solid.as_ref().is_some_and(|hit| hit.distance <= t) {
+                continue;
+            }
+
+            // the surface normal there, falling back to the triangle's own
+            let interpolated = solid.normals[i]
+                .times(1.0 - u - v)
+                .plus(solid.normals[j].times(u))
+                .plus(solid.normals[k].times(v));
+            let normal = if interpolated.length() > 1e-9 {
+                interpolated.normalize()
+            } else {
+                b.minus(a).cross(&c.minus(a)).normalize()
+            };
+
+            result.solid = Some(SolidHit {
+                solid_id: solid_id.clone(),
+                face_id: *face_id,
+                face_name: solid.face_name(*face_id),
+                edge_name: None,
+                vertex_name: None,
+                point: origin.plus(direction.times(t)).to_point3(),
+                normal,
+                distance: t,
+            });
+        }
+    }
+
+    if let Some(hit) = result.solid.as_mut() {
+        let solid = &realized.solids[&hit.solid_id];
+        let tolerance = tolerance_at(hit.distance);
+        let nearest = |distances: Vec<f64>, names: Vec<String>| {
+            distances
+                .into_iter()
+                .zip(names)
+                .filter(|(distance, _)| *distance <= tolerance)
+                .min_by(|(x, _), (y, _)| x.total_cmp(y))
+                .map(|(_, name)| name)
+        };
+
+        let edge_distances = solid
+            .edges
+            .iter()
+            .map(|edge| {
+                closest_point_on_polyline(&edge.points, &hit.point)
+                    .map_or(f64::MAX, |(p, _)| p.distance_to(&hit.point))
+            })
+            .collect();
+        hit.edge_name = nearest(edge_distances, solid.edge_names());
+
+        let vertex_distances = solid
+            .vertex_points()
+            .iter()
+            .map(|p| p.distance_to(&hit.point))
+            .collect();
+        hit.vertex_name = nearest(vertex_distances, solid.vertex_names());
+    }
+
+    // sketch entities on the hit face still count, but not ones further behind it
+    let depth_limit = result
+        .solid
+        .as_ref()
+        .map_or(f64::MAX, |hit| hit.distance + tolerance_at(hit.distance));
+    let mut sketch_ids: Vec<&String> = realized.sketches.keys().collect();
+    sketch_ids.sort();
+    for sketch_id in sketch_ids {
+        let sketch = &realized.sketches[sketch_id].0;
+        let mut consider = |entity_id: String, closest: Option<(f64, f64, Point3)>| {
+            if let Some((distance, miss, point)) = closest {
+                if miss <= tolerance_at(distance) && distance <= depth_limit {
+                    result.sketch_entities.push(SketchHit {
+                        sketch_id: sketch_id.clone(),
+                        entity_id,
+                        point,
+                        distance,
+                        miss,
+                    });
+                }
+            }
+        };
+
+        let mut point_ids: Vec<&u64> = sketch.points.keys().collect();
+        point_ids.sort();
+        for point_id in point_ids {
+            let point = &sketch.points[point_id];
+            if point.hidden {
+                continue;
+            }
+            let t = point.minus(origin).dot(&direction);
+            let closest = (t >= 0.0).then(|| {
+                let on_ray = origin.plus(direction.times(t)).to_point3();
+                (t, on_ray.distance_to(point), point.clone())
+            });
+            consider(format!("Point-{}", point_id), closest);
+        }
+
+        let mut edges: Vec<(String, EdgeReference)> = vec![];
+        for line_id in sketch.line_segments.keys() {
+            edges.push((
+                format!("Line-{}", line_id),
+                EdgeReference::SketchLine {
+                    sketch_id: sketch_id.clone(),
+                    line_id: *line_id,
+                },
+            ));
+        }
+        for circle_id in sketch.circles.keys() {
+            edges.push((
+                format!("Circle-{}", circle_id),
+                EdgeReference::SketchCircle {
+                    sketch_id: sketch_id.clone(),
+                    circle_id: *circle_id,
+                },
+            ));
+        }
+        for arc_id in sketch.arcs.keys() {
+            edges.push((
+                format!("Arc-{}", arc_id),
+                EdgeReference::SketchArc {
+                    sketch_id: sketch_id.clone(),
+                    arc_id: *arc_id,
+                },
+            ));
+        }
+        edges.sort_by(|(x, _), (y, _)| x.cmp(y));
+
+        for (entity_id, edge) in edges {
+            let Ok(points) = edge.polyline(realized) else {
+                continue;
+            };
+            consider(entity_id, closest_to_ray(origin, &direction, &points));
+        }
+    }
+    result
+        .sketch_entities
+        .sort_by(|x, y| x.miss.total_cmp(&y.miss));
+
+    result
+}
+
+// How far along the ray the polyline comes closest to it, how close, and the point on the
+// polyline where it does
+fn closest_to_ray(
+    origin: &Point3,
+    direction: &Vector3,
+    points: &[Point3],
+) -> Option<(f64, f64, Point3)> {
+    // the ray only needs to reach past the furthest point
+    let reach = points
+        .iter()
+        .map(|p| p.distance_to(origin))
+        .fold(0.0, f64::max)
+        * 2.0
+        + 1.0;
+    let end = origin.plus(direction.times(reach)).to_point3();
+
+    let mut best: Option<(f64, f64, Point3)> = None;
+    for pair in points.windows(2) {
+        let (on_ray, on_edge) = closest_points_between_segments(origin, &end, &pair[0], &pair[1]);
+        let miss = on_ray.distance_to(&on_edge);
+        if best.as_ref().is_none_or(|(_, m, _)| miss < *m) {
+            best = Some((on_ray.minus(origin).dot(direction), miss, on_edge));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbench::tests::block;
+
+    use super::*;
+
+    #[test]
+    fn rays_pick_faces_edges_and_sketch_lines() {
+        let (wb, block_sketch) = block();
+
+        let realized = wb.realize(1000);
+        let plane = &realized.planes["Plane-0"].plane;
+        let at = |x: f64, y: f64, z: f64| {
+            plane
+                .origin
+                .plus(plane.primary.times(x))
+                .plus(plane.secondary.times(y))
+                .plus(plane.tertiary.times(z))
+                .to_point3()
+        };
+
+        // straight down onto the middle of the top face, which hides the sketch below it
+        let down = plane.tertiary.times(-1.0);
+        let result = pick(&realized, &at(10.0, 5.0, 100.0), &down, 0.1, 0.0);
+        let hit = result.solid.unwrap();
+        assert_eq!(hit.solid_id, "Extrusion-0:0");
+        assert!((hit.distance - 90.0).abs() < 1e-6);
+        assert!(hit.normal.dot(&plane.tertiary) > 0.99);
+        assert!(hit.edge_name.is_none() && hit.vertex_name.is_none());
+        assert!(result.sketch_entities.is_empty());
+
+        // up from underneath, just inside one edge of the bottom face, which the sketch
+        // line along that edge lies on
+        let result = pick(&realized, &at(0.05, 5.0, -100.0), &plane.tertiary, 0.1, 0.0);
+        let hit = result.solid.unwrap();
+        assert!((hit.distance - 100.0).abs() < 1e-6);
+        assert!(hit.normal.dot(&plane.tertiary) < -0.99);
+        assert!(hit.edge_name.is_some());
+        assert!(hit.vertex_name.is_none());
+        assert_eq!(result.sketch_entities.len(), 1);
+        assert_eq!(result.sketch_entities[0].sketch_id, block_sketch.sketch_id);
+        assert_eq!(
+            result.sketch_entities[0].entity_id,
+            format!("Line-{}", block_sketch.sides[3])
+        );
+
+        // and right on a corner
+        let result = pick(
+            &realized,
+            &at(0.02, 0.02, -100.0),
+            &plane.tertiary,
+            0.1,
+            0.0,
+        );
+        assert!(result.solid.unwrap().vertex_name.is_some());
+
+        let result = pick(&realized, &at(50.0, 5.0, 100.0), &down, 0.1, 0.0);
+        assert!(result.solid.is_none());
+
+        // as seen by a perspective camera, the same 0.05 miss is close enough 100 away only
+        // if the pixels there are big enough
+        let edge_name = |spread: f64| {
+            let result = pick(
+                &realized,
+                &at(0.05, 5.0, -100.0),
+                &plane.tertiary,
+                0.0,
+                spread,
+            );
+            result.solid.unwrap().edge_name
+        };
+        assert!(edge_name(0.001).is_some());
+        assert!(edge_name(0.0001).is_none());
+    }
+}
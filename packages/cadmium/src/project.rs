@@ -3,13 +3,17 @@ use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
 use crate::archetypes::*;
+use crate::assembly::{Assembly, Component, Transform};
 use crate::error::CADmiumError;
+use crate::journal::Journal;
+use crate::mates::{self, MateSolution};
 use crate::realization::Realization;
 use crate::sketch::constraints::Constraint;
 use crate::sketch::{Face, Point2, Sketch};
 use crate::step::StepData;
+use crate::undo::UndoStack;
 use crate::workbench::Workbench;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Tsify, Debug, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
@@ -17,6 +21,20 @@ pub struct Project {
     pub name: String,
     pub assemblies: Vec<Assembly>,
     pub workbenches: Vec<Workbench>,
+    // the ID the next new workbench gets. IDs are never reused
+    #[serde(default)]
+    pub(crate) next_workbench_id: u64,
+    #[serde(default)]
+    pub(crate) next_assembly_id: u64,
+    // the ID of the workbench the user is working in
+    #[serde(default)]
+    pub active_workbench: u64,
+
+    #[serde(skip)]
+    pub(crate) undo_stack: UndoStack,
+    // saved separately, see journal.rs
+    #[serde(skip)]
+    pub(crate) journal: Journal,
 }
 
 impl Project {
@@ -25,10 +43,14 @@ impl Project {
             name: name.to_owned(),
             assemblies: vec![],
             workbenches: vec![],
+            next_workbench_id: 0,
+            next_assembly_id: 0,
+            active_workbench: 0,
+            undo_stack: UndoStack::default(),
+            journal: Journal::new(name),
         };
 
-        let wb = Workbench::new("Workbench 1");
-        p.workbenches.push(wb);
+        p.add_workbench(Workbench::new("Workbench 1"));
 
         p
     }
@@ -41,10 +63,17 @@ impl Project {
         }
     }
 
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
     pub fn from_json(json: &str) -> Self {
         let result = serde_json::from_str(json);
         match result {
-            Ok(p) => p,
+            Ok(mut p) => {
+                Project::assign_workbench_ids(&mut p);
+                p
+            }
             Err(e) => {
                 println!("Error: {}", e);
                 Project::new("Error")
@@ -52,6 +81,41 @@ impl Project {
         }
     }
 
+    pub fn undo(&mut self) -> Result<(), CADmiumError> {
+        let previous = self
+            .undo_stack
+            .undo(self.json())
+            .ok_or(CADmiumError::NothingToUndo)?;
+        self.restore(&previous)
+    }
+
+    pub fn redo(&mut self) -> Result<(), CADmiumError> {
+        let next = self
+            .undo_stack
+            .redo(self.json())
+            .ok_or(CADmiumError::NothingToRedo)?;
+        self.restore(&next)
+    }
+
+    // Swaps in a snapshot, keeping the undo stack and each workbench's realization cache.
+    // The caches are keyed by a hash of the steps' contents that doesn't depend on map
+    // order, so they stay valid for the snapshot too
+    pub(crate) fn restore(&mut self, json: &str) -> Result<(), CADmiumError> {
+        let mut restored: Project =
+            serde_json::from_str(json).map_err(|e| CADmiumError::RestoreFailed(e.to_string()))?;
+
+        for restored_wb in restored.workbenches.iter_mut() {
+            if let Ok(wb) = self.get_workbench_by_id_mut(restored_wb.id) {
+                restored_wb.cache = std::mem::take(&mut wb.cache);
+            }
+        }
+        restored.undo_stack = std::mem::take(&mut self.undo_stack);
+        restored.journal = std::mem::take(&mut self.journal);
+
+        *self = restored;
+        Ok(())
+    }
+
     pub fn compute_constraint_errors(&mut self) {
         for workbench in self.workbenches.iter_mut() {
             for step in workbench.history.iter_mut() {
@@ -72,23 +136,189 @@ impl Project {
             .ok_or(CADmiumError::WorkbenchNameNotFound(name.to_string()))
     }
 
+    pub fn get_workbench_by_id(&self, id: u64) -> Result<&Workbench, CADmiumError> {
+        self.workbenches
+            .iter()
+            .find(|wb| wb.id == id)
+            .ok_or(CADmiumError::WorkbenchIDNotFound(id))
+    }
+
     pub fn get_workbench_by_id_mut(&mut self, id: u64) -> Result<&mut Workbench, CADmiumError> {
         self.workbenches
-            .get_mut(id as usize)
+            .iter_mut()
+            .find(|wb| wb.id == id)
             .ok_or(CADmiumError::WorkbenchIDNotFound(id))
     }
 
-    pub fn get_realization(&self, workbench_id: u64, max_steps: u64) -> Realization {
-        let workbench = &self.workbenches[workbench_id as usize];
-        let realization = workbench.realize(max_steps);
-        realization
+    // Gives the workbench the next ID and adds it to the end of the project
+    pub fn add_workbench(&mut self, mut workbench: Workbench) -> u64 {
+        let id = self.next_workbench_id;
+        self.next_workbench_id += 1;
+        workbench.id = id;
+        self.workbenches.push(workbench);
+        id
     }
-}
 
-#[derive(Tsify, Debug, Serialize, Deserialize)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct Assembly {
-    name: String,
+    // Copies a workbench's whole history into a new workbench, right after the original
+    pub fn duplicate_workbench(&mut self, id: u64, new_name: &str) -> Result<u64, anyhow::Error> {
+        let index = self
+            .workbenches
+            .iter()
+            .position(|wb| wb.id == id)
+            .ok_or(CADmiumError::WorkbenchIDNotFound(id))?;
+        let mut copy: Workbench =
+            serde_json::from_str(&serde_json::to_string(&self.workbenches[index])?)?;
+
+        copy.id = self.next_workbench_id;
+        copy.name = new_name.to_owned();
+        self.next_workbench_id += 1;
+        self.workbenches.insert(index + 1, copy);
+        Ok(self.workbenches[index + 1].id)
+    }
+
+    // A project always keeps at least one workbench. Deleting the active workbench
+    // makes the one before it active, or the one after if it was the first
+    pub fn delete_workbench(&mut self, id: u64) -> Result<(), CADmiumError> {
+        let index = self
+            .workbenches
+            .iter()
+            .position(|wb| wb.id == id)
+            .ok_or(CADmiumError::WorkbenchIDNotFound(id))?;
+        if self.workbenches.len() == 1 {
+            return Err(CADmiumError::LastWorkbench(id));
+        }
+
+        self.workbenches.remove(index);
+        self.remove_instances_of(&Component::Part { workbench_id: id });
+        if self.active_workbench == id {
+            self.active_workbench = self.workbenches[index.saturating_sub(1)].id;
+        }
+        Ok(())
+    }
+
+    pub fn set_active_workbench(&mut self, id: u64) -> Result<(), CADmiumError> {
+        self.get_workbench_by_id(id)?;
+        self.active_workbench = id;
+        Ok(())
+    }
+
+    // Projects saved before workbenches had IDs load with every ID at 0, so those get
+    // their position instead, which is what the IDs used to be
+    fn assign_workbench_ids(&mut self) {
+        let ids: HashSet<u64> = self.workbenches.iter().map(|wb| wb.id).collect();
+        if ids.len() < self.workbenches.len() {
+            for (index, wb) in self.workbenches.iter_mut().enumerate() {
+                wb.id = index as u64;
+            }
+        }
+
+        let highest = self.workbenches.iter().map(|wb| wb.id + 1).max();
+        self.next_workbench_id = self.next_workbench_id.max(highest.unwrap_or(0));
+    }
+
+    pub fn get_assembly_by_id(&self, id: u64) -> Result<&Assembly, CADmiumError> {
+        self.assemblies
+            .iter()
+            .find(|a| a.id == id)
+            .ok_or(CADmiumError::AssemblyIDNotFound(id))
+    }
+
+    pub fn get_assembly_by_id_mut(&mut self, id: u64) -> Result<&mut Assembly, CADmiumError> {
+        self.assemblies
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or(CADmiumError::AssemblyIDNotFound(id))
+    }
+
+    pub fn add_assembly(&mut self, name: &str) -> u64 {
+        let id = self.next_assembly_id;
+        self.next_assembly_id += 1;
+        let mut assembly = Assembly::new(name);
+        assembly.id = id;
+        self.assemblies.push(assembly);
+        id
+    }
+
+    // Instances of the assembly in other assemblies are removed along with it
+    pub fn delete_assembly(&mut self, id: u64) -> Result<(), CADmiumError> {
+        let index = self
+            .assemblies
+            .iter()
+            .position(|a| a.id == id)
+            .ok_or(CADmiumError::AssemblyIDNotFound(id))?;
+        self.assemblies.remove(index);
+        self.remove_instances_of(&Component::Assembly { assembly_id: id });
+        Ok(())
+    }
+
+    // Checks the component exists, and that placing it wouldn't put an assembly inside itself
+    pub fn add_instance(
+        &mut self,
+        assembly_id: u64,
+        name: &str,
+        component: Component,
+        transform: Transform,
+    ) -> Result<String, CADmiumError> {
+        self.get_assembly_by_id(assembly_id)?;
+        match &component {
+            Component::Part { workbench_id } => {
+                self.get_workbench_by_id(*workbench_id)?;
+            }
+            Component::Assembly { assembly_id: inner } => {
+                if self.assembly_contains(*inner, assembly_id)? {
+                    return Err(CADmiumError::AssemblyCycle(*inner));
+                }
+            }
+        }
+
+        let assembly = self.get_assembly_by_id_mut(assembly_id)?;
+        Ok(assembly.add_instance(name, component, transform))
+    }
+
+    // Whether `outer` is `target` or has it somewhere among its instances
+    fn assembly_contains(&self, outer: u64, target: u64) -> Result<bool, CADmiumError> {
+        if outer == target {
+            return Ok(true);
+        }
+        for instance in self.get_assembly_by_id(outer)?.instances.iter() {
+            if let Component::Assembly { assembly_id } = instance.component {
+                if self.assembly_contains(assembly_id, target)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn remove_instances_of(&mut self, component: &Component) {
+        for assembly in self.assemblies.iter_mut() {
+            assembly
+                .instances
+                .retain(|instance| instance.component != *component);
+            assembly.remove_dangling_mates();
+        }
+    }
+
+    // Moves the assembly's instances to where its mates put them
+    pub fn solve_mates(&mut self, assembly_id: u64) -> Result<MateSolution, CADmiumError> {
+        let solution = mates::solve(self, self.get_assembly_by_id(assembly_id)?)?;
+        let assembly = self.get_assembly_by_id_mut(assembly_id)?;
+        for instance in assembly.instances.iter_mut() {
+            if let Some(transform) = solution.transforms.get(&instance.id) {
+                instance.transform = transform.clone();
+            }
+        }
+        Ok(solution)
+    }
+
+    pub fn get_realization(
+        &self,
+        workbench_id: u64,
+        max_steps: u64,
+    ) -> Result<Realization, CADmiumError> {
+        let workbench = self.get_workbench_by_id(workbench_id)?;
+        Ok(workbench.realize(max_steps))
+    }
 }
 
 #[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
@@ -220,10 +450,11 @@ pub struct RealPlane {
 pub mod tests {
     use truck_polymesh::obj;
 
+    use crate::datum::FaceReference;
     use crate::extrusion::Direction;
     use crate::extrusion::Extrusion;
     use crate::extrusion::ExtrusionMode;
-    use crate::message::Message;
+    use crate::message::{Message, MessageResult, MessageSuccess};
     use truck_meshalgo::filters::*;
     use truck_meshalgo::tessellation::*;
 
@@ -260,10 +491,10 @@ pub mod tests {
     fn one_extrusion() {
         let p = create_test_project();
 
-        let realization = p.get_realization(0, 1000);
+        let realization = p.get_realization(0, 1000).unwrap();
         let solids = realization.solids;
 
-        let solid = &solids["Ext1:0"];
+        let solid = &solids["Extrusion-0:0"];
 
         println!("{:?}", solid);
     }
@@ -302,12 +533,43 @@ pub mod tests {
 
         let result = message.handle(&mut p);
         match result {
-            Ok(res) => println!("{}", res),
+            Ok(res) => println!("{:?}", res),
             Err(e) => println!("{}", e),
         }
         // let realization = p.get_realization(0, 1000);
     }
 
+    #[test]
+    fn sketches_only_move_onto_faces_made_before_them() {
+        let mut p = create_test_project();
+        let wb = p.workbenches.get_mut(0).unwrap();
+        let sketch_id = wb.add_sketch_to_plane("Sketch 2", "Plane-1");
+        let realized = p.get_realization(0, 1000).unwrap();
+        let face_name = realized.solids["Extrusion-0:0"].face_names[0].clone();
+
+        let move_onto = |sketch_id: &str, face_name: &str| Message::SetSketchPlane {
+            workbench_id: 0,
+            sketch_id: sketch_id.to_owned(),
+            plane_id: "".to_owned(),
+            face: Some(FaceReference {
+                solid_id: "Extrusion-0:0".to_owned(),
+                face_name: face_name.to_owned(),
+            }),
+        };
+        let face_not_found = |message: Message, p: &mut Project| {
+            let error = message.handle(p).unwrap_err();
+            matches!(
+                error.downcast_ref::<CADmiumError>(),
+                Some(CADmiumError::FaceNotFound(..))
+            )
+        };
+
+        assert!(face_not_found(move_onto(&sketch_id, "Nowhere"), &mut p));
+        // the first sketch comes before the block it makes
+        assert!(face_not_found(move_onto("Sketch-0", &face_name), &mut p));
+        assert!(move_onto(&sketch_id, &face_name).handle(&mut p).is_ok());
+    }
+
     // Removed because this seems pretty redundant with all the other tests that read .cadmium files
     // #[test]
     // fn to_and_from_json() {
@@ -328,7 +590,7 @@ pub mod tests {
 
         let p2 = Project::from_json(&file_contents);
 
-        let realization = p2.get_realization(0, 1000);
+        let realization = p2.get_realization(0, 1000).unwrap();
         println!("{:?}", realization);
     }
 
@@ -337,7 +599,8 @@ pub mod tests {
         let mut p = create_test_project();
         let wb = p.workbenches.get_mut(0).unwrap();
 
-        let s2_id = wb.add_sketch_to_solid_face("Sketch-2", "Ext1:0", Vector3::new(0.0, 0.0, 1.0));
+        let s2_id =
+            wb.add_sketch_to_solid_face("Sketch-2", "Extrusion-0:0", Vector3::new(0.0, 0.0, 1.0));
         let s2 = wb.get_sketch_mut("Sketch-2").unwrap();
 
         // smaller
@@ -363,7 +626,7 @@ pub mod tests {
             25.0,
             0.0,
             Direction::Normal,
-            ExtrusionMode::Add(vec!["Ext1:0".to_string()]),
+            ExtrusionMode::Add(vec!["Extrusion-0:0".to_string()]),
         );
         wb.add_extrusion("Ext2", extrusion2);
 
@@ -378,18 +641,18 @@ pub mod tests {
             50.0,
             0.0,
             Direction::NegativeNormal,
-            ExtrusionMode::Remove(vec!["Ext1:0".to_string()]),
+            ExtrusionMode::Remove(vec!["Extrusion-0:0".to_string()]),
         );
         wb.add_extrusion("Ext3", extrusion3);
 
-        let realization = p.get_realization(0, 1000);
+        let realization = p.get_realization(0, 1000).unwrap();
         let solids = realization.solids;
 
         let num_solids = solids.len();
         println!("Num Solids: {:?}", num_solids);
         assert!(num_solids == 1);
 
-        let final_solid = &solids["Ext1:0"];
+        let final_solid = &solids["Extrusion-0:0"];
         println!("Final solid: {:?}", final_solid.truck_solid);
         let mut mesh = final_solid.truck_solid.triangulation(0.02).to_polygon();
         mesh.put_together_same_attrs(0.1);
@@ -405,7 +668,8 @@ pub mod tests {
         let mut p = create_test_project();
         let wb = p.workbenches.get_mut(0).unwrap();
 
-        let s2_id = wb.add_sketch_to_solid_face("Sketch-2", "Ext1:0", Vector3::new(0.0, 0.0, 1.0));
+        let s2_id =
+            wb.add_sketch_to_solid_face("Sketch-2", "Extrusion-0:0", Vector3::new(0.0, 0.0, 1.0));
         let s2 = wb.get_sketch_mut("Sketch-2").unwrap();
 
         // smaller
@@ -426,18 +690,18 @@ pub mod tests {
             25.0,
             0.0,
             Direction::Normal,
-            ExtrusionMode::Add(vec!["Ext1:0".to_string()]),
+            ExtrusionMode::Add(vec!["Extrusion-0:0".to_string()]),
         );
         wb.add_extrusion("Ext2", extrusion2);
 
-        let realization = p.get_realization(0, 1000);
+        let realization = p.get_realization(0, 1000).unwrap();
         let solids = realization.solids;
 
         let num_solids = solids.len();
         println!("Num Solids: {:?}", num_solids);
         assert!(num_solids == 1);
 
-        let final_solid = &solids["Ext1:0"];
+        let final_solid = &solids["Extrusion-0:0"];
         let mut mesh = final_solid.truck_solid.triangulation(0.02).to_polygon();
         mesh.put_together_same_attrs(0.1);
         let file = std::fs::File::create("secondary_extrusion.obj").unwrap();
@@ -446,4 +710,212 @@ pub mod tests {
         let file = std::fs::File::create("secondary_extrusion.json").unwrap();
         serde_json::to_writer(file, &p).unwrap();
     }
+
+    #[test]
+    fn undo_and_redo_messages() {
+        let mut p = Project::new("Test Project");
+        let point = |x: f64| Message::NewPointOnSketch2 {
+            workbench_id: 0,
+            sketch_id: "Sketch-0".to_owned(),
+            x,
+            y: 0.0,
+            hidden: false,
+        };
+        let point_count = |p: &mut Project| {
+            p.workbenches[0]
+                .get_sketch_by_id_mut("Sketch-0")
+                .unwrap()
+                .points
+                .len()
+        };
+
+        Message::NewSketchOnPlane {
+            workbench_id: 0,
+            sketch_name: "Sketch 1".to_owned(),
+            plane_id: "Plane-0".to_owned(),
+        }
+        .handle(&mut p)
+        .unwrap();
+        point(0.0).handle(&mut p).unwrap();
+
+        // these two points go in and come out together
+        Message::BeginUndoGroup.handle(&mut p).unwrap();
+        point(1.0).handle(&mut p).unwrap();
+        point(2.0).handle(&mut p).unwrap();
+        Message::EndUndoGroup.handle(&mut p).unwrap();
+        assert_eq!(point_count(&mut p), 3);
+
+        Message::Undo.handle(&mut p).unwrap();
+        assert_eq!(point_count(&mut p), 1);
+        Message::Undo.handle(&mut p).unwrap();
+        assert_eq!(point_count(&mut p), 0);
+        Message::Undo.handle(&mut p).unwrap();
+        assert_eq!(p.workbenches[0].history.len(), 4);
+        assert!(Message::Undo.handle(&mut p).is_err());
+
+        Message::Redo.handle(&mut p).unwrap();
+        Message::Redo.handle(&mut p).unwrap();
+        assert_eq!(point_count(&mut p), 1);
+
+        // a new change clears whatever could have been redone
+        point(5.0).handle(&mut p).unwrap();
+        assert!(Message::Redo.handle(&mut p).is_err());
+    }
+
+    #[test]
+    fn solver_steps_undo_together_and_switching_workbenches_not_at_all() {
+        let mut p = create_test_project();
+        let step = || Message::StepSketch {
+            workbench_id: 0,
+            sketch_name: "Sketch 1".to_owned(),
+            steps: 1,
+        };
+        for _ in 0..3 {
+            step().handle(&mut p).unwrap();
+        }
+        Message::SetActiveWorkbench { workbench_id: 0 }
+            .handle(&mut p)
+            .unwrap();
+
+        Message::Undo.handle(&mut p).unwrap();
+        assert!(Message::Undo.handle(&mut p).is_err());
+    }
+
+    #[test]
+    fn batches_apply_all_or_nothing() {
+        let mut p = Project::new("Test Project");
+        let batch: Message = serde_json::from_str(
+            r#"{ "Batch": { "messages": [
+                { "NewSketchOnPlane": { "workbench_id": 0, "sketch_name": "Sketch 1", "plane_id": "Plane-0" } },
+                { "NewPointOnSketch2": { "workbench_id": 0, "sketch_id": { "$ref": 0, "field": "id" }, "x": 0.0, "y": 0.0, "hidden": false } },
+                { "NewPointOnSketch2": { "workbench_id": 0, "sketch_id": { "$ref": 0, "field": "id" }, "x": 1.0, "y": 0.0, "hidden": false } },
+                { "NewLineOnSketch": { "workbench_id": 0, "sketch_id": { "$ref": 0, "field": "id" }, "start_point_id": { "$ref": 1, "field": "id" }, "end_point_id": { "$ref": 2, "field": "id" } } }
+            ] } }"#,
+        )
+        .unwrap();
+
+        let MessageSuccess::Batch { results } = batch.handle(&mut p).unwrap() else {
+            panic!("a batch should report a result for each message");
+        };
+        assert_eq!(
+            results[0],
+            MessageSuccess::StepCreated {
+                id: "Sketch-0".to_owned()
+            }
+        );
+        let sketch = p.workbenches[0].get_sketch_by_id_mut("Sketch-0").unwrap();
+        assert_eq!(sketch.points.len(), 2);
+        assert_eq!(sketch.line_segments.len(), 1);
+
+        // the whole batch is one change to undo
+        Message::Undo.handle(&mut p).unwrap();
+        assert_eq!(p.workbenches[0].history.len(), 4);
+
+        // the second message refers to a result that doesn't exist, so the first is put back
+        let batch: Message = serde_json::from_str(
+            r#"{ "Batch": { "messages": [
+                { "NewSketchOnPlane": { "workbench_id": 0, "sketch_name": "Sketch 1", "plane_id": "Plane-0" } },
+                { "NewPointOnSketch2": { "workbench_id": 0, "sketch_id": { "$ref": 5, "field": "id" }, "x": 0.0, "y": 0.0, "hidden": false } }
+            ] } }"#,
+        )
+        .unwrap();
+        assert!(batch.handle(&mut p).is_err());
+        assert_eq!(p.workbenches[0].history.len(), 4);
+
+        // undo groups can't be opened inside a batch
+        let batch: Message = serde_json::from_str(
+            r#"{ "Batch": { "messages": [
+                { "NewSketchOnPlane": { "workbench_id": 0, "sketch_name": "Sketch 1", "plane_id": "Plane-0" } },
+                "BeginUndoGroup"
+            ] } }"#,
+        )
+        .unwrap();
+        assert!(batch.handle(&mut p).is_err());
+        assert_eq!(p.workbenches[0].history.len(), 4);
+
+        // strings that look like references are left alone
+        let batch: Message = serde_json::from_str(
+            r#"{ "Batch": { "messages": [
+                { "NewSketchOnPlane": { "workbench_id": 0, "sketch_name": "$0.id", "plane_id": "Plane-0" } }
+            ] } }"#,
+        )
+        .unwrap();
+        batch.handle(&mut p).unwrap();
+        assert_eq!(p.workbenches[0].history[4].name, "$0.id");
+    }
+
+    #[test]
+    fn errors_say_what_went_wrong_and_where() {
+        let mut p = Project::new("Test Project");
+        let message = Message::RenameStep {
+            workbench_id: 0,
+            step_id: 10,
+            new_name: "Nowhere".to_owned(),
+        };
+
+        let MessageResult::Error(error) = message.handle(&mut p).into() else {
+            panic!("renaming a step that doesn't exist should fail");
+        };
+        assert_eq!(error.kind, "StepIDNotFound");
+        assert_eq!(error.ids, vec!["10"]);
+    }
+
+    #[test]
+    fn workbench_ids_survive_deleting_other_workbenches() {
+        let mut p = Project::new("Test Project");
+        let created = |result: Result<MessageSuccess, anyhow::Error>| match result.unwrap() {
+            MessageSuccess::WorkbenchCreated { id } => id,
+            other => panic!("expected a new workbench, got {:?}", other),
+        };
+
+        let second = created(
+            Message::NewWorkbench {
+                name: "Second".to_owned(),
+            }
+            .handle(&mut p),
+        );
+        let copy = created(
+            Message::DuplicateWorkbench {
+                workbench_id: 0,
+                new_name: "Copy".to_owned(),
+            }
+            .handle(&mut p),
+        );
+        assert_eq!((second, copy), (1, 2));
+
+        // the copy goes right after its original, with the same history
+        let names: Vec<&str> = p.workbenches.iter().map(|wb| wb.name.as_str()).collect();
+        assert_eq!(names, vec!["Workbench 1", "Copy", "Second"]);
+        assert_eq!(
+            p.workbenches[1].history.len(),
+            p.workbenches[0].history.len()
+        );
+
+        Message::SetActiveWorkbench { workbench_id: 0 }
+            .handle(&mut p)
+            .unwrap();
+        Message::DeleteWorkbench { workbench_id: 0 }
+            .handle(&mut p)
+            .unwrap();
+        assert_eq!(p.active_workbench, 2);
+        assert_eq!(p.get_workbench_by_id_mut(1).unwrap().name, "Second");
+        assert!(p.get_workbench_by_id_mut(0).is_err());
+
+        // IDs aren't reused, and the last workbench can't go
+        let third = created(
+            Message::NewWorkbench {
+                name: "Third".to_owned(),
+            }
+            .handle(&mut p),
+        );
+        assert_eq!(third, 3);
+        for id in [1, 2] {
+            Message::DeleteWorkbench { workbench_id: id }
+                .handle(&mut p)
+                .unwrap();
+        }
+        assert!(Message::DeleteWorkbench { workbench_id: 3 }
+            .handle(&mut p)
+            .is_err());
+    }
 }
@@ -2,20 +2,279 @@ use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
-use crate::archetypes::Point3;
+use truck_meshalgo::tessellation::{MeshableShape, MeshedShape};
+
+use crate::archetypes::{Point3, Vector3};
+use crate::datum::RealAxis;
+use crate::error::CADmiumError;
+use crate::export;
+use crate::hole::RealHole;
+use crate::interference::{self, InterferenceReport};
+use crate::mass::{MassIntegrals, MassProperties};
+use crate::measure::{self, EntityReference, Measurement};
+use crate::picking::{self, PickResult};
 use crate::project::{RealPlane, RealSketch};
 use crate::solid::Solid;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
-#[derive(Tsify, Debug, Serialize, Deserialize)]
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Realization {
     // a Realization is what you get if you apply the steps in a Workbench's
     // history and build a bunch of geometry
     pub planes: HashMap<String, RealPlane>,
     pub points: HashMap<String, Point3>,
+    pub axes: HashMap<String, RealAxis>,
     pub sketches: HashMap<String, (RealSketch, RealSketch, String)>,
     pub solids: HashMap<String, Solid>,
+    pub holes: HashMap<String, RealHole>,
+    // how each step went, in history order
+    #[serde(default)]
+    pub statuses: Vec<StepStatus>,
+}
+
+// What realizing a history produced at each of its steps, keyed by a hash of that step
+// and every step before it, so an edit only redoes the steps from the first changed one on
+#[derive(Debug, Clone, Default)]
+pub struct RealizationCache {
+    pub(crate) entries: Vec<CachedStep>,
+    // how many steps have been realized to fill the cache, over its whole life
+    pub(crate) steps_realized: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CachedStep {
+    pub(crate) key: u64,
+    // only what this step changed, so the cache grows with the model rather than with the
+    // model's size times the number of steps
+    pub(crate) changes: StepChanges,
+    pub(crate) status: StepStatus,
+    // whether the step was left out, which the steps after it need to know about
+    pub(crate) skipped: bool,
+}
+
+impl RealizationCache {
+    // The realization after the first count cached steps, and the steps left out of it
+    pub(crate) fn replay(&self, count: usize) -> (Realization, HashSet<String>) {
+        let mut realized = Realization::new();
+        let mut skipped = HashSet::new();
+        for entry in self.entries[..count].iter() {
+            entry.changes.apply_to(&mut realized);
+            realized.statuses.push(entry.status.clone());
+            if entry.skipped {
+                skipped.insert(entry.status.step_id.clone());
+            }
+        }
+        (realized, skipped)
+    }
+
+    // The solids after the first count cached steps, without copying them
+    pub(crate) fn solids(&self, count: usize) -> HashMap<&str, &Solid> {
+        let mut solids = HashMap::new();
+        for entry in self.entries[..count].iter() {
+            for solid_id in entry.changes.solids.removed.iter() {
+                solids.remove(solid_id.as_str());
+            }
+            for (solid_id, solid) in entry.changes.solids.changed.iter() {
+                solids.insert(solid_id.as_str(), solid);
+            }
+        }
+        solids
+    }
+}
+
+// The entries of one kind that a step added or replaced, and the keys of those it removed
+#[derive(Debug, Clone)]
+pub(crate) struct EntryChanges<T> {
+    pub(crate) changed: HashMap<String, T>,
+    pub(crate) removed: Vec<String>,
+}
+
+impl<T: Clone> EntryChanges<T> {
+    fn between(
+        before: &HashMap<String, u64>,
+        after: &HashMap<String, T>,
+        version: fn(&T) -> u64,
+    ) -> Self {
+        EntryChanges {
+            changed: after
+                .iter()
+                .filter(|(key, value)| before.get(*key) != Some(&version(value)))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            removed: before
+                .keys()
+                .filter(|key| !after.contains_key(*key))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn apply_to(&self, entries: &mut HashMap<String, T>) {
+        for key in self.removed.iter() {
+            entries.remove(key);
+        }
+        for (key, value) in self.changed.iter() {
+            entries.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn versions<T>(entries: &HashMap<String, T>, version: fn(&T) -> u64) -> HashMap<String, u64> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.clone(), version(value)))
+        .collect()
+}
+
+// Steps only ever add planes, points, axes, sketches and holes, under IDs of their own
+fn never_replaced<T>(_: &T) -> u64 {
+    0
+}
+
+// Extrusions and holes replace the solids they merge with or cut
+fn solid_version(solid: &Solid) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    solid.crc32.hash(&mut hasher);
+    solid.face_names.hash(&mut hasher);
+    hasher.finish()
+}
+
+// A hash of the value's JSON that doesn't depend on the order its maps happen to list
+// their entries in, so a copy of a step restored by undo or loaded from a file hashes
+// the same as the original
+pub fn canonical_hash<T: Serialize>(value: &T) -> u64 {
+    fn hash_value(value: &serde_json::Value, hasher: &mut DefaultHasher) {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<(&String, &serde_json::Value)> = map.iter().collect();
+                entries.sort_by(|(x, _), (y, _)| x.cmp(y));
+                '{'.hash(hasher);
+                for (key, value) in entries {
+                    key.hash(hasher);
+                    hash_value(value, hasher);
+                }
+                '}'.hash(hasher);
+            }
+            serde_json::Value::Array(items) => {
+                '['.hash(hasher);
+                for item in items {
+                    hash_value(item, hasher);
+                }
+                ']'.hash(hasher);
+            }
+            other => other.to_string().hash(hasher),
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    if let Ok(value) = serde_json::to_value(value) {
+        hash_value(&value, &mut hasher);
+    }
+    hasher.finish()
+}
+
+// Enough about a realization to tell afterwards what a step changed in it
+pub(crate) struct RealizationVersions {
+    planes: HashMap<String, u64>,
+    points: HashMap<String, u64>,
+    axes: HashMap<String, u64>,
+    sketches: HashMap<String, u64>,
+    solids: HashMap<String, u64>,
+    holes: HashMap<String, u64>,
+}
+
+impl RealizationVersions {
+    pub(crate) fn of(realized: &Realization) -> Self {
+        RealizationVersions {
+            planes: versions(&realized.planes, never_replaced),
+            points: versions(&realized.points, never_replaced),
+            axes: versions(&realized.axes, never_replaced),
+            sketches: versions(&realized.sketches, never_replaced),
+            solids: versions(&realized.solids, solid_version),
+            holes: versions(&realized.holes, never_replaced),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StepChanges {
+    pub(crate) planes: EntryChanges<RealPlane>,
+    pub(crate) points: EntryChanges<Point3>,
+    pub(crate) axes: EntryChanges<RealAxis>,
+    pub(crate) sketches: EntryChanges<(RealSketch, RealSketch, String)>,
+    pub(crate) solids: EntryChanges<Solid>,
+    pub(crate) holes: EntryChanges<RealHole>,
+}
+
+impl StepChanges {
+    pub(crate) fn between(before: &RealizationVersions, after: &Realization) -> Self {
+        StepChanges {
+            planes: EntryChanges::between(&before.planes, &after.planes, never_replaced),
+            points: EntryChanges::between(&before.points, &after.points, never_replaced),
+            axes: EntryChanges::between(&before.axes, &after.axes, never_replaced),
+            sketches: EntryChanges::between(&before.sketches, &after.sketches, never_replaced),
+            solids: EntryChanges::between(&before.solids, &after.solids, solid_version),
+            holes: EntryChanges::between(&before.holes, &after.holes, never_replaced),
+        }
+    }
+
+    fn apply_to(&self, realized: &mut Realization) {
+        self.planes.apply_to(&mut realized.planes);
+        self.points.apply_to(&mut realized.points);
+        self.axes.apply_to(&mut realized.axes);
+        self.sketches.apply_to(&mut realized.sketches);
+        self.solids.apply_to(&mut realized.solids);
+        self.holes.apply_to(&mut realized.holes);
+    }
+}
+
+#[derive(Tsify, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum StepState {
+    Ok,
+    // the step produced something, but not everything it should have
+    Warning,
+    Error,
+    Suppressed,
+    // the step builds on something that was suppressed or failed
+    Skipped,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct StepIssue {
+    // the name of the CADmiumError variant, like "SolidNotFound"
+    pub error: String,
+    pub message: String,
+}
+
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct StepStatus {
+    pub step_id: String,
+    pub state: StepState,
+    pub issues: Vec<StepIssue>,
+}
+
+impl StepStatus {
+    pub fn new(step_id: &str, state: StepState, errors: &[CADmiumError]) -> Self {
+        let issues = errors
+            .iter()
+            .map(|e| StepIssue {
+                error: <&'static str>::from(e).to_owned(),
+                message: e.to_string(),
+            })
+            .collect();
+
+        StepStatus {
+            step_id: step_id.to_owned(),
+            state,
+            issues,
+        }
+    }
 }
 
 impl Realization {
@@ -23,11 +282,20 @@ impl Realization {
         Realization {
             planes: HashMap::new(),
             points: HashMap::new(),
+            axes: HashMap::new(),
             sketches: HashMap::new(),
             solids: HashMap::new(),
+            holes: HashMap::new(),
+            statuses: vec![],
         }
     }
 
+    pub fn status(&self, step_id: &str) -> Option<&StepStatus> {
+        self.statuses
+            .iter()
+            .find(|status| status.step_id == step_id)
+    }
+
     pub fn solid_to_obj(&self, solid_name: &str, tolerance: f64) -> String {
         let solid = &self.solids[solid_name];
         let obj_text = solid.to_obj_string(tolerance);
@@ -39,6 +307,78 @@ impl Realization {
         solid.save_as_obj(filename, tolerance);
     }
 
+    // The solids in ID order
+    fn bodies(&self) -> Vec<(String, &Solid)> {
+        let mut solid_ids: Vec<&String> = self.solids.keys().collect();
+        solid_ids.sort();
+        solid_ids
+            .into_iter()
+            .map(|id| (id.clone(), &self.solids[id]))
+            .collect()
+    }
+
+    // Which of the solids overlap, and how far apart the others are
+    pub fn interference(&self, tolerance: f64) -> InterferenceReport {
+        interference::check(&self.bodies(), tolerance)
+    }
+
+    pub fn to_glb(&self) -> Vec<u8> {
+        export::to_glb(&self.bodies())
+    }
+
+    pub fn to_3mf(&self) -> Vec<u8> {
+        export::to_3mf(&self.bodies())
+    }
+
+    // Without a density, the solid's material says how heavy it is
+    pub fn solid_mass_properties(
+        &self,
+        solid_id: &str,
+        density: Option<f64>,
+        tolerance: f64,
+    ) -> Result<MassProperties, CADmiumError> {
+        let solid = self
+            .solids
+            .get(solid_id)
+            .ok_or(CADmiumError::SolidNotFound(solid_id.to_owned()))?;
+        Ok(solid.mass_properties(density.unwrap_or(solid.material.density), tolerance))
+    }
+
+    // All of the solids taken together, as if they were one part
+    pub fn mass_properties(&self, density: Option<f64>, tolerance: f64) -> MassProperties {
+        let mut integrals = MassIntegrals::default();
+        for solid in self.solids.values() {
+            integrals.add_mesh(
+                &solid.truck_solid.triangulation(tolerance).to_polygon(),
+                density.unwrap_or(solid.material.density),
+            );
+        }
+        integrals.properties()
+    }
+
+    // Measures one entity, or the distance and angle between two
+    pub fn measure(
+        &self,
+        a: &EntityReference,
+        b: Option<&EntityReference>,
+    ) -> Result<Measurement, CADmiumError> {
+        match b {
+            None => measure::measure_one(self, a),
+            Some(b) => measure::measure_between(self, a, b),
+        }
+    }
+
+    // What a ray from the camera hits, see picking.rs
+    pub fn pick(
+        &self,
+        origin: &Point3,
+        direction: &Vector3,
+        tolerance: f64,
+        spread: f64,
+    ) -> PickResult {
+        picking::pick(self, origin, direction, tolerance, spread)
+    }
+
     pub fn solid_to_step(&self, solid_name: &str) -> String {
         let solid = &self.solids[solid_name];
         let step_text = solid.to_step_string();
@@ -1314,7 +1314,7 @@ mod tests {
         let p: Project = serde_json::from_str(&contents).unwrap();
         // println!("{:?}", p);
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
         println!("Sketch: {:?}", sketch_split);
         println!("Faces: {:?}", sketch_split.faces);
@@ -1330,7 +1330,7 @@ mod tests {
         let p: Project = serde_json::from_str(&contents).unwrap();
         // println!("{:?}", p);
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
         // println!("Sketch: {:?}", sketch_split);
         // println!("Faces: {:?}", sketch_split.faces);
@@ -1347,7 +1347,7 @@ mod tests {
         .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1363,7 +1363,7 @@ mod tests {
         .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1378,7 +1378,7 @@ mod tests {
                 .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1406,7 +1406,7 @@ mod tests {
         .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1422,7 +1422,7 @@ mod tests {
         .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1438,7 +1438,7 @@ mod tests {
         .unwrap();
         let p: Project = serde_json::from_str(&contents).unwrap();
 
-        let realized = p.get_realization(0, 1000);
+        let realized = p.get_realization(0, 1000).unwrap();
         let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
         println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1693,7 +1693,7 @@ mod tests {
             sketch.add_segment(g, h);
             sketch.add_segment(h, e);
 
-            let realized = p.get_realization(0, 1000);
+            let realized = p.get_realization(0, 1000).unwrap();
             let (sketch_unsplit, sketch_split, _) = realized.sketches.get("Sketch-0").unwrap();
 
             println!("Number of faces: {:?}", sketch_split.faces.len());
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f64::consts::PI;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 
@@ -8,14 +9,21 @@ use truck_meshalgo::prelude::OptimizingFilter;
 use truck_meshalgo::tessellation::MeshableShape;
 use truck_meshalgo::tessellation::MeshedShape;
 use truck_polymesh::obj;
+use truck_polymesh::Invertible;
+use truck_polymesh::PolygonMesh;
+use truck_polymesh::PolylineCurve;
 use truck_polymesh::Rad;
 use truck_stepio::out;
 
-use crate::archetypes::Vector3;
+use crate::archetypes::{Plane, Point3, Vector3};
+use crate::error::CADmiumError;
 use crate::extrusion::find_transit;
 use crate::extrusion::merge_faces;
 use crate::extrusion::Direction;
 use crate::extrusion::Extrusion;
+use crate::mass::{MassIntegrals, MassProperties};
+use crate::material::Material;
+use crate::naming::{name_extrusion_faces, unique_names};
 use crate::project::{RealPlane, RealSketch};
 use crate::sketch::Vector2;
 use crate::sketch::{Face, Ring, Segment};
@@ -26,6 +34,24 @@ use truck_polymesh::Point3 as TruckPoint3;
 use truck_polymesh::Vector3 as TruckVector3;
 use truck_topology::Solid as TruckSolid;
 
+// The plane a planar face lies in, with its normal pointing out of the solid. The
+// in-plane axes come from the face's own surface, so a face that was extruded from
+// a sketch keeps that sketch's orientation. Returns None for curved faces
+pub fn face_plane(face: &TruckFace) -> Option<Plane> {
+    match face.surface() {
+        truck_modeling::geometry::Surface::Plane(p) => {
+            let mut plane = Plane::from_truck(p);
+            if !face.orientation() {
+                // flip the normal but keep the primary axis, so the plane stays right-handed
+                plane.secondary = plane.secondary.times(-1.0);
+                plane.tertiary = plane.tertiary.times(-1.0);
+            }
+            Some(plane)
+        }
+        _ => None,
+    }
+}
+
 #[derive(Tsify, Debug, Serialize, Deserialize, Clone)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Solid {
@@ -36,6 +62,18 @@ pub struct Solid {
     pub uvs: Vec<Vector2>,
     pub indices: Vec<usize>,
     pub triangles: Vec<Vec<u64>>,
+    // persistent names for each face, in face_meshes order. See naming.rs
+    #[serde(default)]
+    pub face_names: Vec<String>,
+    // for each triangle in indices, the face (in face_meshes order) it is part of
+    #[serde(default)]
+    pub triangle_faces: Vec<u64>,
+    // every edge, tessellated for drawing. edge_id is the edge's place in edge_names
+    #[serde(default)]
+    pub edges: Vec<EdgePolyline>,
+    // set by the workbench when it is realized
+    #[serde(default)]
+    pub material: Material,
     pub truck_solid: TruckSolid<
         truck_polymesh::cgmath::Point3<f64>,
         truck_modeling::Curve,
@@ -43,6 +81,93 @@ pub struct Solid {
     >,
 }
 
+// A tessellated B-rep face. face_id is the face's position in the solid's boundary
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct FaceMesh {
+    pub face_id: u64,
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vector3>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl FaceMesh {
+    pub fn triangle_points(&self) -> impl Iterator<Item = (&Point3, &Point3, &Point3)> {
+        self.triangles.iter().map(|[a, b, c]| {
+            (
+                &self.positions[*a],
+                &self.positions[*b],
+                &self.positions[*c],
+            )
+        })
+    }
+
+    pub fn area(&self) -> f64 {
+        self.triangle_points()
+            .map(|(a, b, c)| b.minus(a).cross(&c.minus(a)).length() / 2.0)
+            .sum()
+    }
+}
+
+// The volume a closed, outward-facing mesh encloses, as a sum of signed tetrahedra
+// from the origin to each triangle
+pub fn mesh_volume(mesh: &PolygonMesh) -> f64 {
+    let volume: f64 = mesh
+        .tri_faces()
+        .iter()
+        .map(|[a, b, c]| {
+            let (a, b, c) = (
+                mesh.positions()[a.pos],
+                mesh.positions()[b.pos],
+                mesh.positions()[c.pos],
+            );
+            a.x * (b.y * c.z - b.z * c.y) - a.y * (b.x * c.z - b.z * c.x)
+                + a.z * (b.x * c.y - b.y * c.x)
+        })
+        .sum();
+    volume.abs() / 6.0
+}
+
+// A tessellated B-rep edge. edge_id is the order in which the edge is first
+// encountered when walking the boundaries of every face
+#[derive(Tsify, Debug, Clone, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct EdgePolyline {
+    pub edge_id: u64,
+    pub points: Vec<Point3>,
+}
+
+// The polyline along every edge of a solid that has already been meshed, each edge once
+fn meshed_edge_polylines(
+    meshed: &TruckSolid<TruckPoint3, PolylineCurve<TruckPoint3>, Option<PolygonMesh>>,
+) -> Vec<EdgePolyline> {
+    let mut seen = HashSet::new();
+    let mut edge_polylines = vec![];
+
+    for shell in meshed.boundaries().iter() {
+        for face in shell.face_iter() {
+            for wire in face.boundaries().iter() {
+                for edge in wire.edge_iter() {
+                    if !seen.insert(edge.id()) {
+                        continue;
+                    }
+                    let points = edge
+                        .curve()
+                        .iter()
+                        .map(|p| Point3::new(p.x, p.y, p.z))
+                        .collect();
+                    edge_polylines.push(EdgePolyline {
+                        edge_id: edge_polylines.len() as u64,
+                        points,
+                    });
+                }
+            }
+        }
+    }
+
+    edge_polylines
+}
+
 impl Solid {
     pub fn from_truck_solid(
         name: String,
@@ -60,35 +185,60 @@ impl Solid {
             triangles: vec![],
             uvs: vec![],
             indices: vec![],
+            face_names: vec![],
+            triangle_faces: vec![],
+            edges: vec![],
+            material: Material::default(),
             truck_solid,
         };
-        let mesh = solid.truck_solid.triangulation(0.01).to_polygon();
+        solid.face_names = (0..solid.faces().len())
+            .map(|face_index| format!("{}/Face{}", solid.name, face_index))
+            .collect();
+        let meshed = solid.truck_solid.triangulation(0.01);
 
         // the mesh is prepared for obj export, but we need to convert it
         // to a format compatible for rendering
-        // We have to brute force this. Go through every single triangle
-        // and emit three positions, three normals, and three uvs.
+        // We have to brute force this. Go through every triangle of every face
+        // and emit three positions, three normals, and three uvs, remembering
+        // which face the triangle belongs to so it can be picked out later.
         let mut index = 0 as usize;
-        for face in mesh.tri_faces() {
-            for v in face.iter() {
-                let vertex_index = v.pos;
-                let normal_index = v.nor.unwrap();
-                let uv_index = v.uv.unwrap();
-                let vertex = mesh.positions()[vertex_index];
-                let normal = mesh.normals()[normal_index];
-                let uv = mesh.uv_coords()[uv_index];
-
-                let pt = Vector3::new(vertex.x, vertex.y, vertex.z);
-                solid.vertices.push(pt);
-                solid
-                    .normals
-                    .push(Vector3::new(normal.x, normal.y, normal.z));
-                solid.uvs.push(Vector2::new(uv.x, uv.y));
-                solid.indices.push(index);
-
-                index += 1;
+        for (face_id, face) in meshed
+            .boundaries()
+            .iter()
+            .flat_map(|shell| shell.face_iter())
+            .enumerate()
+        {
+            let Some(mut mesh) = face.surface() else {
+                continue;
+            };
+            if !face.orientation() {
+                mesh.invert();
+            }
+
+            for tri in mesh.faces().triangle_iter() {
+                for v in tri.iter() {
+                    let vertex = mesh.positions()[v.pos];
+                    let normal = v.nor.map_or(Vector3::new(0.0, 0.0, 0.0), |nor| {
+                        let n = mesh.normals()[nor];
+                        Vector3::new(n.x, n.y, n.z)
+                    });
+                    let uv = v.uv.map_or(Vector2::new(0.0, 0.0), |uv| {
+                        let uv = mesh.uv_coords()[uv];
+                        Vector2::new(uv.x, uv.y)
+                    });
+
+                    let pt = Vector3::new(vertex.x, vertex.y, vertex.z);
+                    solid.vertices.push(pt);
+                    solid.normals.push(normal);
+                    solid.uvs.push(uv);
+                    solid.indices.push(index);
+
+                    index += 1;
+                }
+                solid.triangle_faces.push(face_id as u64);
             }
         }
+        solid.edges = meshed_edge_polylines(&meshed);
 
         // compute the crc32 of the vertices
         let mut hasher = crc32fast::Hasher::new();
@@ -102,6 +252,158 @@ impl Solid {
         solid
     }
 
+    pub fn face_meshes(&self, tolerance: f64) -> Vec<FaceMesh> {
+        let meshed = self.truck_solid.triangulation(tolerance);
+        let mut face_meshes = vec![];
+
+        for (face_id, face) in meshed
+            .boundaries()
+            .iter()
+            .flat_map(|shell| shell.face_iter())
+            .enumerate()
+        {
+            let mut face_mesh = FaceMesh {
+                face_id: face_id as u64,
+                positions: vec![],
+                normals: vec![],
+                triangles: vec![],
+            };
+
+            if let Some(mut mesh) = face.surface() {
+                if !face.orientation() {
+                    mesh.invert();
+                }
+                face_mesh.positions = mesh
+                    .positions()
+                    .iter()
+                    .map(|p| Point3::new(p.x, p.y, p.z))
+                    .collect();
+                face_mesh.normals = vec![Vector3::new(0.0, 0.0, 0.0); mesh.positions().len()];
+                for tri in mesh.faces().triangle_iter() {
+                    for v in tri.iter() {
+                        if let Some(nor) = v.nor {
+                            let n = mesh.normals()[nor];
+                            face_mesh.normals[v.pos] = Vector3::new(n.x, n.y, n.z);
+                        }
+                    }
+                    face_mesh
+                        .triangles
+                        .push([tri[0].pos, tri[1].pos, tri[2].pos]);
+                }
+            }
+
+            face_meshes.push(face_mesh);
+        }
+
+        face_meshes
+    }
+
+    pub fn edge_polylines(&self, tolerance: f64) -> Vec<EdgePolyline> {
+        meshed_edge_polylines(&self.truck_solid.triangulation(tolerance))
+    }
+
+    pub fn vertex_points(&self) -> Vec<Point3> {
+        let mut seen = HashSet::new();
+        let mut points = vec![];
+
+        for shell in self.truck_solid.boundaries().iter() {
+            for face in shell.face_iter() {
+                for wire in face.boundaries().iter() {
+                    for vertex in wire.vertex_iter() {
+                        if seen.insert(vertex.id()) {
+                            let p = vertex.point();
+                            points.push(Point3::new(p.x, p.y, p.z));
+                        }
+                    }
+                }
+            }
+        }
+
+        points
+    }
+
+    // Faces are numbered in the same order as face_meshes, across all shells
+    pub fn faces(&self) -> Vec<TruckFace> {
+        self.truck_solid
+            .boundaries()
+            .iter()
+            .flat_map(|shell| shell.face_iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_face(&self, face_id: u64) -> Option<TruckFace> {
+        self.faces().into_iter().nth(face_id as usize)
+    }
+
+    pub fn face_name(&self, face_id: u64) -> String {
+        self.face_names
+            .get(face_id as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("{}/Face{}", self.name, face_id))
+    }
+
+    pub fn face_index(&self, face_name: &str) -> Option<u64> {
+        self.face_names
+            .iter()
+            .position(|name| name == face_name)
+            .map(|index| index as u64)
+    }
+
+    // Each edge is named after the faces on either side of it, in edge_polylines order
+    pub fn edge_names(&self) -> Vec<String> {
+        let mut order = vec![];
+        let mut adjacent_faces = HashMap::new();
+        for (face_index, face) in self.faces().iter().enumerate() {
+            for wire in face.boundaries().iter() {
+                for edge in wire.edge_iter() {
+                    adjacent_faces
+                        .entry(edge.id())
+                        .or_insert_with(|| {
+                            order.push(edge.id());
+                            vec![]
+                        })
+                        .push(self.face_name(face_index as u64));
+                }
+            }
+        }
+
+        unique_names(
+            order
+                .iter()
+                .map(|edge_id| adjacent_faces[edge_id].iter().sorted().dedup().join("|"))
+                .collect(),
+        )
+    }
+
+    // Each vertex is named after the faces that meet there, in vertex_points order
+    pub fn vertex_names(&self) -> Vec<String> {
+        let mut order = vec![];
+        let mut adjacent_faces = HashMap::new();
+        for (face_index, face) in self.faces().iter().enumerate() {
+            for wire in face.boundaries().iter() {
+                for vertex in wire.vertex_iter() {
+                    adjacent_faces
+                        .entry(vertex.id())
+                        .or_insert_with(|| {
+                            order.push(vertex.id());
+                            vec![]
+                        })
+                        .push(self.face_name(face_index as u64));
+                }
+            }
+        }
+
+        unique_names(
+            order
+                .iter()
+                .map(|vertex_id| adjacent_faces[vertex_id].iter().sorted().dedup().join("|"))
+                .collect(),
+        )
+    }
+
+    // Returns None if no face, or more than one face, has this normal. A stepped
+    // block has several faces pointing the same way, so prefer get_face where possible
     pub fn get_face_by_normal(&self, normal: &Vector3) -> Option<TruckFace> {
         let truck_solid = &self.truck_solid;
         let boundaries = &truck_solid.boundaries()[0];
@@ -127,18 +429,20 @@ impl Solid {
         });
 
         match candidate_faces.len() {
-            0 => None,
             1 => Some(candidate_faces[0].clone()),
-            _ => panic!("More than one face with the same normal!"),
+            _ => None,
         }
     }
 
+    // The solids are named after the step, "<step ID>:<n>", so they keep their names
+    // however the step is renamed
     pub fn from_extrusion(
-        name: String,
+        step_id: &str,
         plane: &RealPlane,
         sketch: &RealSketch,
         extrusion: &Extrusion,
-    ) -> HashMap<String, Self> {
+        warnings: &mut Vec<CADmiumError>,
+    ) -> Result<HashMap<String, Self>, CADmiumError> {
         let mut retval = HashMap::new();
 
         let extrusion_direction = match &extrusion.direction {
@@ -156,9 +460,9 @@ impl Solid {
         // Sometimes the chosen faces are touching, or one even envelops another. Let's
         // merge those faces together so that we have single solid wherever possible
         let unmerged_faces: Vec<Face> = extrusion
-            .face_ids
+            .resolve_face_ids(sketch, warnings)?
             .iter()
-            .map(|face_id| sketch.faces.get(*face_id as usize).unwrap().clone())
+            .map(|face_id| sketch.faces[*face_id as usize].clone())
             .collect();
         let merged_faces = merge_faces(&unmerged_faces, sketch);
 
@@ -177,17 +481,29 @@ impl Solid {
                 wires.push(Self::to_wire(plane, sketch, extrusion, interior).inverse());
             }
 
-            let face = builder::try_attach_plane(&wires).unwrap();
+            let truck_face = builder::try_attach_plane(&wires).map_err(|e| {
+                CADmiumError::InvalidGeometry(format!("{} face {}: {:?}", step_id, f_index, e))
+            })?;
 
-            let truck_solid = builder::tsweep(&face, vector);
+            let truck_solid = builder::tsweep(&truck_face, vector);
             let truck_solid = translated(&truck_solid, offset_vector);
 
-            let solid = Solid::from_truck_solid(format!("{}:{}", name, f_index), truck_solid);
-
-            retval.insert(format!("{}:{}", name, f_index), solid);
+            let solid_id = format!("{}:{}", step_id, f_index);
+            let mut solid = Solid::from_truck_solid(solid_id.clone(), truck_solid);
+            name_extrusion_faces(
+                &mut solid,
+                step_id,
+                &plane.plane,
+                sketch,
+                face,
+                &extrusion_direction,
+                (extrusion.offset, extrusion.length),
+            );
+
+            retval.insert(solid_id, solid);
         }
 
-        retval
+        Ok(retval)
     }
 
     pub fn to_wire(
@@ -291,6 +607,21 @@ impl Solid {
         }
     }
 
+    pub fn volume(&self, tolerance: f64) -> f64 {
+        mesh_volume(&self.truck_solid.triangulation(tolerance).to_polygon())
+    }
+
+    // Integrated over a mesh made with `tolerance`, so a smaller one is slower but closer
+    // for curved faces. Flat-faced solids come out exact at any tolerance
+    pub fn mass_properties(&self, density: f64, tolerance: f64) -> MassProperties {
+        let mut integrals = MassIntegrals::default();
+        integrals.add_mesh(
+            &self.truck_solid.triangulation(tolerance).to_polygon(),
+            density,
+        );
+        integrals.properties()
+    }
+
     pub fn to_obj_string(&self, tolerance: f64) -> String {
         let mesh = self.truck_solid.triangulation(tolerance).to_polygon();
         let mut buf = Vec::new();
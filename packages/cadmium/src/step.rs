@@ -4,8 +4,10 @@ use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
 use crate::archetypes::{Plane, PlaneDescription, Point3, Vector3};
+use crate::datum::{AxisDefinition, FaceReference, PlaneDefinition, PointDefinition};
 use crate::sketch::Sketch;
-use crate::extrusion::Extrusion;
+use crate::extrusion::{Extrusion, ExtrusionMode};
+use crate::hole::Hole;
 
 #[derive(Tsify, Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -14,11 +16,23 @@ pub enum StepData {
     Point {
         point: Point3,
     },
+    DatumPoint {
+        definition: PointDefinition,
+    },
+    Axis {
+        definition: AxisDefinition,
+        length: f64,
+    },
     Plane {
         plane: Plane,
         width: f64,
         height: f64,
     },
+    DatumPlane {
+        definition: PlaneDefinition,
+        width: f64,
+        height: f64,
+    },
     Sketch {
         plane_description: PlaneDescription,
         width: f64,
@@ -28,6 +42,49 @@ pub enum StepData {
     Extrusion {
         extrusion: Extrusion,
     },
+    Hole {
+        hole: Hole,
+    },
+}
+
+// Something a step needs from earlier in the history. Solids are named after the
+// step that made them rather than by step ID, so they get their own variant
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dependency {
+    Step(String),
+    Solid(String),
+}
+
+impl StepData {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        match self {
+            StepData::Point { .. } | StepData::Plane { .. } => vec![],
+            StepData::DatumPoint { definition } => definition.dependencies(),
+            StepData::Axis { definition, .. } => definition.dependencies(),
+            StepData::DatumPlane { definition, .. } => definition.dependencies(),
+            StepData::Sketch { plane_description, .. } => match plane_description {
+                PlaneDescription::PlaneId(plane_id) if plane_id == "" => vec![],
+                PlaneDescription::PlaneId(plane_id) => vec![Dependency::Step(plane_id.to_owned())],
+                PlaneDescription::SolidFace { solid_id, .. } => vec![Dependency::Solid(solid_id.to_owned())],
+                PlaneDescription::Face(face) => face.dependencies(),
+            },
+            StepData::Extrusion { extrusion } => {
+                let mut dependencies = vec![Dependency::Step(extrusion.sketch_id.to_owned())];
+                match &extrusion.mode {
+                    ExtrusionMode::New => {}
+                    ExtrusionMode::Add(merge_scope) | ExtrusionMode::Remove(merge_scope) => {
+                        dependencies.extend(merge_scope.iter().map(|solid_id| Dependency::Solid(solid_id.to_owned())));
+                    }
+                }
+                dependencies
+            }
+            StepData::Hole { hole } => {
+                let mut dependencies = vec![Dependency::Step(hole.sketch_id.to_owned())];
+                dependencies.extend(hole.merge_scope.iter().map(|solid_id| Dependency::Solid(solid_id.to_owned())));
+                dependencies
+            }
+        }
+    }
 }
 
 #[derive(Tsify, Debug, Serialize, Deserialize)]
@@ -51,6 +108,27 @@ impl Step {
         }
     }
 
+    pub fn new_datum_point(name: &str, definition: PointDefinition, point_id: u64) -> Self {
+        Step {
+            name: name.to_owned(),
+            unique_id: format!("Point-{}", point_id),
+            suppressed: false,
+            data: StepData::DatumPoint { definition },
+        }
+    }
+
+    pub fn new_axis(name: &str, definition: AxisDefinition, axis_id: u64) -> Self {
+        Step {
+            name: name.to_owned(),
+            unique_id: format!("Axis-{}", axis_id),
+            suppressed: false,
+            data: StepData::Axis {
+                definition,
+                length: 100.0,
+            },
+        }
+    }
+
     pub fn new_plane(name: &str, plane: Plane, plane_id: u64) -> Self {
         Step {
             name: name.to_owned(),
@@ -64,6 +142,19 @@ impl Step {
         }
     }
 
+    pub fn new_datum_plane(name: &str, definition: PlaneDefinition, plane_id: u64) -> Self {
+        Step {
+            name: name.to_owned(),
+            unique_id: format!("Plane-{}", plane_id),
+            suppressed: false,
+            data: StepData::DatumPlane {
+                definition,
+                height: 100.0,
+                width: 100.0,
+            },
+        }
+    }
+
     pub fn new_sketch(name: &str, plane_id: &str, sketch_id: u64) -> Self {
         Step {
             name: name.to_owned(),
@@ -100,6 +191,20 @@ impl Step {
         }
     }
 
+    pub fn new_sketch_on_face(name: &str, face: FaceReference, sketch_id: u64) -> Self {
+        Step {
+            name: name.to_owned(),
+            unique_id: format!("Sketch-{}", sketch_id),
+            suppressed: false,
+            data: StepData::Sketch {
+                plane_description: PlaneDescription::Face(face),
+                width: 12.5,
+                height: 7.5,
+                sketch: Sketch::new(),
+            },
+        }
+    }
+
     pub fn new_extrusion(name: &str, extrusion: Extrusion, extrusion_id: u64) -> Self {
         Step {
             name: name.to_owned(),
@@ -108,4 +213,13 @@ impl Step {
             data: StepData::Extrusion { extrusion },
         }
     }
+
+    pub fn new_hole(name: &str, hole: Hole, hole_id: u64) -> Self {
+        Step {
+            name: name.to_owned(),
+            unique_id: format!("Hole-{}", hole_id),
+            suppressed: false,
+            data: StepData::Hole { hole },
+        }
+    }
 }
@@ -30,7 +30,7 @@ fn secondary_extrusion_simple() {
     );
     wb.add_extrusion("Ext1", extrusion);
 
-    let s2_id = wb.add_sketch_to_solid_face("Sketch-2", "Ext1:0", Vector3::new(0.0, 0.0, 1.0));
+    let s2_id = wb.add_sketch_to_solid_face("Sketch-2", "Extrusion-0:0", Vector3::new(0.0, 0.0, 1.0));
     let mut s2 = wb.get_sketch_mut("Sketch-2").unwrap();
 
     // smaller
@@ -56,18 +56,18 @@ fn secondary_extrusion_simple() {
         25.0,
         0.0,
         Direction::Normal,
-        ExtrusionMode::Add(vec!["Ext1:0".to_string()]),
+        ExtrusionMode::Add(vec!["Extrusion-0:0".to_string()]),
     );
     wb.add_extrusion("Ext2", extrusion2);
 
-    let realization = p.get_realization(0, 1000);
+    let realization = p.get_realization(0, 1000).unwrap();
     let solids = realization.solids;
 
     let num_solids = solids.len();
     println!("Num Solids: {:?}", num_solids);
     assert!(num_solids == 1);
 
-    let final_solid = &solids["Ext1:0"];
+    let final_solid = &solids["Extrusion-0:0"];
     let mut mesh = final_solid.truck_solid.triangulation(0.02).to_polygon();
     mesh.put_together_same_attrs();
     let file = std::fs::File::create("secondary_extrusion.obj").unwrap();
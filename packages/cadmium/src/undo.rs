@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+
+// How many changes can be undone before the oldest ones are forgotten
+pub const UNDO_LIMIT: usize = 100;
+
+// Snapshots of the project, as JSON, taken before each change. Messages sent between
+// BeginUndoGroup and EndUndoGroup share one snapshot, so they are undone together, and
+// so do runs of messages recorded with the same merge key, like solver steps
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: SnapshotChain,
+    redo: SnapshotChain,
+    group_depth: usize,
+    // whether the open group has taken its snapshot yet
+    group_recorded: bool,
+    // the merge key the newest snapshot was recorded with
+    merge_key: Option<String>,
+}
+
+// A stack of snapshots where only the newest is kept whole. Each older one is kept as the
+// patch that turns the snapshot after it back into it, which is small for a small change
+#[derive(Debug, Default)]
+struct SnapshotChain {
+    newest: Option<String>,
+    // oldest first
+    older: VecDeque<Patch>,
+}
+
+impl SnapshotChain {
+    fn push(&mut self, snapshot: String) {
+        if let Some(newest) = self.newest.take() {
+            self.older.push_back(Patch::between(&snapshot, &newest));
+        }
+        self.newest = Some(snapshot);
+    }
+
+    fn pop(&mut self) -> Option<String> {
+        let newest = self.newest.take()?;
+        self.newest = self.older.pop_back().map(|patch| patch.apply(&newest));
+        Some(newest)
+    }
+
+    fn forget_oldest(&mut self) {
+        if self.older.pop_front().is_none() {
+            self.newest = None;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.older.len() + usize::from(self.newest.is_some())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    fn clear(&mut self) {
+        self.newest = None;
+        self.older.clear();
+    }
+}
+
+// Turns one snapshot into another by keeping the first `start` and last `end` bytes and
+// replacing whatever is between them with `middle`
+#[derive(Debug)]
+struct Patch {
+    start: usize,
+    end: usize,
+    middle: String,
+}
+
+impl Patch {
+    fn between(from: &str, to: &str) -> Patch {
+        let (a, b) = (from.as_bytes(), to.as_bytes());
+        // the bytes kept are the same in both, so a char boundary in one is one in the other
+        let mut start = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+        while !to.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = a[start..]
+            .iter()
+            .rev()
+            .zip(b[start..].iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        while !to.is_char_boundary(to.len() - end) {
+            end -= 1;
+        }
+
+        Patch {
+            start,
+            end,
+            middle: to[start..to.len() - end].to_owned(),
+        }
+    }
+
+    fn apply(&self, from: &str) -> String {
+        [
+            &from[..self.start],
+            self.middle.as_str(),
+            &from[from.len() - self.end..],
+        ]
+        .concat()
+    }
+}
+
+impl UndoStack {
+    // Records the snapshot from before a change. Changes with a merge key are undone
+    // together with the ones straight before them that had the same key
+    pub fn record(&mut self, before: String, merge_key: Option<String>) {
+        if self.group_depth > 0 {
+            self.merge_key = None;
+            if self.group_recorded {
+                return;
+            }
+            self.group_recorded = true;
+        } else if merge_key.is_some() && merge_key == self.merge_key {
+            return;
+        }
+
+        self.merge_key = merge_key;
+        self.undo.push(before);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.forget_oldest();
+        }
+        self.redo.clear();
+    }
+
+    // Whether recording a change with this merge key would keep its snapshot. When it
+    // would be undone along with the change before it there is no need to take one
+    pub fn needs_snapshot(&self, merge_key: Option<&str>) -> bool {
+        if self.group_depth > 0 {
+            return !self.group_recorded;
+        }
+        merge_key.is_none() || merge_key != self.merge_key.as_deref()
+    }
+
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.group_recorded = false;
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+    }
+
+    // Takes the snapshot to go back to, remembering the current state so it can be redone
+    pub fn undo(&mut self, current: String) -> Option<String> {
+        // undoing in the middle of a group closes it
+        self.group_depth = 0;
+        self.merge_key = None;
+
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: String) -> Option<String> {
+        self.group_depth = 0;
+        self.merge_key = None;
+
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouped_changes_share_a_snapshot() {
+        let mut stack = UndoStack::default();
+        stack.record("0".to_owned(), None);
+        stack.begin_group();
+        stack.record("1".to_owned(), None);
+        stack.record("2".to_owned(), None);
+        stack.end_group();
+
+        assert_eq!(stack.undo("3".to_owned()), Some("1".to_owned()));
+        assert_eq!(stack.undo("1".to_owned()), Some("0".to_owned()));
+        assert_eq!(stack.undo("0".to_owned()), None);
+        assert_eq!(stack.redo("0".to_owned()), Some("1".to_owned()));
+
+        // a new change means the undone ones can't be redone any more
+        stack.record("1".to_owned(), None);
+        assert!(!stack.can_redo());
+    }
+
+    #[test]
+    fn changes_with_the_same_merge_key_share_a_snapshot() {
+        let mut stack = UndoStack::default();
+        let solve = || Some("Solve Sketch-0".to_owned());
+        stack.record("0".to_owned(), solve());
+        assert!(!stack.needs_snapshot(Some("Solve Sketch-0")));
+        assert!(stack.needs_snapshot(None));
+        stack.record("1".to_owned(), solve());
+        stack.record("2".to_owned(), None);
+        stack.record("3".to_owned(), solve());
+        stack.record("4".to_owned(), solve());
+
+        assert_eq!(stack.undo("5".to_owned()), Some("3".to_owned()));
+        assert_eq!(stack.undo("3".to_owned()), Some("2".to_owned()));
+        assert_eq!(stack.undo("2".to_owned()), Some("0".to_owned()));
+        assert!(stack.needs_snapshot(Some("Solve Sketch-0")));
+    }
+
+    #[test]
+    fn only_the_newest_changes_are_kept() {
+        let mut stack = UndoStack::default();
+        for i in 0..UNDO_LIMIT + 10 {
+            stack.record(i.to_string(), None);
+        }
+
+        let mut undone = 0;
+        while stack.undo("".to_owned()).is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, UNDO_LIMIT);
+    }
+
+    #[test]
+    fn older_snapshots_are_kept_as_patches() {
+        let snapshots = [
+            r#"{"name":"Project","points":[1,2,3],"note":"café"}"#,
+            r#"{"name":"Project","points":[1,2,3,4],"note":"café"}"#,
+            // only the last byte of the é changes, which can't be patched on its own
+            r#"{"name":"Project","points":[1,2,3,4],"note":"cafè"}"#,
+            r#"{"name":"Project","points":[1,2,3,4],"note":"naïf"}"#,
+        ];
+        let mut stack = UndoStack::default();
+        for snapshot in snapshots.iter() {
+            stack.record(snapshot.to_string(), None);
+        }
+        assert!(stack.undo.older.iter().all(|patch| patch.middle.len() <= 6));
+
+        for snapshot in snapshots.iter().rev() {
+            assert_eq!(stack.undo("".to_owned()).as_deref(), Some(*snapshot));
+        }
+        assert!(!stack.can_undo());
+    }
+}
@@ -3,15 +3,26 @@ use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
 use crate::archetypes::{Plane, PlaneDescription, Point3, Vector3};
+use crate::datum::{AxisDefinition, FaceReference, PlaneDefinition, PointDefinition, RealAxis};
+use crate::dependency::DependencyGraph;
 use crate::error::CADmiumError;
 use crate::extrusion::{fuse, Extrusion, ExtrusionMode};
+use crate::hole::{Hole, RealHole};
+use crate::material::Material;
+use crate::naming::{inherit_face_names, sketch_face_name};
 use crate::project::{RealPlane, RealSketch};
-use crate::realization::Realization;
+use crate::realization::{
+    canonical_hash, CachedStep, Realization, RealizationCache, RealizationVersions, StepChanges,
+    StepState, StepStatus,
+};
 use crate::sketch::Sketch;
 use crate::solid::Solid;
-use crate::step::{Step, StepData};
+use crate::step::{Dependency, Step, StepData};
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
 // use truck_base::math::Vector3 as truck_vector3;
 use truck_shapeops::and as solid_and;
@@ -19,14 +30,33 @@ use truck_shapeops::and as solid_and;
 #[derive(Tsify, Debug, Serialize, Deserialize)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct Workbench {
+    // given by the project, and kept when other workbenches come and go
+    #[serde(default)]
+    pub(crate) id: u64,
     pub(crate) name: String,
     pub(crate) history: Vec<Step>,
     pub(crate) step_counters: HashMap<String, u64>,
+
+    // The rollback marker. Only the steps up to and including this one are realized,
+    // and new steps go right after it. None means the end of the history
+    #[serde(default)]
+    pub(crate) rollback_after: Option<String>,
+
+    // what the solids are made of: one material for the whole workbench, which individual
+    // solids can override by ID
+    #[serde(default)]
+    pub(crate) material: Option<Material>,
+    #[serde(default)]
+    pub(crate) solid_materials: HashMap<String, Material>,
+
+    #[serde(skip)]
+    pub(crate) cache: RefCell<RealizationCache>,
 }
 
 impl Workbench {
     pub fn new(name: &str) -> Self {
         let mut wb = Workbench {
+            id: 0,
             name: name.to_owned(),
             history: vec![],
             step_counters: HashMap::from([
@@ -34,7 +64,13 @@ impl Workbench {
                 ("Plane".to_owned(), 0),
                 ("Sketch".to_owned(), 0),
                 ("Extrusion".to_owned(), 0),
+                ("Hole".to_owned(), 0),
+                ("Axis".to_owned(), 0),
             ]),
+            rollback_after: None,
+            material: None,
+            solid_materials: HashMap::new(),
+            cache: RefCell::default(),
         };
 
         wb.add_point("Origin", Point3::new(0.0, 0.0, 0.0));
@@ -48,11 +84,7 @@ impl Workbench {
     pub fn get_first_plane_id(&self) -> Option<String> {
         for step in self.history.iter() {
             match &step.data {
-                StepData::Plane {
-                    plane: _,
-                    width: _,
-                    height: _,
-                } => {
+                StepData::Plane { .. } | StepData::DatumPlane { .. } => {
                     return Some(step.unique_id.clone());
                 }
                 _ => {}
@@ -77,11 +109,7 @@ impl Workbench {
         let mut last_plane_id = None;
         for step in self.history.iter() {
             match &step.data {
-                StepData::Plane {
-                    plane: _,
-                    width: _,
-                    height: _,
-                } => {
+                StepData::Plane { .. } | StepData::DatumPlane { .. } => {
                     last_plane_id = Some(step.unique_id.clone());
                 }
                 _ => {}
@@ -130,29 +158,237 @@ impl Workbench {
         }
     }
 
-    pub fn add_point(&mut self, name: &str, point: Point3) {
-        let counter = self.step_counters.get_mut("Point").unwrap();
-        self.history.push(Step::new_point(name, point, *counter));
+    // The IDs of the earlier steps that the step at this index builds on
+    pub fn step_dependencies(&self, index: usize) -> Vec<String> {
+        let earlier = &self.history[..index];
+        let mut step_ids: Vec<String> = vec![];
+
+        for dependency in self.history[index].data.dependencies() {
+            let step_id = match dependency {
+                Dependency::Step(step_id) => Some(step_id),
+                Dependency::Solid(solid_id) => {
+                    extrusion_for_solid(earlier, &solid_id).map(|step| step.unique_id.clone())
+                }
+            };
+
+            if let Some(step_id) = step_id {
+                if earlier.iter().any(|step| step.unique_id == step_id)
+                    && !step_ids.contains(&step_id)
+                {
+                    step_ids.push(step_id);
+                }
+            }
+        }
+
+        step_ids
+    }
+
+    // The references made by the step at this index which no earlier step provides
+    pub fn missing_dependencies(&self, index: usize) -> Vec<String> {
+        let earlier = &self.history[..index];
+
+        self.history[index]
+            .data
+            .dependencies()
+            .into_iter()
+            .filter_map(|dependency| match dependency {
+                Dependency::Step(step_id) => {
+                    (!earlier.iter().any(|step| step.unique_id == step_id)).then_some(step_id)
+                }
+                Dependency::Solid(solid_id) => match extrusion_for_solid(earlier, &solid_id) {
+                    Some(_) => None,
+                    None => Some(solid_id),
+                },
+            })
+            .collect()
+    }
+
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(self)
+    }
+
+    // Deletes a step and returns the IDs of every step that builds on it. With cascade
+    // those steps are deleted too, otherwise they stay in the history and realize as broken
+    pub fn delete_step(
+        &mut self,
+        step_id: &str,
+        cascade: bool,
+    ) -> Result<Vec<String>, CADmiumError> {
+        self.step_index(step_id)?;
+        let affected = self.dependency_graph().downstream(step_id);
+
+        let rollback_index = self.rollback_index();
+        let mut kept_before_rollback = 0;
+        let mut index = 0;
+        self.history.retain(|step| {
+            let keep =
+                step.unique_id != step_id && !(cascade && affected.contains(&step.unique_id));
+            if keep && index < rollback_index {
+                kept_before_rollback += 1;
+            }
+            index += 1;
+            keep
+        });
+
+        // a rollback marker on a deleted step moves back to the nearest step that is left
+        if self.rollback_after.is_some() {
+            self.rollback_after = match kept_before_rollback {
+                0 => None,
+                n => Some(self.history[n - 1].unique_id.clone()),
+            };
+        }
+
+        Ok(affected)
+    }
+
+    pub fn set_suppressed(&mut self, step_id: &str, suppressed: bool) -> Result<(), CADmiumError> {
+        self.get_step_by_id_mut(step_id)?.suppressed = suppressed;
+        Ok(())
+    }
+
+    // The first later step that the step at this index builds on, if there is one
+    fn misplaced_dependency(&self, index: usize) -> Option<String> {
+        let earlier = &self.history[..index];
+        let later = &self.history[index + 1..];
+
+        self.history[index]
+            .data
+            .dependencies()
+            .into_iter()
+            .find_map(|dependency| match dependency {
+                Dependency::Step(step_id) => later
+                    .iter()
+                    .find(|step| step.unique_id == step_id)
+                    .map(|step| step.unique_id.clone()),
+                Dependency::Solid(solid_id) => match extrusion_for_solid(earlier, &solid_id) {
+                    Some(_) => None,
+                    None => {
+                        extrusion_for_solid(later, &solid_id).map(|step| step.unique_id.clone())
+                    }
+                },
+            })
+    }
+
+    // Moves a step so it sits just before or just after the target step. The move is
+    // undone if it would leave any step ahead of something it depends on
+    pub fn move_step(
+        &mut self,
+        step_id: &str,
+        target_id: &str,
+        after: bool,
+    ) -> Result<(), CADmiumError> {
+        let from = self.step_index(step_id)?;
+        if step_id == target_id {
+            return Ok(());
+        }
+        let step = self.history.remove(from);
+        let to = match self.step_index(target_id) {
+            Ok(index) if after => index + 1,
+            Ok(index) => index,
+            Err(e) => {
+                self.history.insert(from, step);
+                return Err(e);
+            }
+        };
+        self.history.insert(to, step);
+
+        // only the steps between the old and new positions changed their relative order
+        for index in from.min(to)..=from.max(to) {
+            if let Some(dependency_id) = self.misplaced_dependency(index) {
+                let step_id = self.history[index].unique_id.clone();
+                let step = self.history.remove(to);
+                self.history.insert(from, step);
+                return Err(CADmiumError::StepBeforeDependency(step_id, dependency_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn step_index(&self, step_id: &str) -> Result<usize, CADmiumError> {
+        self.history
+            .iter()
+            .position(|step| step.unique_id == step_id)
+            .ok_or(CADmiumError::StepIDNotFound(step_id.to_owned()))
+    }
+
+    // Moves the rollback marker to just after this step, or to the end of the history for None
+    pub fn set_rollback(&mut self, after_step_id: Option<&str>) -> Result<(), CADmiumError> {
+        if let Some(step_id) = after_step_id {
+            self.step_index(step_id)?;
+        }
+        self.rollback_after = after_step_id.map(str::to_owned);
+        Ok(())
+    }
+
+    // Moves the rollback marker forward or back by some number of steps, stopping at either end
+    pub fn move_rollback(&mut self, steps: i64) {
+        let index = (self.rollback_index() as i64 + steps).clamp(1, self.history.len() as i64);
+        self.rollback_after = if index as usize == self.history.len() {
+            None
+        } else {
+            Some(self.history[index as usize - 1].unique_id.clone())
+        };
+    }
+
+    // The number of steps before the rollback marker
+    pub fn rollback_index(&self) -> usize {
+        self.rollback_after
+            .as_ref()
+            .and_then(|step_id| self.step_index(step_id).ok())
+            .map_or(self.history.len(), |index| index + 1)
+    }
+
+    // Adds a step at the rollback marker and moves the marker past it,
+    // so several new steps end up in the order they were added
+    fn insert_step(&mut self, step: Step) -> String {
+        let step_id = step.unique_id.clone();
+        let index = self.rollback_index();
+        self.history.insert(index, step);
+        if self.rollback_after.is_some() {
+            self.rollback_after = Some(step_id.clone());
+        }
+
+        step_id
+    }
+
+    fn next_counter(&mut self, kind: &str) -> u64 {
+        // files saved before some kinds of step existed won't have a counter for them yet
+        let counter = self.step_counters.entry(kind.to_owned()).or_insert(0);
         *counter += 1;
+        *counter - 1
+    }
+
+    pub fn add_point(&mut self, name: &str, point: Point3) {
+        let counter = self.next_counter("Point");
+        self.insert_step(Step::new_point(name, point, counter));
+    }
+
+    pub fn add_datum_point(&mut self, name: &str, definition: PointDefinition) -> String {
+        let counter = self.next_counter("Point");
+        self.insert_step(Step::new_datum_point(name, definition, counter))
+    }
+
+    pub fn add_axis(&mut self, name: &str, definition: AxisDefinition) -> String {
+        let counter = self.next_counter("Axis");
+        self.insert_step(Step::new_axis(name, definition, counter))
     }
 
     pub fn add_plane(&mut self, name: &str, plane: Plane) -> String {
-        let counter = self.step_counters.get_mut("Plane").unwrap();
-        self.history.push(Step::new_plane(name, plane, *counter));
-        *counter += 1;
+        let counter = self.next_counter("Plane");
+        self.insert_step(Step::new_plane(name, plane, counter))
+    }
 
-        self.plane_name_to_id(name).unwrap()
+    pub fn add_datum_plane(&mut self, name: &str, definition: PlaneDefinition) -> String {
+        let counter = self.next_counter("Plane");
+        self.insert_step(Step::new_datum_plane(name, definition, counter))
     }
 
     pub fn plane_name_to_id(&self, plane_name: &str) -> Option<String> {
         for step in self.history.iter() {
             if step.name == plane_name {
                 match &step.data {
-                    StepData::Plane {
-                        plane: _,
-                        width: _,
-                        height: _,
-                    } => {
+                    StepData::Plane { .. } | StepData::DatumPlane { .. } => {
                         return Some(step.unique_id.clone());
                     }
                     _ => {}
@@ -171,29 +407,31 @@ impl Workbench {
         // TODO: maybe this shouldn't just take in a normal. Maybe it should take in the o, p, q points as well
         // that way it could try to match even if there are multiple faces on this solid which have the same normal vector
         // println!("New Normal! {:?}", normal);
-        // called like: wb.add_sketch_to_solid_face("Sketch-2", "Ext1:0", Vector3::new(0.0, 0.0, 1.0));
-
-        let counter = self.step_counters.get_mut("Sketch").unwrap();
-        let new_step = Step::new_sketch_on_solid_face(&new_sketch_name, solid_id, normal, *counter);
-        let new_step_id = new_step.unique_id.clone();
-        self.history.push(new_step);
-        *counter += 1;
+        // called like: wb.add_sketch_to_solid_face("Sketch-2", "Extrusion-0:0", Vector3::new(0.0, 0.0, 1.0));
+
+        let counter = self.next_counter("Sketch");
+        self.insert_step(Step::new_sketch_on_solid_face(
+            &new_sketch_name,
+            solid_id,
+            normal,
+            counter,
+        ))
+    }
 
-        new_step_id
+    pub fn add_sketch_to_face(&mut self, new_sketch_name: &str, face: FaceReference) -> String {
+        let counter = self.next_counter("Sketch");
+        self.insert_step(Step::new_sketch_on_face(new_sketch_name, face, counter))
     }
 
     pub fn add_sketch_to_plane(&mut self, name: &str, plane_id: &str) -> String {
         if plane_id != "" {
             // if the plane id is specified, check to make sure a plane with that ID exists
+            // somewhere before the rollback marker, which is where the sketch will go
             let mut plane_exists = false;
-            for step in self.history.iter() {
+            for step in self.history[..self.rollback_index()].iter() {
                 if step.unique_id == plane_id {
                     match &step.data {
-                        StepData::Plane {
-                            plane: _,
-                            width: _,
-                            height: _,
-                        } => {
+                        StepData::Plane { .. } | StepData::DatumPlane { .. } => {
                             plane_exists = true;
                         }
                         _ => {}
@@ -209,294 +447,596 @@ impl Workbench {
 
         // If the sketch name is empty string, then we need to generate a new name
         // Let's use "Sketch n" where n is the number of sketches
-        let counter = self.step_counters.get_mut("Sketch").unwrap();
+        let counter = self.next_counter("Sketch");
         let sketch_name = if name == "" {
-            format!("Sketch {}", *counter + 1)
+            format!("Sketch {}", counter + 1)
         } else {
             name.to_owned()
         };
 
-        let new_step = Step::new_sketch(&sketch_name, &plane_id, *counter);
-        let new_step_id = new_step.unique_id.clone();
-        self.history.push(new_step);
-        *counter += 1;
-
-        new_step_id
+        self.insert_step(Step::new_sketch(&sketch_name, &plane_id, counter))
     }
 
-    pub fn add_extrusion(&mut self, name: &str, extrusion: Extrusion) -> u64 {
+    pub fn add_extrusion(&mut self, name: &str, mut extrusion: Extrusion) -> u64 {
+        if extrusion.face_names.is_empty() {
+            // remember the faces in a way that survives the sketch being edited
+            extrusion.face_names = self
+                .sketch_face_names(&extrusion.sketch_id, &extrusion.face_ids)
+                .unwrap_or_default();
+        }
+
         // If the extrusion name is empty string, then we need to generate a new name
         // Let's use "Extrusion n" where n is the number of extrusions
-        let counter = self.step_counters.get_mut("Extrusion").unwrap();
+        let counter = self.next_counter("Extrusion");
         let extrusion_name = if name == "" {
-            format!("Extrusion {}", *counter + 1)
+            format!("Extrusion {}", counter + 1)
         } else {
             name.to_owned()
         };
-        self.history
-            .push(Step::new_extrusion(&extrusion_name, extrusion, *counter));
-        *counter += 1;
-        *counter - 1
+        self.insert_step(Step::new_extrusion(&extrusion_name, extrusion, counter));
+        counter
     }
 
+    // The persistent names of some of a sketch's faces, as the sketch is right now. They
+    // come from the cache, so only steps that changed since the last realize are redone
+    pub fn sketch_face_names(
+        &self,
+        sketch_id: &str,
+        face_ids: &[u64],
+    ) -> Result<Vec<String>, CADmiumError> {
+        let index = self
+            .history
+            .iter()
+            .position(|step| step.unique_id == sketch_id)
+            .ok_or(CADmiumError::StepIDNotFound(sketch_id.to_owned()))?;
+        self.fill_cache(index + 1);
+        let cache = self.cache.borrow();
+        let (_sketch, split_sketch, _name) = cache.entries[index]
+            .changes
+            .sketches
+            .changed
+            .get(sketch_id)
+            .ok_or(CADmiumError::StepIDNotFound(sketch_id.to_owned()))?;
+
+        face_ids
+            .iter()
+            .map(|face_id| {
+                split_sketch
+                    .faces
+                    .get(*face_id as usize)
+                    .map(sketch_face_name)
+                    .ok_or(CADmiumError::SketchFaceNotFound(
+                        sketch_id.to_owned(),
+                        face_id.to_string(),
+                    ))
+            })
+            .collect()
+    }
+
+    pub fn add_hole(&mut self, name: &str, hole: Hole) -> String {
+        let counter = self.next_counter("Hole");
+        let hole_name = if name == "" {
+            format!("Hole {}", counter + 1)
+        } else {
+            name.to_owned()
+        };
+        self.insert_step(Step::new_hole(&hole_name, hole, counter))
+    }
+
+    // Realizes at most max_steps steps, never going past the rollback marker
     pub fn realize(&self, max_steps: u64) -> Realization {
-        let mut realized = Realization::new();
-        let max_steps = max_steps as usize; // just coerce the type once
+        let mut realized = self.realize_steps((max_steps as usize).min(self.rollback_index()));
+        // materials are applied afterwards so changing one doesn't throw away the cache
+        for (solid_id, solid) in realized.solids.iter_mut() {
+            solid.material = self.material_for(solid_id);
+        }
+        realized
+    }
 
-        for (step_n, step) in self.history.iter().enumerate() {
-            // println!("{:?}", step.name);
-            if step_n >= max_steps {
-                break;
-            }
+    // The ID and checksum of each solid the history realizes to, read straight from the
+    // cache rather than copied out of a realization
+    pub fn solid_checksums(&self) -> Vec<(String, String)> {
+        let max_steps = self.rollback_index().min(self.history.len());
+        self.fill_cache(max_steps);
+        self.cache
+            .borrow()
+            .solids(max_steps)
+            .into_iter()
+            .map(|(solid_id, solid)| (solid_id.to_owned(), solid.crc32.clone()))
+            .collect()
+    }
 
-            let step_data = &step.data;
-            // println!("{:?}", step_data);
-            match step_data {
-                StepData::Point { point } => {
-                    realized
-                        .points
-                        .insert(step.unique_id.to_owned(), point.clone());
-                }
-                StepData::Plane {
-                    plane,
-                    width,
-                    height,
-                } => {
-                    let rp = RealPlane {
-                        plane: plane.clone(),
-                        width: *width,
-                        height: *height,
-                        name: step.name.clone(),
-                    };
-                    realized.planes.insert(step.unique_id.to_owned(), rp);
+    // The solid's own material if it has one, otherwise the workbench's
+    pub fn material_for(&self, solid_id: &str) -> Material {
+        self.solid_materials
+            .get(solid_id)
+            .or(self.material.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Sets or, with None, clears the material of one solid or of the whole workbench
+    pub fn set_material(
+        &mut self,
+        solid_id: Option<&str>,
+        material: Option<Material>,
+    ) -> Result<(), CADmiumError> {
+        match (solid_id, material) {
+            (None, material) => self.material = material,
+            (Some(solid_id), None) => {
+                self.solid_materials.remove(solid_id);
+            }
+            (Some(solid_id), Some(material)) => {
+                if !self
+                    .realize(self.history.len() as u64)
+                    .solids
+                    .contains_key(solid_id)
+                {
+                    return Err(CADmiumError::SolidNotFound(solid_id.to_owned()));
                 }
-                StepData::Sketch {
-                    width: _,
-                    height: _,
-                    plane_description,
-                    sketch,
-                } => match plane_description {
-                    PlaneDescription::PlaneId(plane_id) => {
-                        if plane_id == "" {
-                            println!("Sketch {} has no plane", step.name);
-                            continue;
-                        }
+                self.solid_materials.insert(solid_id.to_owned(), material);
+            }
+        }
+        Ok(())
+    }
 
-                        let plane = &realized.planes[plane_id];
-
-                        realized.sketches.insert(
-                            step.unique_id.to_owned(),
-                            (
-                                RealSketch::new(&plane.name, plane_id, plane, sketch),
-                                RealSketch::new(
-                                    &plane.name,
-                                    plane_id,
-                                    plane,
-                                    &sketch.split_intersections(false),
-                                ),
-                                step.name.clone(),
-                            ),
-                        );
-                    }
-                    PlaneDescription::SolidFace { solid_id, normal } => {
-                        let solid = &realized.solids[solid_id];
-                        let face = solid.get_face_by_normal(normal).unwrap();
-                        let oriented_surface = face.oriented_surface();
-
-                        println!("Surface: {:?}", oriented_surface);
-                        let sketch_plane;
-                        match oriented_surface {
-                            truck_modeling::geometry::Surface::Plane(p) => {
-                                let plane = Plane::from_truck(p);
-                                println!("Plane: {:?}", plane);
-                                sketch_plane = plane;
-                            }
-                            _ => {
-                                panic!("I only know how to put sketches on planes");
-                            }
-                        }
+    fn realize_steps(&self, max_steps: usize) -> Realization {
+        let max_steps = max_steps.min(self.history.len());
+        self.fill_cache(max_steps);
+        self.cache.borrow().replay(max_steps).0
+    }
+
+    // Makes sure the cache holds the first max_steps steps as they are now
+    fn fill_cache(&self, max_steps: usize) {
+        let keys = self.step_keys(max_steps);
+        let mut cache = self.cache.borrow_mut();
+
+        // pick up after the last step which, along with everything before it, hasn't changed
+        let reusable = cache
+            .entries
+            .iter()
+            .zip(keys.iter())
+            .take_while(|(entry, key)| entry.key == **key)
+            .count();
+        if reusable == max_steps {
+            return;
+        }
+        let (mut realized, mut skipped) = cache.replay(reusable);
+        cache.entries.truncate(reusable);
+
+        for step_n in reusable..max_steps {
+            let before = RealizationVersions::of(&realized);
+            let status = self.realize_next(step_n, &mut realized, &mut skipped);
+            cache.steps_realized += 1;
+
+            cache.entries.push(CachedStep {
+                key: keys[step_n],
+                changes: StepChanges::between(&before, &realized),
+                status: status.clone(),
+                skipped: skipped.contains(&status.step_id),
+            });
+            realized.statuses.push(status);
+        }
+    }
+
+    // Realizes the step at this index on top of everything before it. Steps that are
+    // suppressed or fail, and any steps that build on them, are added to skipped
+    fn realize_next(
+        &self,
+        step_n: usize,
+        realized: &mut Realization,
+        skipped: &mut HashSet<String>,
+    ) -> StepStatus {
+        let step = &self.history[step_n];
+
+        if step.suppressed {
+            skipped.insert(step.unique_id.clone());
+            return StepStatus::new(&step.unique_id, StepState::Suppressed, &[]);
+        }
+
+        let missing = self.missing_dependencies(step_n);
+        if !missing.is_empty() {
+            skipped.insert(step.unique_id.clone());
+            return StepStatus::new(
+                &step.unique_id,
+                StepState::Error,
+                &[CADmiumError::MissingDependency(missing.join(", "))],
+            );
+        }
+
+        if let Some(skipped_dependency) = self
+            .step_dependencies(step_n)
+            .into_iter()
+            .find(|step_id| skipped.contains(step_id))
+        {
+            skipped.insert(step.unique_id.clone());
+            return StepStatus::new(
+                &step.unique_id,
+                StepState::Skipped,
+                &[CADmiumError::DependencyUnavailable(skipped_dependency)],
+            );
+        }
+
+        let mut warnings = vec![];
+        match realize_step(realized, step, &mut warnings) {
+            Ok(()) if warnings.is_empty() => StepStatus::new(&step.unique_id, StepState::Ok, &[]),
+            Ok(()) => StepStatus::new(&step.unique_id, StepState::Warning, &warnings),
+            Err(e) => {
+                skipped.insert(step.unique_id.clone());
+                StepStatus::new(&step.unique_id, StepState::Error, &[e])
+            }
+        }
+    }
 
-                        let new_plane_id = format!("derived_plane_for:{}", step.name);
-
-                        let rp = RealPlane {
-                            plane: sketch_plane.clone(),
-                            width: 90.0,
-                            height: 60.0,
-                            name: new_plane_id.clone(),
-                        };
-                        realized.planes.insert(new_plane_id.clone(), rp);
-                        let rp = &realized.planes[&new_plane_id];
-
-                        realized.sketches.insert(
-                            step.unique_id.to_owned(),
-                            (
-                                RealSketch::new(&new_plane_id, &new_plane_id, &rp, sketch),
-                                RealSketch::new(
-                                    &new_plane_id,
-                                    &new_plane_id,
-                                    &rp,
-                                    &sketch.split_intersections(false),
-                                ),
-                                step.name.clone(),
-                            ),
-                        );
+    // A hash of each of the first count steps together with every step before it
+    fn step_keys(&self, count: usize) -> Vec<u64> {
+        let mut key = 0;
+        self.history[..count]
+            .iter()
+            .map(|step| {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                canonical_hash(step).hash(&mut hasher);
+                key = hasher.finish();
+                key
+            })
+            .collect()
+    }
+
+    // Forgets every cached step, so the next realize starts from scratch
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().entries.clear();
+    }
+}
+
+// Adds whatever a single step produces to the realization. Problems that leave the step
+// with some output, like a failed merge, are pushed onto warnings instead of returned
+fn realize_step(
+    realized: &mut Realization,
+    step: &Step,
+    warnings: &mut Vec<CADmiumError>,
+) -> Result<(), CADmiumError> {
+    match &step.data {
+        StepData::Point { point } => {
+            realized
+                .points
+                .insert(step.unique_id.to_owned(), point.clone());
+        }
+        StepData::DatumPoint { definition } => {
+            let point = definition.evaluate(realized)?;
+            realized.points.insert(step.unique_id.to_owned(), point);
+        }
+        StepData::Axis { definition, length } => {
+            let ra = RealAxis {
+                axis: definition.evaluate(realized)?,
+                length: *length,
+                name: step.name.clone(),
+            };
+            realized.axes.insert(step.unique_id.to_owned(), ra);
+        }
+        StepData::Plane {
+            plane,
+            width,
+            height,
+        } => {
+            let rp = RealPlane {
+                plane: plane.clone(),
+                width: *width,
+                height: *height,
+                name: step.name.clone(),
+            };
+            realized.planes.insert(step.unique_id.to_owned(), rp);
+        }
+        StepData::DatumPlane {
+            definition,
+            width,
+            height,
+        } => {
+            // datum planes are evaluated against whatever they reference right now,
+            // so they follow along when upstream geometry moves
+            let rp = RealPlane {
+                plane: definition.evaluate(realized)?,
+                width: *width,
+                height: *height,
+                name: step.name.clone(),
+            };
+            realized.planes.insert(step.unique_id.to_owned(), rp);
+        }
+        StepData::Sketch {
+            width: _,
+            height: _,
+            plane_description,
+            sketch,
+        } => match plane_description {
+            PlaneDescription::PlaneId(plane_id) => {
+                if plane_id == "" {
+                    // a new sketch may not have been given a plane yet, that's fine
+                    warnings.push(CADmiumError::SketchWithoutPlane(step.unique_id.clone()));
+                    return Ok(());
+                }
+
+                let plane = realized
+                    .planes
+                    .get(plane_id)
+                    .ok_or(CADmiumError::PlaneNotFound(plane_id.to_owned()))?;
+
+                let real_sketches = (
+                    RealSketch::new(&plane.name, plane_id, plane, sketch),
+                    RealSketch::new(
+                        &plane.name,
+                        plane_id,
+                        plane,
+                        &sketch.split_intersections(false),
+                    ),
+                    step.name.clone(),
+                );
+                realized
+                    .sketches
+                    .insert(step.unique_id.to_owned(), real_sketches);
+            }
+            PlaneDescription::SolidFace { solid_id, normal } => {
+                // Older projects picked the face by its normal alone. Their sketches
+                // were drawn on the oriented surface, so keep building the plane that way
+                let solid = realized
+                    .solids
+                    .get(solid_id)
+                    .ok_or(CADmiumError::SolidNotFound(solid_id.to_owned()))?;
+                let sketch_plane = solid
+                    .get_face_by_normal(normal)
+                    .and_then(|face| match face.oriented_surface() {
+                        truck_modeling::geometry::Surface::Plane(p) => Some(Plane::from_truck(p)),
+                        _ => None,
+                    })
+                    .ok_or(CADmiumError::InvalidGeometry(format!(
+                        "{} needs exactly one planar face with normal {:?}",
+                        solid_id, normal
+                    )))?;
+
+                insert_sketch_on_face(realized, step, sketch, sketch_plane);
+            }
+            PlaneDescription::Face(face) => {
+                let sketch_plane = face.plane(realized)?;
+                insert_sketch_on_face(realized, step, sketch, sketch_plane);
+            }
+        },
+        StepData::Extrusion { extrusion } => {
+            let (_sketch, split_sketch, _name) = realized
+                .sketches
+                .get(&extrusion.sketch_id)
+                .ok_or(CADmiumError::StepIDNotFound(extrusion.sketch_id.to_owned()))?;
+            let plane =
+                realized
+                    .planes
+                    .get(&split_sketch.plane_id)
+                    .ok_or(CADmiumError::PlaneNotFound(
+                        split_sketch.plane_id.to_owned(),
+                    ))?;
+
+            let new_solids =
+                Solid::from_extrusion(&step.unique_id, plane, split_sketch, extrusion, warnings)?;
+
+            match &extrusion.mode {
+                ExtrusionMode::New => {
+                    // if this extrusion is in mode "New" then this old behavior is correct!
+                    for (name, solid) in new_solids {
+                        realized.solids.insert(name, solid);
                     }
-                },
-                StepData::Extrusion { extrusion } => {
-                    let (_sketch, split_sketch, _name) = &realized.sketches[&extrusion.sketch_id];
-                    let plane = &realized.planes[&split_sketch.plane_id];
-
-                    match &extrusion.mode {
-                        ExtrusionMode::New => {
-                            // if this extrusion is in mode "New" then this old behavior is correct!
-
-                            let solids = Solid::from_extrusion(
-                                step.name.clone(),
-                                plane,
-                                split_sketch,
-                                extrusion,
-                            );
+                }
+                ExtrusionMode::Add(merge_scope) => {
+                    // if this extrusion is in mode "Add" Then we need to merge the resulting solids
+                    // with each of the solids listed in the merge scope
+                    for existing_solid_name in merge_scope {
+                        let mut existing_solid_to_merge_with =
+                            match realized.solids.remove(existing_solid_name) {
+                                Some(s) => s,
+                                None => {
+                                    warnings.push(CADmiumError::SolidNotFound(
+                                        existing_solid_name.to_owned(),
+                                    ));
+                                    continue;
+                                }
+                            };
 
-                            for (name, solid) in solids {
-                                realized.solids.insert(name, solid);
-                            }
-                        }
-                        ExtrusionMode::Add(merge_scope) => {
-                            // if this extrusion is in mode "Add" Then we need to merge the resulting solids
-                            // with each of the solids listed in the merge scope
-
-                            let new_solids = Solid::from_extrusion(
-                                step.name.clone(),
-                                plane,
-                                split_sketch,
-                                extrusion,
+                        // merge this existing solid with as many of the new solids as possible
+                        for (_, new_solid) in new_solids.iter() {
+                            let fused = fuse(
+                                &existing_solid_to_merge_with.truck_solid,
+                                &new_solid.truck_solid,
                             );
 
-                            // NO LONGER NEEDED
-                            // // this is some bullshit, but bear with me. To make the solids merge properly we need to
-                            // // lengthen the extrusion a tiny bit, basically build in some buffer
-                            // let mut longer_extrusion = extrusion.clone();
-                            // longer_extrusion.length += 0.001;
-                            // longer_extrusion.offset -= 0.001;
-                            // let solids = Solid::from_extrusion(
-                            //     step.name.clone(),
-                            //     plane,
-                            //     split_sketch,
-                            //     &longer_extrusion,
-                            // );
-
-                            for existing_solid_name in merge_scope {
-                                let mut existing_solid_to_merge_with =
-                                    realized.solids.remove(existing_solid_name).unwrap();
-
-                                // merge this existing solid with as many of the new solids as possible
-                                for (_, new_solid) in new_solids.iter() {
-                                    // let new_candidate = translated(
-                                    //     &solid.truck_solid,
-                                    //     TruckVector3::new(0.0, 0.0, 1.0),
-                                    // );
-                                    // println!("\nTranslated new candidate: {:?}", new_candidate);
-
-                                    // let result =
-                                    //     solid_or(&existing_solid.truck_solid, &new_candidate, 0.1);
-
-                                    let fused = fuse(
-                                        &existing_solid_to_merge_with.truck_solid,
-                                        &new_solid.truck_solid,
+                            match fused {
+                                Some(s) => {
+                                    let mut merged =
+                                        Solid::from_truck_solid(existing_solid_name.to_owned(), s);
+                                    inherit_face_names(
+                                        &mut merged,
+                                        &[&existing_solid_to_merge_with, new_solid],
                                     );
-
-                                    match fused {
-                                        Some(s) => {
-                                            existing_solid_to_merge_with = Solid::from_truck_solid(
-                                                existing_solid_name.to_owned(),
-                                                s,
-                                            );
-                                        }
-                                        None => {
-                                            println!("Failed to merge with OR");
-                                        }
-                                    }
+                                    existing_solid_to_merge_with = merged;
+                                }
+                                None => {
+                                    warnings.push(CADmiumError::BooleanFailed(format!(
+                                        "merge {} with {}",
+                                        new_solid.name, existing_solid_name
+                                    )));
                                 }
-
-                                realized.solids.insert(
-                                    existing_solid_name.to_owned(),
-                                    existing_solid_to_merge_with,
-                                );
                             }
                         }
 
-                        ExtrusionMode::Remove(merge_scope) => {
-                            // If this extrusion is in mode "Remove" then we need to subtract the resulting solid
-                            // with each of the solids listed in the merge scope
-                            println!("Okay, let's remove");
-                            let new_solids = Solid::from_extrusion(
-                                step.name.clone(),
-                                plane,
-                                split_sketch,
-                                extrusion,
-                            );
-
-                            for existing_solid_name in merge_scope {
-                                let mut existing_solid_to_merge_with =
-                                    realized.solids.remove(existing_solid_name).unwrap();
-
-                                // merge this existing solid with as many of the new solids as possible
-                                for (_, new_solid) in new_solids.iter() {
-                                    // let translated_solid = translated(
-                                    //     &solid.truck_solid,
-                                    //     TruckVector3::new(0.0, 0.0, 1.0),
-                                    // );
-                                    // println!("\nTranslated new candidate: {:?}", new_candidate);
-
-                                    // let result =
-                                    //     solid_or(&existing_solid.truck_solid, &new_candidate, 0.1);
-
-                                    let punch = new_solid.truck_solid.clone();
-                                    // punch.not();
-                                    println!("Have a punch");
-
-                                    let cleared = solid_and(
-                                        &existing_solid_to_merge_with.truck_solid,
-                                        &punch,
-                                        0.1,
+                        realized
+                            .solids
+                            .insert(existing_solid_name.to_owned(), existing_solid_to_merge_with);
+                    }
+                }
+                ExtrusionMode::Remove(merge_scope) => {
+                    // If this extrusion is in mode "Remove" then we need to subtract the resulting solid
+                    // with each of the solids listed in the merge scope
+                    for existing_solid_name in merge_scope {
+                        let mut existing_solid_to_merge_with =
+                            match realized.solids.remove(existing_solid_name) {
+                                Some(s) => s,
+                                None => {
+                                    warnings.push(CADmiumError::SolidNotFound(
+                                        existing_solid_name.to_owned(),
+                                    ));
+                                    continue;
+                                }
+                            };
+
+                        // merge this existing solid with as many of the new solids as possible
+                        for (_, new_solid) in new_solids.iter() {
+                            let punch = new_solid.truck_solid.clone();
+                            // punch.not();
+
+                            let cleared =
+                                solid_and(&existing_solid_to_merge_with.truck_solid, &punch, 0.1);
+
+                            match cleared {
+                                Some(s) => {
+                                    let mut cleared =
+                                        Solid::from_truck_solid(existing_solid_name.to_owned(), s);
+                                    inherit_face_names(
+                                        &mut cleared,
+                                        &[&existing_solid_to_merge_with, new_solid],
                                     );
-
-                                    println!("have cleared");
-
-                                    match cleared {
-                                        Some(s) => {
-                                            println!("Merged with AND");
-                                            // println!("{:?}", s);
-                                            existing_solid_to_merge_with = Solid::from_truck_solid(
-                                                existing_solid_name.to_owned(),
-                                                s,
-                                            );
-                                        }
-                                        None => {
-                                            println!("Failed to merge with AND");
-                                        }
-                                    }
+                                    existing_solid_to_merge_with = cleared;
+                                }
+                                None => {
+                                    warnings.push(CADmiumError::BooleanFailed(format!(
+                                        "cut {} from {}",
+                                        new_solid.name, existing_solid_name
+                                    )));
                                 }
-
-                                realized.solids.insert(
-                                    existing_solid_name.to_owned(),
-                                    existing_solid_to_merge_with,
-                                );
-                                println!("inserted the solid back in")
                             }
                         }
+
+                        realized
+                            .solids
+                            .insert(existing_solid_name.to_owned(), existing_solid_to_merge_with);
                     }
                 }
             }
         }
+        StepData::Hole { hole } => {
+            let (sketch, _split_sketch, _name) = realized
+                .sketches
+                .get(&hole.sketch_id)
+                .ok_or(CADmiumError::StepIDNotFound(hole.sketch_id.to_owned()))?;
+            let plane = realized
+                .planes
+                .get(&sketch.plane_id)
+                .ok_or(CADmiumError::PlaneNotFound(sketch.plane_id.to_owned()))?;
+
+            let targets: Vec<&Solid> = hole
+                .merge_scope
+                .iter()
+                .filter_map(|solid_name| realized.solids.get(solid_name))
+                .collect();
+
+            let punches = hole.punches(plane, sketch, &targets)?;
+
+            // each punch is named after the hole and the sketch point it is drilled at,
+            // so the faces it leaves behind get names like "Hole-0/3/Face1"
+            let punches: Vec<Solid> = punches
+                .into_iter()
+                .zip(hole.point_ids.iter())
+                .map(|(punch, point_id)| {
+                    Solid::from_truck_solid(format!("{}/{}", step.unique_id, point_id), punch)
+                })
+                .collect();
+
+            let locations = hole.locations(sketch).unwrap_or_default();
+            let real_hole = RealHole {
+                locations,
+                axis: hole.direction_vector(plane),
+                diameter: hole.diameter,
+                callout: hole.callout(),
+            };
+
+            for existing_solid_name in hole.merge_scope.iter() {
+                let mut existing_solid = match realized.solids.remove(existing_solid_name) {
+                    Some(s) => s,
+                    None => {
+                        warnings.push(CADmiumError::SolidNotFound(existing_solid_name.to_owned()));
+                        continue;
+                    }
+                };
+
+                for punch in punches.iter() {
+                    // subtracting is intersecting with the inside-out punch
+                    let mut inverted = punch.truck_solid.clone();
+                    inverted.not();
+
+                    match solid_and(&existing_solid.truck_solid, &inverted, 0.1) {
+                        Some(s) => {
+                            let mut drilled =
+                                Solid::from_truck_solid(existing_solid_name.to_owned(), s);
+                            inherit_face_names(&mut drilled, &[&existing_solid, punch]);
+                            existing_solid = drilled;
+                        }
+                        None => {
+                            warnings.push(CADmiumError::BooleanFailed(format!(
+                                "drill {} into {}",
+                                punch.name, existing_solid_name
+                            )));
+                        }
+                    }
+                }
 
-        realized
+                realized
+                    .solids
+                    .insert(existing_solid_name.to_owned(), existing_solid);
+            }
+
+            realized.holes.insert(step.unique_id.to_owned(), real_hole);
+        }
     }
+
+    Ok(())
+}
+
+// Sketches on solid faces get their own plane, named after the sketch
+fn insert_sketch_on_face(realized: &mut Realization, step: &Step, sketch: &Sketch, plane: Plane) {
+    let new_plane_id = format!("derived_plane_for:{}", step.unique_id);
+
+    let rp = RealPlane {
+        plane,
+        width: 90.0,
+        height: 60.0,
+        name: new_plane_id.clone(),
+    };
+    realized.planes.insert(new_plane_id.clone(), rp);
+    let rp = &realized.planes[&new_plane_id];
+
+    let real_sketches = (
+        RealSketch::new(&new_plane_id, &new_plane_id, rp, sketch),
+        RealSketch::new(
+            &new_plane_id,
+            &new_plane_id,
+            rp,
+            &sketch.split_intersections(false),
+        ),
+        step.name.clone(),
+    );
+    realized
+        .sketches
+        .insert(step.unique_id.to_owned(), real_sketches);
+}
+
+// The extrusion step among these steps that produces this solid. Solids are named "<extrusion ID>:<n>"
+fn extrusion_for_solid<'a>(steps: &'a [Step], solid_id: &str) -> Option<&'a Step> {
+    let (step_id, _) = solid_id.rsplit_once(':').unwrap_or((solid_id, ""));
+    steps
+        .iter()
+        .find(|step| step.unique_id == step_id && matches!(step.data, StepData::Extrusion { .. }))
 }
 
 #[cfg(test)]
 pub mod tests {
+    use crate::datum::{EdgeReference, PlaneReference, PointReference};
     use crate::extrusion::Direction;
+    use crate::hole::{HoleDepth, HoleFit, HoleKind};
+    use crate::solid::face_plane;
 
     use super::*;
 
@@ -546,4 +1086,684 @@ pub mod tests {
         assert_eq!(realization.sketches.len(), 1);
         assert_eq!(realization.solids.len(), 1);
     }
+
+    // A 40x40x25 block with a counterbored M6 hole through the middle
+    pub fn block_with_hole() -> Workbench {
+        let mut wb = Workbench::new("Test Workbench");
+        wb.add_sketch_to_plane("Sketch 1", "Plane-0");
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        let ll = s.add_point(0.0, 0.0);
+        let lr = s.add_point(40.0, 0.0);
+        let ul = s.add_point(0.0, 40.0);
+        let ur = s.add_point(40.0, 40.0);
+        s.add_segment(ll, lr);
+        s.add_segment(lr, ur);
+        s.add_segment(ur, ul);
+        s.add_segment(ul, ll);
+
+        let extrusion = Extrusion::new(
+            "Sketch-0".to_owned(),
+            vec![0],
+            25.0,
+            0.0,
+            Direction::Normal,
+            ExtrusionMode::New,
+        );
+        wb.add_extrusion("Ext1", extrusion);
+
+        let sketch_id = wb.add_sketch_to_plane("Hole Sketch", "Plane-0");
+        let s = wb.get_sketch_mut("Hole Sketch").unwrap();
+        let center = s.add_point(20.0, 20.0);
+
+        let mut hole = Hole::from_standard(
+            sketch_id,
+            vec![center],
+            "M6",
+            HoleFit::Normal,
+            HoleKind::Counterbore,
+            HoleDepth::ThroughAll,
+            vec!["Extrusion-0:0".to_owned()],
+        )
+        .unwrap();
+        hole.direction = Direction::Normal;
+        wb.add_hole("Hole 1", hole);
+
+        wb
+    }
+
+    // The ids of a block's sketch, and of the corners and sides of its rectangle going
+    // counterclockwise from the corner nearest the origin
+    pub struct BlockSketch {
+        pub sketch_id: String,
+        pub corners: [u64; 4],
+        pub sides: [u64; 4],
+    }
+
+    // Sketches a width x height rectangle on the front plane with its corner at (x, y), and
+    // extrudes it depth along the plane's normal as a new solid
+    pub fn add_block(
+        wb: &mut Workbench,
+        name: &str,
+        (x, y): (f64, f64),
+        [width, height, depth]: [f64; 3],
+    ) -> BlockSketch {
+        let sketch_name = format!("{} Sketch", name);
+        let sketch_id = wb.add_sketch_to_plane(&sketch_name, "Plane-0");
+        let s = wb.get_sketch_mut(&sketch_name).unwrap();
+        let corners = [
+            s.add_point(x, y),
+            s.add_point(x + width, y),
+            s.add_point(x + width, y + height),
+            s.add_point(x, y + height),
+        ];
+        let sides = [0, 1, 2, 3].map(|i| s.add_segment(corners[i], corners[(i + 1) % 4]));
+
+        let extrusion = Extrusion::new(
+            sketch_id.clone(),
+            vec![0],
+            depth,
+            0.0,
+            Direction::Normal,
+            ExtrusionMode::New,
+        );
+        wb.add_extrusion(name, extrusion);
+
+        BlockSketch {
+            sketch_id,
+            corners,
+            sides,
+        }
+    }
+
+    // A plain 40x20x10 block, "Extrusion-0:0"
+    pub fn block() -> (Workbench, BlockSketch) {
+        let mut wb = Workbench::new("Test Workbench");
+        let sketch = add_block(&mut wb, "Ext1", (0.0, 0.0), [40.0, 20.0, 10.0]);
+        (wb, sketch)
+    }
+
+    #[test]
+    fn make_workbench_with_hole() {
+        let mut wb = block_with_hole();
+
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 1);
+        assert_eq!(realization.holes.len(), 1);
+        assert_eq!(realization.holes["Hole-0"].locations.len(), 1);
+
+        // the hole takes away a 6.6 mm bore through the block and an 11 mm counterbore
+        // 6.5 mm deep, and adds their faces to the block's 6
+        wb.set_suppressed("Hole-0", true).unwrap();
+        let block = wb.realize(1000).solids["Extrusion-0:0"].clone();
+        let drilled = &realization.solids["Extrusion-0:0"];
+        assert_eq!(block.face_names.len(), 6);
+        assert!(drilled.face_names.len() > block.face_names.len());
+
+        let removed =
+            block.mass_properties(1.0, 0.01).volume - drilled.mass_properties(1.0, 0.01).volume;
+        let expected = std::f64::consts::PI * (3.3 * 3.3 * 18.5 + 5.5 * 5.5 * 6.5);
+        assert!((removed - expected).abs() < 0.01 * expected);
+    }
+
+    #[test]
+    fn render_meshes_know_their_faces_and_edges() {
+        let wb = block_with_hole();
+        let solid = &wb.realize(1000).solids["Extrusion-0:0"];
+
+        // every triangle belongs to a face, and every face has triangles
+        assert_eq!(solid.triangle_faces.len() * 3, solid.indices.len());
+        let faces: HashSet<u64> = solid.triangle_faces.iter().cloned().collect();
+        assert_eq!(faces.len(), solid.face_names.len());
+        assert!(faces
+            .iter()
+            .all(|face_id| (*face_id as usize) < faces.len()));
+
+        assert_eq!(solid.edges.len(), solid.edge_names().len());
+        assert!(solid.edges.iter().all(|edge| edge.points.len() >= 2));
+    }
+
+    #[test]
+    fn suppressed_steps_and_their_dependents_are_skipped() {
+        let mut wb = block_with_hole();
+        assert_eq!(wb.step_dependencies(5), vec!["Sketch-0"]);
+        assert_eq!(wb.step_dependencies(7), vec!["Sketch-1", "Extrusion-0"]);
+
+        wb.set_suppressed("Hole-0", true).unwrap();
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 1);
+        assert_eq!(realization.holes.len(), 0);
+
+        // without the block there is nothing to drill, so the hole goes too
+        wb.set_suppressed("Hole-0", false).unwrap();
+        wb.set_suppressed("Extrusion-0", true).unwrap();
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 0);
+        assert_eq!(realization.holes.len(), 0);
+        assert_eq!(realization.sketches.len(), 2);
+
+        wb.set_suppressed("Extrusion-0", false).unwrap();
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 1);
+        assert_eq!(realization.holes.len(), 1);
+    }
+
+    #[test]
+    fn steps_can_be_moved_and_inserted() {
+        let mut wb = block_with_hole();
+        let ids = |wb: &Workbench| -> Vec<String> {
+            wb.history[4..]
+                .iter()
+                .map(|step| step.unique_id.clone())
+                .collect()
+        };
+
+        // the hole needs the block to exist already
+        match wb.move_step("Hole-0", "Extrusion-0", false) {
+            Err(CADmiumError::StepBeforeDependency(step_id, dependency_id)) => {
+                assert_eq!(step_id, "Hole-0");
+                assert_eq!(dependency_id, "Extrusion-0");
+            }
+            other => panic!("expected a dependency error, got {:?}", other),
+        }
+        assert!(wb.move_step("Extrusion-0", "Hole-0", true).is_err());
+        assert!(wb.move_step("Sketch-0", "Plane-0", false).is_err());
+        assert_eq!(ids(&wb), ["Sketch-0", "Extrusion-0", "Sketch-1", "Hole-0"]);
+
+        wb.move_step("Sketch-1", "Extrusion-0", false).unwrap();
+        assert_eq!(ids(&wb), ["Sketch-0", "Sketch-1", "Extrusion-0", "Hole-0"]);
+        let realization = wb.realize(1000);
+        assert_eq!(realization.holes.len(), 1);
+
+        // consecutive insertions keep their order
+        wb.set_rollback(Some("Sketch-0")).unwrap();
+        wb.add_sketch_to_plane("Inserted 1", "Plane-1");
+        wb.add_sketch_to_plane("Inserted 2", "Plane-2");
+        wb.set_rollback(None).unwrap();
+        wb.add_point("Last", Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            ids(&wb),
+            [
+                "Sketch-0",
+                "Sketch-2",
+                "Sketch-3",
+                "Sketch-1",
+                "Extrusion-0",
+                "Hole-0",
+                "Point-1"
+            ]
+        );
+        assert!(wb.set_rollback(Some("Sketch-99")).is_err());
+    }
+
+    #[test]
+    fn rollback_marker_limits_realization() {
+        let mut wb = block_with_hole();
+        wb.set_rollback(Some("Extrusion-0")).unwrap();
+        assert_eq!(wb.rollback_index(), 6);
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 1);
+        assert_eq!(realization.holes.len(), 0);
+        assert_eq!(realization.sketches.len(), 1);
+
+        // the marker is saved with the workbench
+        let wb: Workbench = serde_json::from_str(&wb.json()).unwrap();
+        assert_eq!(wb.rollback_after, Some("Extrusion-0".to_owned()));
+
+        let mut wb = wb;
+        wb.move_rollback(-2);
+        assert_eq!(wb.rollback_after, Some("Plane-2".to_owned()));
+        assert_eq!(wb.realize(1000).sketches.len(), 0);
+
+        wb.move_rollback(100);
+        assert_eq!(wb.rollback_after, None);
+        assert_eq!(wb.realize(1000).holes.len(), 1);
+    }
+
+    #[test]
+    fn deleting_a_step_breaks_or_cascades_to_its_dependents() {
+        let mut wb = block_with_hole();
+        let affected = wb.delete_step("Extrusion-0", false).unwrap();
+        assert_eq!(affected, vec!["Hole-0"]);
+        assert_eq!(wb.history.len(), 7);
+        assert_eq!(
+            wb.dependency_graph().broken["Hole-0"],
+            vec!["Extrusion-0:0"]
+        );
+
+        // the hole is left out rather than panicking on the missing solid
+        let realization = wb.realize(1000);
+        assert_eq!(realization.solids.len(), 0);
+        assert_eq!(realization.holes.len(), 0);
+        assert_eq!(realization.sketches.len(), 2);
+
+        // deleting the plane both sketches sit on takes everything else with it
+        let mut wb = block_with_hole();
+        wb.set_rollback(Some("Hole-0")).unwrap();
+        let affected = wb.delete_step("Plane-0", true).unwrap();
+        assert_eq!(
+            affected,
+            vec!["Sketch-0", "Extrusion-0", "Sketch-1", "Hole-0"]
+        );
+        assert_eq!(wb.history.len(), 3);
+        assert_eq!(wb.rollback_after, Some("Plane-2".to_owned()));
+        assert!(wb.dependency_graph().broken.is_empty());
+    }
+
+    #[test]
+    fn solids_stay_with_their_step_however_it_is_named() {
+        let mut wb = block_with_hole();
+        // the extrusion takes the hole's name, and neither loses track of the block
+        wb.get_step_by_id_mut("Extrusion-0").unwrap().name = "Hole 1".to_owned();
+
+        assert!(wb.dependency_graph().broken.is_empty());
+        assert!(wb.step_dependencies(7).contains(&"Extrusion-0".to_owned()));
+        let realization = wb.realize(1000);
+        assert!(realization.solids.contains_key("Extrusion-0:0"));
+        assert!(realization
+            .statuses
+            .iter()
+            .all(|status| status.state == StepState::Ok));
+    }
+
+    #[test]
+    fn realize_reports_a_status_for_every_step() {
+        let mut wb = block_with_hole();
+        let realization = wb.realize(1000);
+        assert_eq!(realization.statuses.len(), 8);
+        assert!(realization
+            .statuses
+            .iter()
+            .all(|status| status.state == StepState::Ok));
+
+        wb.set_suppressed("Extrusion-0", true).unwrap();
+        wb.add_sketch_to_plane("Unplaced", "");
+        let realization = wb.realize(1000);
+        let state = |step_id: &str| realization.status(step_id).unwrap().state.clone();
+        assert_eq!(state("Extrusion-0"), StepState::Suppressed);
+        assert_eq!(state("Hole-0"), StepState::Skipped);
+        assert_eq!(state("Sketch-2"), StepState::Warning);
+        assert_eq!(
+            realization.status("Hole-0").unwrap().issues[0].error,
+            "DependencyUnavailable"
+        );
+
+        wb.set_suppressed("Extrusion-0", false).unwrap();
+        wb.delete_step("Extrusion-0", false).unwrap();
+        let realization = wb.realize(1000);
+        let status = realization.status("Hole-0").unwrap();
+        assert_eq!(status.state, StepState::Error);
+        assert_eq!(status.issues[0].error, "MissingDependency");
+        assert_eq!(
+            status.issues[0].message,
+            "This step refers to Extrusion-0:0, which doesn't exist before it"
+        );
+    }
+
+    #[test]
+    fn edits_only_redo_the_steps_after_them() {
+        let mut wb = block_with_hole();
+        let steps_realized = |wb: &Workbench| wb.cache.borrow().steps_realized;
+        wb.realize(1000);
+        assert_eq!(steps_realized(&wb), 8);
+        let block_key = wb.cache.borrow().entries[5].key;
+
+        // only the hole is realized again
+        wb.set_suppressed("Hole-0", true).unwrap();
+        let realization = wb.realize(1000);
+        assert_eq!(realization.holes.len(), 0);
+        assert_eq!(realization.solids.len(), 1);
+        assert_eq!(wb.cache.borrow().entries[5].key, block_key);
+        assert_eq!(steps_realized(&wb), 9);
+
+        // realizing fewer steps doesn't throw away the steps after them, or redo any
+        let realization = wb.realize(6);
+        assert_eq!(realization.statuses.len(), 6);
+        assert_eq!(wb.cache.borrow().entries.len(), 8);
+        assert_eq!(steps_realized(&wb), 9);
+
+        // each step keeps only what it changed: the block's sketch doesn't hold the block,
+        // and the hole replaces it
+        wb.set_suppressed("Hole-0", false).unwrap();
+        wb.realize(1000);
+        assert_eq!(steps_realized(&wb), 10);
+        let solids_changed_by = |n: usize| -> Vec<String> {
+            let cache = wb.cache.borrow();
+            let solids = &cache.entries[n].changes.solids;
+            solids.changed.keys().cloned().collect()
+        };
+        assert!(solids_changed_by(4).is_empty());
+        assert_eq!(solids_changed_by(5), vec!["Extrusion-0:0"]);
+        assert!(solids_changed_by(6).is_empty());
+        assert_eq!(solids_changed_by(7), vec!["Extrusion-0:0"]);
+
+        match &mut wb.get_step_by_id_mut("Extrusion-0").unwrap().data {
+            StepData::Extrusion { extrusion } => extrusion.length = 10.0,
+            _ => panic!("Expected an extrusion"),
+        }
+        let cached = wb.realize(1000);
+        assert_ne!(wb.cache.borrow().entries[5].key, block_key);
+
+        wb.clear_cache();
+        let fresh = wb.realize(1000);
+        assert_eq!(cached.holes.len(), 1);
+        assert_eq!(
+            cached.solids["Extrusion-0:0"].face_names,
+            fresh.solids["Extrusion-0:0"].face_names
+        );
+        assert_eq!(
+            cached.solids["Extrusion-0:0"].vertex_points().len(),
+            fresh.solids["Extrusion-0:0"].vertex_points().len()
+        );
+    }
+
+    #[test]
+    fn copies_made_through_json_reuse_the_cache() {
+        let wb = block_with_hole();
+        wb.realize(1000);
+
+        // the copy's sketches list their points in whatever order their maps come up with
+        let json = serde_json::to_string(&wb).unwrap();
+        let copy: Workbench = serde_json::from_str(&json).unwrap();
+        copy.cache.replace(wb.cache.take());
+        copy.realize(1000);
+        assert_eq!(copy.cache.borrow().steps_realized, 8);
+    }
+
+    #[test]
+    fn datum_planes_follow_their_references() {
+        let mut wb = Workbench::new("Test Workbench");
+        let offset_id = wb.add_datum_plane(
+            "Offset",
+            PlaneDefinition::Offset {
+                reference: PlaneReference::Plane {
+                    plane_id: "Plane-2".to_owned(),
+                },
+                distance: 10.0,
+            },
+        );
+
+        let sketch_id = wb.add_sketch_to_plane("Sketch 1", &offset_id);
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        let a = s.add_point(5.0, 0.0);
+        let b = s.add_point(5.0, 5.0);
+
+        let three_points_id = wb.add_datum_plane(
+            "Three Points",
+            PlaneDefinition::ThreePoints {
+                p0: PointReference::Point {
+                    point_id: "Point-0".to_owned(),
+                },
+                p1: PointReference::SketchPoint {
+                    sketch_id: sketch_id.clone(),
+                    point_id: a,
+                },
+                p2: PointReference::SketchPoint {
+                    sketch_id: sketch_id.clone(),
+                    point_id: b,
+                },
+            },
+        );
+
+        let realization = wb.realize(1000);
+        let offset = &realization.planes[&offset_id].plane;
+        assert_eq!(offset.origin.z, 10.0);
+        assert_eq!(offset.tertiary.z, 1.0);
+
+        let three_points = &realization.planes[&three_points_id].plane;
+        let a_3d = &realization.sketches[&sketch_id].0.points[&a];
+        let b_3d = &realization.sketches[&sketch_id].0.points[&b];
+        assert!(three_points.distance_to(a_3d).abs() < 1e-9);
+        assert!(three_points.distance_to(b_3d).abs() < 1e-9);
+        assert!(three_points.tertiary.y.abs() < 1e-9);
+
+        // moving the offset moves everything built on top of it
+        if let StepData::DatumPlane { definition, .. } = &mut wb.history[4].data {
+            *definition = PlaneDefinition::Offset {
+                reference: PlaneReference::Plane {
+                    plane_id: "Plane-2".to_owned(),
+                },
+                distance: 20.0,
+            };
+        }
+        let realization = wb.realize(1000);
+        let sketch_point = &realization.sketches[&sketch_id].0.points[&a];
+        assert_eq!(sketch_point.z, 20.0);
+    }
+
+    #[test]
+    fn datum_axes_and_points() {
+        let mut wb = Workbench::new("Test Workbench");
+
+        // Front and Right meet along the Z axis
+        let z_axis_id = wb.add_axis(
+            "Z Axis",
+            AxisDefinition::PlaneIntersection {
+                plane_a: PlaneReference::Plane {
+                    plane_id: "Plane-0".to_owned(),
+                },
+                plane_b: PlaneReference::Plane {
+                    plane_id: "Plane-1".to_owned(),
+                },
+            },
+        );
+
+        let sketch_id = wb.add_sketch_to_plane("Sketch 1", "Plane-2");
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        let a = s.add_point(2.0, 0.0);
+        let b = s.add_point(2.0, 4.0);
+        let line_id = s.add_segment(a, b);
+        let center = s.add_point(-3.0, 1.0);
+        let circle_id = s.add_circle(center, 1.5);
+
+        let line_axis_id = wb.add_axis(
+            "Line Axis",
+            AxisDefinition::Edge {
+                edge: EdgeReference::SketchLine {
+                    sketch_id: sketch_id.clone(),
+                    line_id,
+                },
+            },
+        );
+        let midpoint_id = wb.add_datum_point(
+            "Midpoint",
+            PointDefinition::Midpoint {
+                edge: EdgeReference::SketchLine {
+                    sketch_id: sketch_id.clone(),
+                    line_id,
+                },
+            },
+        );
+        let center_id = wb.add_datum_point(
+            "Center",
+            PointDefinition::Center {
+                edge: EdgeReference::SketchCircle {
+                    sketch_id: sketch_id.clone(),
+                    circle_id,
+                },
+            },
+        );
+        let pierce_id = wb.add_datum_point(
+            "Pierce",
+            PointDefinition::EdgePlaneIntersection {
+                edge: EdgeReference::Axis {
+                    axis_id: z_axis_id.clone(),
+                },
+                plane: PlaneReference::Plane {
+                    plane_id: "Plane-2".to_owned(),
+                },
+            },
+        );
+
+        let realization = wb.realize(1000);
+
+        let z_axis = &realization.axes[&z_axis_id].axis;
+        assert!((z_axis.direction.z.abs() - 1.0).abs() < 1e-9);
+        assert!(z_axis.distance_to(&Point3::new(0.0, 0.0, 5.0)) < 1e-9);
+
+        let line_axis = &realization.axes[&line_axis_id].axis;
+        assert!((line_axis.direction.y - 1.0).abs() < 1e-9);
+
+        let midpoint = &realization.points[&midpoint_id];
+        assert!(midpoint.distance_to(&Point3::new(2.0, 2.0, 0.0)) < 1e-9);
+
+        let center_point = &realization.points[&center_id];
+        assert!(center_point.distance_to(&Point3::new(-3.0, 1.0, 0.0)) < 1e-6);
+
+        let pierce = &realization.points[&pierce_id];
+        assert!(pierce.distance_to(&Point3::new(0.0, 0.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn sketch_on_one_of_two_parallel_faces() {
+        let mut wb = Workbench::new("Test Workbench");
+
+        // an L-shaped profile makes a stepped block with two faces pointing up
+        wb.add_sketch_to_plane("Sketch 1", "Plane-0");
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        let corners = [
+            (0.0, 0.0),
+            (40.0, 0.0),
+            (40.0, 10.0),
+            (20.0, 10.0),
+            (20.0, 20.0),
+            (0.0, 20.0),
+        ];
+        let ids: Vec<u64> = corners.iter().map(|(x, y)| s.add_point(*x, *y)).collect();
+        for i in 0..ids.len() {
+            s.add_segment(ids[i], ids[(i + 1) % ids.len()]);
+        }
+        let extrusion = Extrusion::new(
+            "Sketch-0".to_owned(),
+            vec![0],
+            25.0,
+            0.0,
+            Direction::Normal,
+            ExtrusionMode::New,
+        );
+        wb.add_extrusion("Ext1", extrusion);
+
+        let realization = wb.realize(1000);
+        let solid = &realization.solids["Extrusion-0:0"];
+        let upward: Vec<(u64, Plane)> = (0..)
+            .map_while(|face_id| solid.get_face(face_id).map(|face| (face_id, face)))
+            .filter_map(|(face_id, face)| face_plane(&face).map(|plane| (face_id, plane)))
+            .filter(|(_, plane)| plane.tertiary.z > 0.99)
+            .collect();
+        assert_eq!(upward.len(), 2);
+        let (step_face_id, _) = upward
+            .iter()
+            .find(|(_, plane)| (plane.origin.z - 10.0).abs() < 1e-9)
+            .unwrap();
+        let step_face_name = solid.face_name(*step_face_id);
+        assert_eq!(
+            step_face_name,
+            format!("Extrusion-0/Side/L{}-{}", ids[2], ids[3])
+        );
+
+        // the old normal-only lookup can't choose, but it no longer panics
+        wb.add_sketch_to_solid_face("Ambiguous", "Extrusion-0:0", Vector3::new(0.0, 0.0, 1.0));
+
+        let sketch_id = wb.add_sketch_to_face(
+            "On The Step",
+            FaceReference {
+                solid_id: "Extrusion-0:0".to_owned(),
+                face_name: step_face_name,
+            },
+        );
+        let s = wb.get_sketch_mut("On The Step").unwrap();
+        let p = s.add_point(1.0, 2.0);
+
+        let realization = wb.realize(1000);
+        assert!(!realization.sketches.contains_key("Sketch-1"));
+        let point = &realization.sketches[&sketch_id].0.points[&p];
+        assert!((point.z - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sketches_on_faces_named_after_their_solid_depend_on_its_extrusion() {
+        let mut wb = block_with_hole();
+        let sketch_id = wb.add_sketch_to_face(
+            "On A Fallback Face",
+            FaceReference {
+                solid_id: "Extrusion-0:0".to_owned(),
+                face_name: "Extrusion-0:0/Face3".to_owned(),
+            },
+        );
+        let index = wb.step_index(&sketch_id).unwrap();
+
+        assert!(wb.missing_dependencies(index).is_empty());
+        assert_eq!(wb.step_dependencies(index), vec!["Extrusion-0"]);
+        assert!(wb.dependency_graph().broken.is_empty());
+    }
+
+    #[test]
+    fn extrusions_keep_their_faces_when_the_sketch_changes() {
+        let mut wb = Workbench::new("Test Workbench");
+        wb.add_sketch_to_plane("Sketch 1", "Plane-2");
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        let mut rectangle = |x0: f64, x1: f64| -> Vec<u64> {
+            let ids = vec![
+                s.add_point(x0, 0.0),
+                s.add_point(x1, 0.0),
+                s.add_point(x1, 10.0),
+                s.add_point(x0, 10.0),
+            ];
+            for i in 0..4 {
+                s.add_segment(ids[i], ids[(i + 1) % 4]);
+            }
+            ids
+        };
+        let small = rectangle(0.0, 10.0);
+        let _big = rectangle(20.0, 50.0);
+
+        // faces are sorted smallest first, so the big rectangle is face 1
+        let extrusion = Extrusion::new(
+            "Sketch-0".to_owned(),
+            vec![1],
+            5.0,
+            0.0,
+            Direction::Normal,
+            ExtrusionMode::New,
+        );
+        wb.add_extrusion("Ext1", extrusion);
+        let realization = wb.realize(1000);
+        let end_cap = realization.solids["Extrusion-0:0"]
+            .face_names
+            .iter()
+            .find(|name| name.starts_with("Extrusion-0/End/"))
+            .unwrap()
+            .clone();
+
+        // now the small rectangle becomes the biggest, which re-sorts the faces
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        s.points.get_mut(&small[1]).unwrap().x = 15.0;
+        s.points.get_mut(&small[2]).unwrap().x = 15.0;
+        s.points.get_mut(&small[0]).unwrap().x = -60.0;
+        s.points.get_mut(&small[3]).unwrap().x = -60.0;
+
+        let realization = wb.realize(1000);
+        let solid = &realization.solids["Extrusion-0:0"];
+        assert!(solid.vertices.iter().all(|v| v.x > 19.9));
+        assert!(solid.face_index(&end_cap).is_some());
+
+        // vertices are named by the faces that meet there, and resolve back to a point
+        let vertex_name = solid
+            .vertex_names()
+            .into_iter()
+            .find(|name| name.contains(&end_cap))
+            .unwrap();
+        let corner = PointReference::Vertex {
+            solid_id: "Extrusion-0:0".to_owned(),
+            vertex_name,
+        }
+        .resolve(&realization)
+        .unwrap();
+        assert!((corner.z - 5.0).abs() < 1e-9);
+
+        // once the face is gone for good, the extrusion fails instead of picking another
+        let s = wb.get_sketch_mut("Sketch 1").unwrap();
+        s.line_segments.clear();
+        let realization = wb.realize(1000);
+        assert!(!realization.solids.contains_key("Extrusion-0:0"));
+    }
 }
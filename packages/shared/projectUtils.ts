@@ -25,21 +25,27 @@ import type {
   WithTarget,
   WorkBench,
 } from "./types"
-import type {Realization as WasmRealization, Message} from "cadmium"
+import type {Realization as WasmRealization, Message, MessageResult} from "cadmium"
 import {
+  isBatch,
   isDeleteArcs,
   isDeleteCircles,
   isDeleteLines,
+  isDeleteStep,
+  isDeleteWorkbench,
   isNewCircleBetweenPoints,
   isNewExtrusion,
   isNewLineOnSketch,
   isNewPointOnSketch2,
   isNewRectangleBetweenPoints,
   isNewSketchOnPlane,
+  isNewWorkbench,
   isRenameProject,
   isRenameStep,
   isRenameWorkbench,
+  isSetActiveWorkbench,
   isSetSketchPlane,
+  isUnitMessage,
   isUpdateExtrusion,
 } from "./typeGuards"
 // import { isDevelopment } from "../+layout"
@@ -70,12 +76,14 @@ export function arraysEqual(a: any[], b: any[]) {
   return true
 }
 
-function sendWasmMessage(message: Message) {
+function sendWasmMessage(message: Message): MessageResult {
   let wp = get(wasmProject)
   log("[sendWasmMessage] sending message:", message)
-  let result = wp.send_message(message)
+  let result: MessageResult = wp.send_message(message)
   log("[sendWasmMessage] reply:", result)
 
+  if ("error" in result) console.error("ERROR [projectUtils.ts sendWasmMessage]", result.error.kind, result.error.message, "message:", message)
+
   messageHistory.update((history: MessageHistory[]) => {
     log("[sendWasmMessage] [messageHistory.update] update:", {message, result})
     return [...history, {message, result}]
@@ -83,6 +91,16 @@ function sendWasmMessage(message: Message) {
   return result
 }
 
+// the id of the step a message created, if it worked
+function createdStepId(reply: MessageResult): string | undefined {
+  if ("success" in reply && reply.success.type === "StepCreated") return reply.success.id
+}
+
+// the id of the sketch point, line or circle a message created, if it worked
+function createdEntityId(reply: MessageResult): number | undefined {
+  if ("success" in reply && reply.success.type === "SketchEntityCreated") return reply.success.id
+}
+
 export function updateExtrusion(extrusionId: string, sketchId: string, length: number, faceIds: string[]) {
   const message: Message = {
     UpdateExtrusion: {
@@ -135,8 +153,9 @@ export function newSketchOnPlane() {
     },
   }
   checkWasmMessage(message)
-  sendWasmMessage(message)
+  const reply = sendWasmMessage(message)
   workbenchIsStale.set(true)
+  return createdStepId(reply)
 }
 
 export function newExtrusion() {
@@ -171,8 +190,9 @@ export function newExtrusion() {
   // todo: maybe change isNewExtrusion? although with the rust api it is possible to send an array of faceids so we ought to check them...
   // probably best to alter isNewExtrusion to allow an empty array or a number[]
   checkWasmMessage(message)
-  sendWasmMessage(message)
+  const reply = sendWasmMessage(message)
   workbenchIsStale.set(true)
+  return createdStepId(reply)
 }
 
 export function deleteEntities(sketchIdx: string, selection: Entity[]) {
@@ -252,8 +272,9 @@ export function addRectangleBetweenPoints(sketchIdx: string, point1: number, poi
     },
   }
   checkWasmMessage(message)
-  sendWasmMessage(message)
+  const reply = sendWasmMessage(message)
   workbenchIsStale.set(true)
+  if ("success" in reply && reply.success.type === "RectangleCreated") return {pointIds: reply.success.point_ids, lineIds: reply.success.line_ids}
 }
 
 export function addCircleBetweenPoints(sketchIdx: string, point1: string, point2: string) {
@@ -273,8 +294,9 @@ export function addCircleBetweenPoints(sketchIdx: string, point1: string, point2
     },
   }
   checkWasmMessage(message)
-  sendWasmMessage(message)
+  const reply = sendWasmMessage(message)
   workbenchIsStale.set(true)
+  return createdEntityId(reply)
 }
 
 export function addLineToSketch(sketchIdx: string, point1: number, point2: number) {
@@ -287,8 +309,9 @@ export function addLineToSketch(sketchIdx: string, point1: number, point2: numbe
     },
   }
   checkWasmMessage(message)
-  sendWasmMessage(message)
+  const reply = sendWasmMessage(message)
   workbenchIsStale.set(true)
+  return createdEntityId(reply)
 }
 
 export function addPointToSketch(sketchIdx: string, point: Vector2Like, hidden: boolean) {
@@ -306,10 +329,8 @@ export function addPointToSketch(sketchIdx: string, point: Vector2Like, hidden:
   const reply = sendWasmMessage(message)
   // log("[addPointToSketch sendWasmMessage]", "message:", message, "reply:", reply)
 
-  if (!reply.success) console.error("ERROR [projectUtils.ts addPointToSketch sendWasmMessage]", "message:", message, "reply:", reply)
-
   workbenchIsStale.set(true)
-  return JSON.parse(reply.success).id
+  return createdEntityId(reply)
 }
 
 export function renameStep(stepIdx: number, newName: string): void {
@@ -505,6 +526,12 @@ function notEmpty(array: unknown[]): boolean {
 }
 
 function checkWasmMessage(message: Message, abort = true, logError = true): boolean {
+  // messages without fields, like "Undo", are sent as just their name
+  if (typeof message === "string") {
+    if (isUnitMessage(message)) return true
+    console.error("[projectUtils.ts] [checkWasmMessage]", "messageType not found:", message)
+    return false
+  }
   const key = Object.keys(message)[0]
   const command = message[key as keyof Message]
   if (!command) {
@@ -612,6 +639,41 @@ function checkWasmMessage(message: Message, abort = true, logError = true): bool
       }
       return true
 
+    case "NewWorkbench":
+      if (!isNewWorkbench(command)) {
+        logOrAbort()
+        return false
+      }
+      return true
+
+    case "SetActiveWorkbench":
+      if (!isSetActiveWorkbench(command)) {
+        logOrAbort()
+        return false
+      }
+      return true
+
+    case "DeleteWorkbench":
+      if (!isDeleteWorkbench(command)) {
+        logOrAbort()
+        return false
+      }
+      return true
+
+    case "DeleteStep":
+      if (!isDeleteStep(command)) {
+        logOrAbort()
+        return false
+      }
+      return true
+
+    case "Batch":
+      if (!isBatch(command)) {
+        logOrAbort()
+        return false
+      }
+      return true
+
     default:
       console.error("[projectUtils.ts] [checkWasmMessage]", "messageType typeGuard not implemented:", key)
       return false
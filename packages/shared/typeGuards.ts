@@ -84,6 +84,12 @@ import type {
   MessageHistory,
   RenameWorkbench,
   RenameProject,
+  NewWorkbench,
+  SetActiveWorkbench,
+  DeleteWorkbench,
+  DeleteStep,
+  Batch,
+  UnitMessage,
 } from "./types"
 import {Vector2} from "three"
 import {Vector3} from "three"
@@ -907,7 +913,10 @@ export function isSetSketchPlane(obj: unknown): obj is SetSketchPlane {
     ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") &&
     typeof typedObj["workbench_id"] === "number" &&
     typeof typedObj["sketch_id"] === "string" &&
-    typeof typedObj["plane_id"] === "string"
+    typeof typedObj["plane_id"] === "string" &&
+    (typedObj["face"] === undefined ||
+      typedObj["face"] === null ||
+      (typeof typedObj["face"] === "object" && typeof typedObj["face"]["solid_id"] === "string" && typeof typedObj["face"]["face_name"] === "string"))
   )
 }
 
@@ -1038,9 +1047,51 @@ export function isRenameProject(obj: unknown): obj is RenameProject {
   return ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && typeof typedObj["new_name"] === "string"
 }
 
+export function isNewWorkbench(obj: unknown): obj is NewWorkbench {
+  const typedObj = obj as NewWorkbench
+  return ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && typeof typedObj["name"] === "string"
+}
+
+export function isSetActiveWorkbench(obj: unknown): obj is SetActiveWorkbench {
+  const typedObj = obj as SetActiveWorkbench
+  return ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && typeof typedObj["workbench_id"] === "number"
+}
+
+export function isDeleteWorkbench(obj: unknown): obj is DeleteWorkbench {
+  const typedObj = obj as DeleteWorkbench
+  return ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && typeof typedObj["workbench_id"] === "number"
+}
+
+export function isDeleteStep(obj: unknown): obj is DeleteStep {
+  const typedObj = obj as DeleteStep
+  return (
+    ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") &&
+    typeof typedObj["workbench_id"] === "number" &&
+    typeof typedObj["step_name"] === "string" &&
+    (typedObj["cascade"] === undefined || typeof typedObj["cascade"] === "boolean") &&
+    (typedObj["dry_run"] === undefined || typeof typedObj["dry_run"] === "boolean")
+  )
+}
+
+// the messages in a batch can refer to earlier results with { "$ref": n, "field": "id" },
+// so only their shape as a whole is checked here
+export function isBatch(obj: unknown): obj is Batch {
+  const typedObj = obj as Batch
+  return (
+    ((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") &&
+    Array.isArray(typedObj["messages"]) &&
+    typedObj["messages"].every((e: any) => typeof e === "string" || (e !== null && typeof e === "object"))
+  )
+}
+
+export function isUnitMessage(obj: unknown): obj is UnitMessage {
+  return obj === "Undo" || obj === "Redo" || obj === "BeginUndoGroup" || obj === "EndUndoGroup"
+}
+
 export function isMessage(obj: unknown): obj is Message {
   const typedObj = obj as Message
   return (
+    (isUnitMessage(typedObj) as boolean) ||
     (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isUpdateExtrusion(typedObj["UpdateExtrusion"]) as boolean)) ||
     (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isSetSketchPlane(typedObj["SetSketchPlane"]) as boolean)) ||
     (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") &&
@@ -1058,7 +1109,13 @@ export function isMessage(obj: unknown): obj is Message {
       (isNewPointOnSketch2(typedObj["NewPointOnSketch2"]) as boolean)) ||
     (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isRenameStep(typedObj["RenameStep"]) as boolean)) ||
     (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isRenameWorkbench(typedObj["RenameWorkbench"]) as boolean)) ||
-    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isRenameProject(typedObj["RenameProject"]) as boolean))
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isRenameProject(typedObj["RenameProject"]) as boolean)) ||
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isNewWorkbench(typedObj["NewWorkbench"]) as boolean)) ||
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") &&
+      (isSetActiveWorkbench(typedObj["SetActiveWorkbench"]) as boolean)) ||
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isDeleteWorkbench(typedObj["DeleteWorkbench"]) as boolean)) ||
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isDeleteStep(typedObj["DeleteStep"]) as boolean)) ||
+    (((typedObj !== null && typeof typedObj === "object") || typeof typedObj === "function") && (isBatch(typedObj["Batch"]) as boolean))
   )
 }
 
@@ -1,4 +1,4 @@
-import { Message, MessageResult } from "cadmium"
+import { FaceReference, Message, MessageResult } from "cadmium"
 import type { Vector2, Vector3, Vector2Like, Vector3Like } from "three"
 
 interface IDictionary<TValue> {
@@ -237,6 +237,7 @@ interface SetSketchPlane {
   workbench_id: number
   sketch_id: string
   plane_id: string
+  face?: FaceReference | null
 }
 
 interface NewSketchOnPlane {
@@ -313,6 +314,32 @@ interface RenameWorkbench {
   new_name: string
 }
 
+interface NewWorkbench {
+  name: string
+}
+
+interface SetActiveWorkbench {
+  workbench_id: number
+}
+
+interface DeleteWorkbench {
+  workbench_id: number
+}
+
+interface DeleteStep {
+  workbench_id: number
+  step_name: string
+  cascade?: boolean
+  dry_run?: boolean
+}
+
+interface Batch {
+  messages: any[]
+}
+
+// messages without fields are sent as just their name
+type UnitMessage = "Undo" | "Redo" | "BeginUndoGroup" | "EndUndoGroup"
+
 interface MessageHistory {
   message: Message
   result: MessageResult